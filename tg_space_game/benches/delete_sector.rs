@@ -0,0 +1,118 @@
+//! Compares `delete_sector` with the per-sector recursion it replaced.
+//!
+//! Runs against `DATABASE_URL`, or an in-memory SQLite database if it isn't
+//! set. Round trips are what the set-based deletion saves, so Postgres
+//! shows the difference best:
+//!
+//!     DATABASE_URL=postgres://localhost/galaxy cargo bench --bench delete_sector
+//!
+//! The large galaxy has more objects than Postgres takes bind parameters
+//! in one query, so it can only be deleted without sending ids back.
+
+extern crate dotenv;
+extern crate tg_space_game;
+
+use std::env;
+use std::time::{Duration, Instant};
+use tg_space_game::config::GenerationConfig;
+use tg_space_game::galaxy_objects::*;
+use tg_space_game::models::StarSector;
+use tg_space_game::store::GalaxyStore;
+use tg_space_game::{GalaxyConnection, GalaxyResult};
+
+const STARS: f32 = 1000f32;
+/// Stars of a sector that holds them directly with default config
+const LEAF_STARS: f32 = 99f32;
+/// Sectors of stars in the large galaxy, about 40k objects
+const LARGE_SECTORS: i64 = 400;
+const RADIUS: f32 = 1000f32;
+const ROUNDS: i64 = 3;
+
+/// Galaxy with all futures fulfilled down to the star systems
+fn fulfilled_galaxy(conn: &GalaxyConnection, seed: i64) -> StarSector {
+    let sector = generate_star_sector(conn, STARS, RADIUS, None, seed, &GenerationConfig::default())
+        .expect("Error generating galaxy");
+    let mut futures = get_star_sector_children_futures(conn, &sector).expect("Error loading futures");
+    while let Some(future) = futures.pop() {
+        let child = fulfill_star_sector_future(conn, future.id).expect("Error fulfilling future");
+        futures.extend(get_star_sector_children_futures(conn, &child).expect("Error loading futures"));
+    }
+    sector
+}
+
+/// Galaxy of many sectors of stars, generated right inside of the root
+/// instead of fulfilling futures, which would take much longer
+fn large_galaxy(conn: &GalaxyConnection, seed: i64) -> StarSector {
+    let config = GenerationConfig::default();
+    let root = generate_star_sector(conn, LEAF_STARS, RADIUS, None, seed, &config).expect("Error generating galaxy");
+    for i in 0..LARGE_SECTORS {
+        generate_star_sector(conn, LEAF_STARS, RADIUS / 10f32, Some(root.id), seed + i + 1, &config)
+            .expect("Error generating sector");
+    }
+    root
+}
+
+/// Deletion as it was done before: a few queries for every sector
+fn delete_sector_recursively(conn: &GalaxyConnection, sector_id: i32) -> GalaxyResult<()> {
+    conn.transaction(|| {
+        let futures = conn.get_child_futures(sector_id)?.iter().map(|f| f.id).collect::<Vec<_>>();
+        conn.delete_links_for_objects(&futures)?;
+        conn.delete_futures(&futures)?;
+        conn.delete_galaxy_objects(&futures)?;
+
+        let systems = conn.get_child_systems(sector_id)?.iter().map(|s| s.id).collect::<Vec<_>>();
+        conn.delete_links_for_objects(&systems)?;
+        conn.delete_systems(&systems)?;
+        conn.delete_galaxy_objects(&systems)?;
+
+        for child in conn.get_child_sectors(sector_id)? {
+            delete_sector_recursively(conn, child.id)?;
+        }
+        conn.delete_links_for_objects(&[sector_id])?;
+        conn.delete_sectors(&[sector_id])?;
+        conn.delete_galaxy_objects(&[sector_id])?;
+        Ok(())
+    })
+}
+
+fn measure<F: Fn(&GalaxyConnection, i32)>(conn: &GalaxyConnection, name: &str, delete: F) {
+    let mut total = Duration::from_secs(0);
+    let mut sectors = 0;
+    for seed in 0..ROUNDS {
+        let sector = fulfilled_galaxy(conn, seed);
+        sectors += get_sector_stats(conn, sector.id).expect("Error counting sectors").sectors;
+        let started = Instant::now();
+        delete(conn, sector.id);
+        total += started.elapsed();
+    }
+    println!(
+        "{:<12} {:>6} sectors in {:>8.1} ms per galaxy",
+        name,
+        sectors as i64 / ROUNDS,
+        total.as_secs_f64() * 1000.0 / ROUNDS as f64
+    );
+}
+
+fn main() {
+    dotenv::dotenv().ok();
+    let url = env::var("DATABASE_URL").unwrap_or_else(|_| "sqlite://:memory:".to_string());
+    let conn = GalaxyConnection::establish(&url).expect("Error connecting to database");
+    conn.run_migrations(&mut std::io::sink()).expect("Error running migrations");
+
+    measure(&conn, "recursive", |conn, id| {
+        delete_sector_recursively(conn, id).expect("Error deleting sector")
+    });
+    measure(&conn, "set-based", |conn, id| {
+        delete_sector(conn, id).expect("Error deleting sector");
+    });
+
+    let sector = large_galaxy(&conn, ROUNDS);
+    let started = Instant::now();
+    let deleted = delete_sector(&conn, sector.id).expect("Error deleting large galaxy");
+    println!(
+        "{:<12} {:>6} objects in {:>8.1} ms",
+        "large",
+        deleted.galaxy_objects,
+        started.elapsed().as_secs_f64() * 1000.0
+    );
+}
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE star_sector_futures DROP COLUMN seed;
+ALTER TABLE star_sectors DROP COLUMN seed;
\ No newline at end of file
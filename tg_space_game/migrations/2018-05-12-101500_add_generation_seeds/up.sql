@@ -0,0 +1,5 @@
+-- Seeds for deterministic generation
+ALTER TABLE star_sectors ADD COLUMN seed BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE star_sectors ALTER COLUMN seed DROP DEFAULT;
+ALTER TABLE star_sector_futures ADD COLUMN seed BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE star_sector_futures ALTER COLUMN seed DROP DEFAULT;
\ No newline at end of file
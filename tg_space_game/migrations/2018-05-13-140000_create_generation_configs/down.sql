@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE star_sectors DROP COLUMN config_id;
+DROP TABLE generation_configs;
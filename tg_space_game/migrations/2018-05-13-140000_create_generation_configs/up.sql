@@ -0,0 +1,18 @@
+-- Generation parameters, saved with every galaxy
+CREATE TABLE generation_configs (
+    id SERIAL PRIMARY KEY,
+    branching_factor INTEGER NOT NULL CHECK (branching_factor > 1),
+    leaf_threshold REAL NOT NULL CHECK (leaf_threshold > 0),
+    link_density REAL NOT NULL CHECK (link_density >= 0),
+    weight_distribution VARCHAR NOT NULL,
+    radius_scaling REAL NOT NULL
+);
+
+-- Existing galaxies were generated with hardcoded defaults
+ALTER TABLE star_sectors ADD COLUMN config_id INTEGER REFERENCES generation_configs (id);
+INSERT INTO generation_configs (branching_factor, leaf_threshold, link_density, weight_distribution, radius_scaling)
+    SELECT 10, 10.0, 4.0, 'exponential', 1.0 / 3.0
+    WHERE EXISTS (SELECT 1 FROM star_sectors);
+UPDATE star_sectors
+    SET config_id = (SELECT MIN(id) FROM generation_configs);
+ALTER TABLE star_sectors ALTER COLUMN config_id SET NOT NULL;
@@ -0,0 +1,3 @@
+-- Style of generated star system names
+ALTER TABLE generation_configs ADD COLUMN name_style VARCHAR NOT NULL DEFAULT 'markov';
+ALTER TABLE generation_configs ALTER COLUMN name_style DROP DEFAULT;
@@ -0,0 +1,15 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE star_systems
+    DROP COLUMN x,
+    DROP COLUMN y,
+    DROP COLUMN z,
+    DROP COLUMN radius;
+ALTER TABLE star_sector_futures
+    DROP COLUMN x,
+    DROP COLUMN y,
+    DROP COLUMN z;
+ALTER TABLE star_sectors
+    DROP COLUMN x,
+    DROP COLUMN y,
+    DROP COLUMN z,
+    DROP COLUMN radius;
@@ -0,0 +1,31 @@
+-- Absolute position in galaxy and bounding radius of every object
+ALTER TABLE star_sectors
+    ADD COLUMN x REAL NOT NULL DEFAULT 0,
+    ADD COLUMN y REAL NOT NULL DEFAULT 0,
+    ADD COLUMN z REAL NOT NULL DEFAULT 0,
+    ADD COLUMN radius REAL NOT NULL DEFAULT 0;
+ALTER TABLE star_sectors
+    ALTER COLUMN x DROP DEFAULT,
+    ALTER COLUMN y DROP DEFAULT,
+    ALTER COLUMN z DROP DEFAULT,
+    ALTER COLUMN radius DROP DEFAULT;
+
+ALTER TABLE star_sector_futures
+    ADD COLUMN x REAL NOT NULL DEFAULT 0,
+    ADD COLUMN y REAL NOT NULL DEFAULT 0,
+    ADD COLUMN z REAL NOT NULL DEFAULT 0;
+ALTER TABLE star_sector_futures
+    ALTER COLUMN x DROP DEFAULT,
+    ALTER COLUMN y DROP DEFAULT,
+    ALTER COLUMN z DROP DEFAULT;
+
+ALTER TABLE star_systems
+    ADD COLUMN x REAL NOT NULL DEFAULT 0,
+    ADD COLUMN y REAL NOT NULL DEFAULT 0,
+    ADD COLUMN z REAL NOT NULL DEFAULT 0,
+    ADD COLUMN radius REAL NOT NULL DEFAULT 0;
+ALTER TABLE star_systems
+    ALTER COLUMN x DROP DEFAULT,
+    ALTER COLUMN y DROP DEFAULT,
+    ALTER COLUMN z DROP DEFAULT,
+    ALTER COLUMN radius DROP DEFAULT;
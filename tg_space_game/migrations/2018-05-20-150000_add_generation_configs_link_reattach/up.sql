@@ -0,0 +1,3 @@
+-- How links are re-attached when a future is fulfilled
+ALTER TABLE generation_configs ADD COLUMN link_reattach VARCHAR NOT NULL DEFAULT 'nearest';
+ALTER TABLE generation_configs ALTER COLUMN link_reattach DROP DEFAULT;
@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE star_links
+    DROP COLUMN length,
+    DROP COLUMN link_type,
+    DROP COLUMN cost;
@@ -0,0 +1,22 @@
+-- Length, type and traversal cost of every link
+ALTER TABLE star_links
+    ADD COLUMN length REAL NOT NULL DEFAULT 0,
+    ADD COLUMN link_type VARCHAR NOT NULL DEFAULT 'hyperlane',
+    ADD COLUMN cost REAL NOT NULL DEFAULT 0;
+
+-- Existing links become hyperlanes as long as the distance between their ends
+WITH ends AS (
+    SELECT id, x, y, z FROM star_systems
+    UNION ALL
+    SELECT id, x, y, z FROM star_sector_futures
+)
+UPDATE star_links
+SET length = sqrt(power(a.x - b.x, 2) + power(a.y - b.y, 2) + power(a.z - b.z, 2))
+FROM ends a, ends b
+WHERE a.id = star_links.a_id AND b.id = star_links.b_id;
+UPDATE star_links SET cost = length;
+
+ALTER TABLE star_links
+    ALTER COLUMN length DROP DEFAULT,
+    ALTER COLUMN link_type DROP DEFAULT,
+    ALTER COLUMN cost DROP DEFAULT;
@@ -0,0 +1,3 @@
+-- How children of a sector are linked to each other
+ALTER TABLE generation_configs ADD COLUMN link_strategy VARCHAR NOT NULL DEFAULT 'random';
+ALTER TABLE generation_configs ALTER COLUMN link_strategy DROP DEFAULT;
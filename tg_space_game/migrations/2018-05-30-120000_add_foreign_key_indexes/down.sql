@@ -0,0 +1,9 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX jumps_to_id;
+DROP INDEX jumps_from_id;
+DROP INDEX players_location_id;
+DROP INDEX star_links_b_id;
+DROP INDEX star_links_a_id;
+DROP INDEX star_systems_sector_id;
+DROP INDEX star_sector_futures_parent_id;
+DROP INDEX star_sectors_parent_id;
@@ -0,0 +1,3 @@
+-- Amount of stars that sector was filled with, so that it can be turned
+-- back into a future. Sectors fulfilled before don't know it.
+ALTER TABLE star_sectors ADD COLUMN stars REAL;
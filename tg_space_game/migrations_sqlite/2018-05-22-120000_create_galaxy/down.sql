@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE star_links;
+DROP TABLE star_systems;
+DROP TABLE star_sector_futures;
+DROP TABLE star_sectors;
+DROP TABLE generation_configs;
+DROP TABLE galaxy_objects;
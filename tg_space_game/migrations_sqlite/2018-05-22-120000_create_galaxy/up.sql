@@ -0,0 +1,66 @@
+-- Whole galaxy schema, matching Postgres migrations up to now.
+-- Galaxy object types are stored as text.
+CREATE TABLE galaxy_objects (
+  id INTEGER PRIMARY KEY NOT NULL,
+  obj_type TEXT NOT NULL CHECK (obj_type IN ('system', 'sector', 'sector_future')),
+  UNIQUE (id, obj_type)
+);
+
+CREATE TABLE generation_configs (
+  id INTEGER PRIMARY KEY NOT NULL,
+  branching_factor INTEGER NOT NULL CHECK (branching_factor > 1),
+  leaf_threshold REAL NOT NULL CHECK (leaf_threshold > 0),
+  link_density REAL NOT NULL CHECK (link_density >= 0),
+  weight_distribution TEXT NOT NULL,
+  radius_scaling REAL NOT NULL,
+  name_style TEXT NOT NULL,
+  link_reattach TEXT NOT NULL
+);
+
+CREATE TABLE star_sectors (
+  id INTEGER PRIMARY KEY NOT NULL,
+  galaxy_object_type TEXT NOT NULL DEFAULT 'sector' CHECK (galaxy_object_type = 'sector'),
+  parent_id INTEGER REFERENCES star_sectors (id),
+  seed BIGINT NOT NULL,
+  config_id INTEGER NOT NULL REFERENCES generation_configs (id),
+  x REAL NOT NULL,
+  y REAL NOT NULL,
+  z REAL NOT NULL,
+  radius REAL NOT NULL,
+  FOREIGN KEY (id, galaxy_object_type) REFERENCES galaxy_objects (id, obj_type)
+);
+
+CREATE TABLE star_sector_futures (
+  id INTEGER PRIMARY KEY NOT NULL,
+  galaxy_object_type TEXT NOT NULL DEFAULT 'sector_future' CHECK (galaxy_object_type = 'sector_future'),
+  parent_id INTEGER NOT NULL REFERENCES star_sectors (id),
+  radius REAL NOT NULL,
+  stars REAL NOT NULL,
+  seed BIGINT NOT NULL,
+  x REAL NOT NULL,
+  y REAL NOT NULL,
+  z REAL NOT NULL,
+  FOREIGN KEY (id, galaxy_object_type) REFERENCES galaxy_objects (id, obj_type)
+);
+
+CREATE TABLE star_systems (
+  id INTEGER PRIMARY KEY NOT NULL,
+  galaxy_object_type TEXT NOT NULL DEFAULT 'system' CHECK (galaxy_object_type = 'system'),
+  name TEXT NOT NULL,
+  sector_id INTEGER NOT NULL REFERENCES star_sectors (id),
+  x REAL NOT NULL,
+  y REAL NOT NULL,
+  z REAL NOT NULL,
+  radius REAL NOT NULL,
+  FOREIGN KEY (id, galaxy_object_type) REFERENCES galaxy_objects (id, obj_type)
+);
+
+CREATE TABLE star_links (
+  id INTEGER PRIMARY KEY NOT NULL,
+  a_id INTEGER NOT NULL,
+  a_obj_type TEXT NOT NULL CHECK (a_obj_type IN ('system', 'sector_future')),
+  b_id INTEGER NOT NULL,
+  b_obj_type TEXT NOT NULL CHECK (b_obj_type IN ('system', 'sector_future')),
+  FOREIGN KEY (a_id, a_obj_type) REFERENCES galaxy_objects (id, obj_type),
+  FOREIGN KEY (b_id, b_obj_type) REFERENCES galaxy_objects (id, obj_type)
+);
@@ -0,0 +1,7 @@
+-- Players are Telegram users, located somewhere in the galaxy
+CREATE TABLE players (
+  telegram_id BIGINT PRIMARY KEY NOT NULL,
+  name TEXT NOT NULL,
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  location_id INTEGER REFERENCES galaxy_objects (id) ON DELETE SET NULL
+);
@@ -0,0 +1,10 @@
+-- Jumps of players that are in flight between two galaxy objects
+CREATE TABLE jumps (
+  telegram_id BIGINT PRIMARY KEY NOT NULL REFERENCES players (telegram_id) ON DELETE CASCADE,
+  from_id INTEGER NOT NULL REFERENCES galaxy_objects (id) ON DELETE CASCADE,
+  to_id INTEGER NOT NULL REFERENCES galaxy_objects (id) ON DELETE CASCADE,
+  departed_at TIMESTAMP NOT NULL,
+  arrives_at TIMESTAMP NOT NULL CHECK (arrives_at >= departed_at)
+);
+
+CREATE INDEX jumps_arrives_at ON jumps (arrives_at);
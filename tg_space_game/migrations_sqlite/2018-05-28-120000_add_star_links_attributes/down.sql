@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE star_links DROP COLUMN cost;
+ALTER TABLE star_links DROP COLUMN link_type;
+ALTER TABLE star_links DROP COLUMN length;
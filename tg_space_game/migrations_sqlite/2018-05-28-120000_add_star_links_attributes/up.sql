@@ -0,0 +1,14 @@
+-- Length, type and traversal cost of every link
+ALTER TABLE star_links ADD COLUMN length REAL NOT NULL DEFAULT 0;
+ALTER TABLE star_links ADD COLUMN link_type TEXT NOT NULL DEFAULT 'hyperlane';
+ALTER TABLE star_links ADD COLUMN cost REAL NOT NULL DEFAULT 0;
+
+-- Existing links become hyperlanes as long as the distance between their ends
+UPDATE star_links
+SET length = COALESCE((
+    SELECT sqrt((a.x - b.x) * (a.x - b.x) + (a.y - b.y) * (a.y - b.y) + (a.z - b.z) * (a.z - b.z))
+    FROM (SELECT id, x, y, z FROM star_systems UNION ALL SELECT id, x, y, z FROM star_sector_futures) a,
+         (SELECT id, x, y, z FROM star_systems UNION ALL SELECT id, x, y, z FROM star_sector_futures) b
+    WHERE a.id = star_links.a_id AND b.id = star_links.b_id
+), 0);
+UPDATE star_links SET cost = length;
@@ -0,0 +1,2 @@
+-- How children of a sector are linked to each other
+ALTER TABLE generation_configs ADD COLUMN link_strategy TEXT NOT NULL DEFAULT 'random';
@@ -0,0 +1,10 @@
+-- Children and links are looked up by the objects they belong to, and
+-- deleting a referenced row checks these columns for references
+CREATE INDEX star_sectors_parent_id ON star_sectors (parent_id);
+CREATE INDEX star_sector_futures_parent_id ON star_sector_futures (parent_id);
+CREATE INDEX star_systems_sector_id ON star_systems (sector_id);
+CREATE INDEX star_links_a_id ON star_links (a_id);
+CREATE INDEX star_links_b_id ON star_links (b_id);
+CREATE INDEX players_location_id ON players (location_id);
+CREATE INDEX jumps_from_id ON jumps (from_id);
+CREATE INDEX jumps_to_id ON jumps (to_id);
@@ -0,0 +1,2 @@
+-- Names are looked up when sectors are filled, to keep them unique in the galaxy
+CREATE INDEX star_systems_name ON star_systems (name);
@@ -0,0 +1,35 @@
+extern crate dotenv;
+extern crate env_logger;
+#[macro_use]
+extern crate log;
+extern crate tg_space_game;
+
+use std::env;
+use std::thread;
+use std::time::Duration;
+use tg_space_game::telegram::{Bot, TelegramApi, DEFAULT_API_URL};
+use tg_space_game::*;
+
+/// Runs the game bot. Configured with environment:
+/// `TELEGRAM_TOKEN`, optional `TELEGRAM_API_URL` and `GALAXY_ID`, and
+/// `DATABASE_URL`.
+fn main() {
+    dotenv::dotenv().ok();
+    env_logger::init();
+
+    let token = env::var("TELEGRAM_TOKEN").expect("Please set TELEGRAM_TOKEN");
+    let api_url = env::var("TELEGRAM_API_URL").unwrap_or_else(|_| DEFAULT_API_URL.to_string());
+    let galaxy = env::var("GALAXY_ID")
+        .ok()
+        .map(|id| id.parse().expect("GALAXY_ID must be numeric"));
+    let connection = establish_connection().unwrap_or_else(|err| panic!("{}", err));
+
+    let mut bot = Bot::new(TelegramApi::new(&api_url, &token), &connection, galaxy);
+    info!("Bot started");
+    loop {
+        if let Err(err) = bot.poll() {
+            warn!("{}", err);
+            thread::sleep(Duration::from_secs(5));
+        }
+    }
+}
@@ -0,0 +1,507 @@
+#[macro_use]
+extern crate clap;
+extern crate dotenv;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+extern crate tg_space_game;
+
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use serde::Serialize;
+use std::process;
+use std::thread;
+use std::time::Duration;
+use tg_space_game::config::GenerationConfig;
+use tg_space_game::export::*;
+use tg_space_game::galaxy_objects::*;
+use tg_space_game::graph_export::*;
+use tg_space_game::models::*;
+use tg_space_game::pregeneration::*;
+use tg_space_game::store::GalaxyStore;
+use tg_space_game::validation::*;
+use tg_space_game::*;
+
+/// Prints results either for humans or as JSON for scripts
+struct Output {
+    json: bool,
+}
+
+impl Output {
+    fn print<T: Serialize, F: FnOnce(&T)>(&self, value: &T, human: F) {
+        if self.json {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(value).expect("Error serializing output")
+            );
+        } else {
+            human(value);
+        }
+    }
+}
+
+fn app() -> App<'static, 'static> {
+    let id_arg = |name: &'static str, help: &'static str| Arg::with_name(name).help(help).required(true);
+
+    App::new("galaxy")
+        .about("Manages generated galaxies")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .arg(
+            Arg::with_name("database-url")
+                .long("database-url")
+                .takes_value(true)
+                .global(true)
+                .help("Postgres or SQLite url, DATABASE_URL by default"),
+        )
+        .arg(
+            Arg::with_name("json")
+                .long("json")
+                .global(true)
+                .help("Print output as JSON"),
+        )
+        .subcommand(SubCommand::with_name("migrate").about("Creates or updates database schema"))
+        .subcommand(
+            SubCommand::with_name("generate")
+                .about("Generates a new galaxy")
+                .arg(
+                    Arg::with_name("stars")
+                        .long("stars")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Expected amount of stars"),
+                )
+                .arg(
+                    Arg::with_name("radius")
+                        .long("radius")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Radius of the galaxy"),
+                )
+                .arg(
+                    Arg::with_name("seed")
+                        .long("seed")
+                        .takes_value(true)
+                        .allow_hyphen_values(true)
+                        .help("Seed of the galaxy, random by default"),
+                )
+                .arg(
+                    Arg::with_name("config")
+                        .long("config")
+                        .takes_value(true)
+                        .help("Generation config in TOML"),
+                )
+                .arg(
+                    Arg::with_name("fulfill-all")
+                        .long("fulfill-all")
+                        .help("Fulfill all futures down to the star systems"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("fulfill")
+                .about("Turns sector future into a sector")
+                .arg(id_arg("FUTURE_ID", "Id of the future")),
+        )
+        .subcommand(
+            SubCommand::with_name("collapse")
+                .about("Turns fulfilled sector back into a sector future")
+                .arg(id_arg("SECTOR_ID", "Id of the sector")),
+        )
+        .subcommand(
+            SubCommand::with_name("tree")
+                .about("Shows root sectors, or the tree of given sector")
+                .arg(Arg::with_name("SECTOR_ID").help("Id of the sector"))
+                .arg(
+                    Arg::with_name("depth")
+                        .long("depth")
+                        .takes_value(true)
+                        .default_value("1")
+                        .help("Levels of sub-sectors to show"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("systems")
+                .about("Shows star systems of given sector, or of all galaxies")
+                .arg(Arg::with_name("SECTOR_ID").help("Id of the sector"))
+                .arg(
+                    Arg::with_name("limit")
+                        .long("limit")
+                        .takes_value(true)
+                        .default_value("20")
+                        .help("Maximum amount of systems without sector"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("links")
+                .about("Shows links of a system or a future")
+                .arg(id_arg("OBJECT_ID", "Id of the object")),
+        )
+        .subcommand(
+            SubCommand::with_name("delete")
+                .about("Deletes sector with everything inside")
+                .arg(id_arg("SECTOR_ID", "Id of the sector")),
+        )
+        .subcommand(
+            SubCommand::with_name("export")
+                .about("Prints sector with everything inside as JSON")
+                .arg(id_arg("SECTOR_ID", "Id of the sector")),
+        )
+        .subcommand(
+            SubCommand::with_name("graph")
+                .about("Prints link graph of a sector for Graphviz or graph editors")
+                .arg(id_arg("SECTOR_ID", "Id of the sector"))
+                .arg(
+                    Arg::with_name("format")
+                        .long("format")
+                        .takes_value(true)
+                        .possible_values(&["dot", "graphml"])
+                        .default_value("dot")
+                        .help("Graphviz DOT, or GraphML"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("import")
+                .about("Saves exported JSON as a new galaxy")
+                .arg(id_arg("FILE", "Exported galaxy")),
+        )
+        .subcommand(
+            SubCommand::with_name("stats")
+                .about("Counts objects inside of a sector")
+                .arg(id_arg("SECTOR_ID", "Id of the sector")),
+        )
+        .subcommand(
+            SubCommand::with_name("pregenerate")
+                .about("Fulfills futures near players ahead of time")
+                .arg(
+                    Arg::with_name("workers")
+                        .long("workers")
+                        .takes_value(true)
+                        .default_value("2")
+                        .help("Futures fulfilled at the same time"),
+                )
+                .arg(
+                    Arg::with_name("queue-size")
+                        .long("queue-size")
+                        .takes_value(true)
+                        .default_value("32")
+                        .help("Maximum amount of futures fulfilled in a round"),
+                )
+                .arg(
+                    Arg::with_name("depth")
+                        .long("depth")
+                        .takes_value(true)
+                        .default_value("2")
+                        .help("Futures up to this many links away from players are fulfilled"),
+                )
+                .arg(
+                    Arg::with_name("priority")
+                        .long("priority")
+                        .takes_value(true)
+                        .possible_values(&["distance", "demand"])
+                        .default_value("distance")
+                        .help("Fulfill futures closest to players, or near most players first"),
+                )
+                .arg(
+                    Arg::with_name("interval")
+                        .long("interval")
+                        .takes_value(true)
+                        .default_value("5")
+                        .help("Seconds between rounds"),
+                )
+                .arg(
+                    Arg::with_name("once")
+                        .long("once")
+                        .help("Run a single round and exit"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("validate")
+                .about("Checks integrity of all galaxies")
+                .arg(
+                    Arg::with_name("repair")
+                        .long("repair")
+                        .help("Fix missing and orphan galaxy objects and dangling links"),
+                ),
+        )
+}
+
+fn main() {
+    dotenv::dotenv().ok();
+    let matches = app().get_matches();
+    let output = Output {
+        json: matches.is_present("json"),
+    };
+
+    if let Err(err) = run(&matches, &output) {
+        eprintln!("{}", err);
+        process::exit(if err.is_user_error() { 1 } else { 2 });
+    }
+}
+
+fn url(matches: &ArgMatches) -> GalaxyResult<String> {
+    match matches.value_of("database-url") {
+        Some(url) => Ok(url.to_string()),
+        None => database_url(),
+    }
+}
+
+fn run(matches: &ArgMatches, output: &Output) -> GalaxyResult<()> {
+    let url = url(matches)?;
+    let conn = GalaxyConnection::establish(&url)?;
+    match matches.subcommand() {
+        ("migrate", Some(_)) => {
+            conn.run_migrations(&mut std::io::sink())?;
+            output.print(&"ok", |_| println!("Database is up to date"));
+            Ok(())
+        }
+        ("generate", Some(m)) => generate(&conn, m, output),
+        ("fulfill", Some(m)) => {
+            let sector = fulfill_star_sector_future(&conn, value_t_or_exit!(m, "FUTURE_ID", i32))?;
+            output.print(&sector, |s| println!("Fulfilled sector {}", s.id));
+            Ok(())
+        }
+        ("collapse", Some(m)) => {
+            let future = collapse_sector(&conn, value_t_or_exit!(m, "SECTOR_ID", i32))?;
+            output.print(&future, |f| println!("Collapsed sector {} into a future", f.id));
+            Ok(())
+        }
+        ("tree", Some(m)) => tree(&conn, m, output),
+        ("systems", Some(m)) => {
+            let systems = match m.value_of("SECTOR_ID") {
+                Some(_) => conn.get_child_systems(value_t_or_exit!(m, "SECTOR_ID", i32))?,
+                None => conn.list_systems(value_t_or_exit!(m, "limit", i64))?,
+            };
+            output.print(&systems, |systems| {
+                for s in systems {
+                    println!("{} {} ({}, {}, {})", s.id, s.name, s.x, s.y, s.z);
+                }
+            });
+            Ok(())
+        }
+        ("links", Some(m)) => {
+            let links = conn.get_links_for_objects(&[value_t_or_exit!(m, "OBJECT_ID", i32)])?;
+            output.print(&links, |links| {
+                for l in links {
+                    println!(
+                        "{} {} - {} {}: {} {:.1} (cost {:.1})",
+                        l.a_obj_type.as_str(),
+                        l.a_id,
+                        l.b_obj_type.as_str(),
+                        l.b_id,
+                        l.link_type.as_str(),
+                        l.length,
+                        l.cost
+                    );
+                }
+            });
+            Ok(())
+        }
+        ("delete", Some(m)) => {
+            let deleted = delete_sector(&conn, value_t_or_exit!(m, "SECTOR_ID", i32))?;
+            output.print(&deleted, |d| {
+                println!("Sectors: {}", d.sectors);
+                println!("Futures: {}", d.futures);
+                println!("Systems: {}", d.systems);
+                println!("Links: {}", d.links);
+                println!("Galaxy objects: {}", d.galaxy_objects);
+            });
+            Ok(())
+        }
+        ("export", Some(m)) => {
+            let export = export_galaxy(&conn, value_t_or_exit!(m, "SECTOR_ID", i32))?;
+            println!("{}", export.to_json());
+            Ok(())
+        }
+        ("graph", Some(m)) => {
+            let format = m.value_of("format").unwrap_or("dot").parse()?;
+            print!("{}", export_graph(&conn, value_t_or_exit!(m, "SECTOR_ID", i32), format)?);
+            Ok(())
+        }
+        ("import", Some(m)) => {
+            let export = GalaxyExport::load(m.value_of("FILE").expect("FILE is required"))?;
+            let sector = import_galaxy(&conn, &export)?;
+            output.print(&sector, |s| println!("Imported galaxy {}", s.id));
+            Ok(())
+        }
+        ("stats", Some(m)) => {
+            let stats = get_sector_stats(&conn, value_t_or_exit!(m, "SECTOR_ID", i32))?;
+            output.print(&stats, |s| {
+                println!("Sectors: {}", s.sectors);
+                println!("Futures: {}", s.futures);
+                println!("Systems: {}", s.systems);
+                println!("Links: {}", s.links);
+            });
+            Ok(())
+        }
+        ("pregenerate", Some(m)) => pregenerate(&url, m, output),
+        ("validate", Some(m)) => validate(&conn, m, output),
+        _ => unreachable!("Subcommand is required"),
+    }
+}
+
+#[derive(Serialize)]
+struct Generated {
+    sector: StarSector,
+    seed: i64,
+    fulfilled: usize,
+}
+
+fn generate(conn: &GalaxyConnection, m: &ArgMatches, output: &Output) -> GalaxyResult<()> {
+    let stars = value_t_or_exit!(m, "stars", f32);
+    let radius = value_t_or_exit!(m, "radius", f32);
+    let seed = match m.value_of("seed") {
+        Some(_) => value_t_or_exit!(m, "seed", i64),
+        None => random_seed(),
+    };
+    let config = match m.value_of("config") {
+        Some(path) => GenerationConfig::load(path)?,
+        None => GenerationConfig::default(),
+    };
+
+    let sector = generate_star_sector(conn, stars, radius, None, seed, &config)?;
+
+    let mut fulfilled = 0;
+    if m.is_present("fulfill-all") {
+        let mut futures = get_star_sector_children_futures(conn, &sector)?;
+        while let Some(future) = futures.pop() {
+            let child = fulfill_star_sector_future(conn, future.id)?;
+            futures.extend(get_star_sector_children_futures(conn, &child)?);
+            fulfilled += 1;
+        }
+    }
+
+    output.print(
+        &Generated {
+            sector,
+            seed,
+            fulfilled,
+        },
+        |g| {
+            println!("Generated galaxy {} with seed {}", g.sector.id, g.seed);
+            if g.fulfilled > 0 {
+                println!("Fulfilled {} futures", g.fulfilled);
+            }
+        },
+    );
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct TreeNode {
+    sector: StarSector,
+    futures: Vec<StarSectorFuture>,
+    systems: usize,
+    children: Vec<TreeNode>,
+}
+
+fn tree_node(conn: &GalaxyConnection, sector: StarSector, depth: u32) -> GalaxyResult<TreeNode> {
+    let children = if depth > 0 {
+        conn.get_child_sectors(sector.id)?
+            .into_iter()
+            .map(|child| tree_node(conn, child, depth - 1))
+            .collect::<GalaxyResult<Vec<_>>>()?
+    } else {
+        Vec::new()
+    };
+    Ok(TreeNode {
+        futures: conn.get_child_futures(sector.id)?,
+        systems: conn.get_child_systems(sector.id)?.len(),
+        sector,
+        children,
+    })
+}
+
+fn print_tree_node(node: &TreeNode, indent: usize) {
+    let pad = "  ".repeat(indent);
+    let s = &node.sector;
+    println!(
+        "{}sector {} ({}, {}, {}) r={}: {} systems, {} futures",
+        pad,
+        s.id,
+        s.x,
+        s.y,
+        s.z,
+        s.radius,
+        node.systems,
+        node.futures.len()
+    );
+    for f in &node.futures {
+        println!("{}  future {}: {} stars", pad, f.id, f.stars);
+    }
+    for child in &node.children {
+        print_tree_node(child, indent + 1);
+    }
+}
+
+fn tree(conn: &GalaxyConnection, m: &ArgMatches, output: &Output) -> GalaxyResult<()> {
+    match m.value_of("SECTOR_ID") {
+        None => {
+            let roots = conn.get_root_sectors()?;
+            output.print(&roots, |roots| {
+                for s in roots {
+                    println!("{}", s.id);
+                }
+            });
+        }
+        Some(_) => {
+            let sector = conn.get_sector(value_t_or_exit!(m, "SECTOR_ID", i32))?;
+            let node = tree_node(conn, sector, value_t_or_exit!(m, "depth", u32))?;
+            output.print(&node, |node| print_tree_node(node, 0));
+        }
+    }
+    Ok(())
+}
+
+fn pregenerate(url: &str, m: &ArgMatches, output: &Output) -> GalaxyResult<()> {
+    let options = PregenerationOptions {
+        depth: value_t_or_exit!(m, "depth", u32),
+        queue_size: value_t_or_exit!(m, "queue-size", usize),
+        workers: value_t_or_exit!(m, "workers", usize),
+        priority: m.value_of("priority").unwrap_or("distance").parse()?,
+    };
+    let interval = Duration::from_secs(value_t_or_exit!(m, "interval", u64));
+    loop {
+        match run_pregeneration(url, &options) {
+            Ok(fulfilled) => output.print(&fulfilled, |n| println!("Fulfilled {} futures", n)),
+            // The database may come back by the next round
+            Err(err) if !m.is_present("once") => eprintln!("{}", err),
+            Err(err) => return Err(err),
+        }
+        if m.is_present("once") {
+            return Ok(());
+        }
+        thread::sleep(interval);
+    }
+}
+
+#[derive(Serialize)]
+struct Validated {
+    repaired: Vec<Violation>,
+    violations: Vec<Violation>,
+}
+
+fn validate(conn: &GalaxyConnection, m: &ArgMatches, output: &Output) -> GalaxyResult<()> {
+    let repaired = if m.is_present("repair") {
+        repair_galaxy(conn)?
+    } else {
+        Vec::new()
+    };
+    let validated = Validated {
+        repaired,
+        violations: validate_galaxy(conn)?,
+    };
+    output.print(&validated, |v| {
+        for violation in &v.repaired {
+            println!("Repaired: {}", violation);
+        }
+        for violation in &v.violations {
+            println!("{}", violation);
+        }
+        if v.violations.is_empty() {
+            println!("Galaxy is valid");
+        }
+    });
+    match validated.violations.len() {
+        0 => Ok(()),
+        count => Err(GalaxyError::InvalidGalaxy(count)),
+    }
+}
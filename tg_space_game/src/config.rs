@@ -0,0 +1,169 @@
+use super::*;
+
+use schema::generation_configs;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+/// Parameters of galaxy generation. Every root sector saves the config it
+/// was generated with, and all of its futures are fulfilled with it later.
+#[derive(Insertable, Queryable, Serialize, Deserialize, PartialEq, Clone, Debug)]
+#[table_name = "generation_configs"]
+#[serde(default, deny_unknown_fields)]
+pub struct GenerationConfig {
+    /// Amount of sub-sectors in a sector that is too big to hold stars
+    pub branching_factor: i32,
+    /// Sectors with less stars than this in every sub-sector hold stars directly
+    pub leaf_threshold: f32,
+    /// Amount of links per star inside a sector
+    pub link_density: f32,
+    /// How link weights are distributed between children
+    pub weight_distribution: WeightDistribution,
+    /// Sub-sector radius is parent radius divided by `branching_factor ^ radius_scaling`
+    pub radius_scaling: f32,
+    /// How star systems are named
+    pub name_style: NameStyle,
+    /// Which child of a fulfilled future inherits each of its links
+    pub link_reattach: LinkReattachPolicy,
+    /// How children of a sector are linked to each other
+    pub link_strategy: LinkStrategy,
+}
+
+impl Default for GenerationConfig {
+    fn default() -> Self {
+        GenerationConfig {
+            branching_factor: 10,
+            leaf_threshold: 10.0,
+            link_density: 4.0,
+            weight_distribution: WeightDistribution::Exponential,
+            radius_scaling: 1.0 / 3.0,
+            name_style: NameStyle::Markov,
+            link_reattach: LinkReattachPolicy::Nearest,
+            link_strategy: LinkStrategy::Random,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(io::Error),
+    Toml(toml::de::Error),
+    Invalid(&'static str),
+    /// Environment variable isn't set
+    MissingVar(&'static str),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ConfigError::Io(ref err) => write!(f, "Error reading config: {}", err),
+            ConfigError::Toml(ref err) => write!(f, "Error parsing config: {}", err),
+            ConfigError::Invalid(reason) => write!(f, "Invalid config: {}", reason),
+            ConfigError::MissingVar(name) => write!(f, "Please set {}", name),
+        }
+    }
+}
+
+impl From<io::Error> for ConfigError {
+    fn from(err: io::Error) -> Self {
+        ConfigError::Io(err)
+    }
+}
+
+impl From<toml::de::Error> for ConfigError {
+    fn from(err: toml::de::Error) -> Self {
+        ConfigError::Toml(err)
+    }
+}
+
+/// Links per star. Sectors can't have more links than pairs of children
+/// anyway, so anything above is a typo.
+const MAX_LINK_DENSITY: f32 = 100.0;
+
+impl GenerationConfig {
+    /// Parses config from TOML. Missing fields are taken from defaults.
+    pub fn from_toml(source: &str) -> Result<GenerationConfig, ConfigError> {
+        let config: GenerationConfig = toml::from_str(source)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<GenerationConfig, ConfigError> {
+        let mut source = String::new();
+        File::open(path)?.read_to_string(&mut source)?;
+        GenerationConfig::from_toml(&source)
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.branching_factor < 2 {
+            return Err(ConfigError::Invalid("branching_factor must be at least 2"));
+        }
+        // Leaves get `leaf_threshold` stars at most, so below one they could
+        // be left without any
+        if self.leaf_threshold.is_nan() || self.leaf_threshold < 1.0 {
+            return Err(ConfigError::Invalid("leaf_threshold must be at least 1"));
+        }
+        if !(self.link_density >= 0.0 && self.link_density <= MAX_LINK_DENSITY) {
+            return Err(ConfigError::Invalid("link_density must be between 0 and 100"));
+        }
+        // Negative scaling would make children bigger than their sector
+        if !self.radius_scaling.is_finite() || self.radius_scaling < 0.0 {
+            return Err(ConfigError::Invalid("radius_scaling must be finite and not negative"));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_toml_fills_missing_fields_with_defaults() {
+        let config = GenerationConfig::from_toml("branching_factor = 4").unwrap();
+        assert_eq!(config.branching_factor, 4);
+        assert_eq!(config.leaf_threshold, GenerationConfig::default().leaf_threshold);
+    }
+
+    #[test]
+    fn from_toml_reads_weight_distribution() {
+        let config = GenerationConfig::from_toml("weight_distribution = \"uniform\"").unwrap();
+        assert_eq!(config.weight_distribution, WeightDistribution::Uniform);
+    }
+
+    #[test]
+    fn from_toml_reads_link_strategy() {
+        let config = GenerationConfig::from_toml("link_strategy = \"nearest_neighbours\"").unwrap();
+        assert_eq!(config.link_strategy, LinkStrategy::NearestNeighbours);
+    }
+
+    #[test]
+    fn from_toml_rejects_invalid_branching_factor() {
+        assert!(GenerationConfig::from_toml("branching_factor = 1").is_err());
+    }
+
+    #[test]
+    fn from_toml_rejects_infinite_link_density() {
+        assert!(GenerationConfig::from_toml("link_density = inf").is_err());
+        assert!(GenerationConfig::from_toml("link_density = 1000.0").is_err());
+        assert!(GenerationConfig::from_toml("link_density = 100.0").is_ok());
+    }
+
+    #[test]
+    fn from_toml_rejects_negative_radius_scaling() {
+        assert!(GenerationConfig::from_toml("radius_scaling = -0.5").is_err());
+        assert!(GenerationConfig::from_toml("radius_scaling = 0.0").is_ok());
+    }
+
+    #[test]
+    fn from_toml_rejects_leaf_threshold_below_one() {
+        assert!(GenerationConfig::from_toml("leaf_threshold = 0.5").is_err());
+        assert!(GenerationConfig::from_toml("leaf_threshold = 1.0").is_ok());
+    }
+
+    #[test]
+    fn from_toml_rejects_unknown_fields() {
+        assert!(GenerationConfig::from_toml("branching = 4").is_err());
+    }
+}
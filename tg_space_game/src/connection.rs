@@ -0,0 +1,276 @@
+use super::*;
+
+use chrono::NaiveDateTime;
+use diesel::connection::SimpleConnection;
+use diesel::sqlite::SqliteConnection;
+use diesel::ConnectionError;
+use galaxy_objects::{DeletedRows, SectorStats};
+use std::collections::HashSet;
+use std::io::Write;
+use store::GalaxyStore;
+
+mod pg_migrations {
+    embed_migrations!("migrations");
+    pub use self::embedded_migrations::run_with_output;
+}
+
+mod sqlite_migrations {
+    embed_migrations!("migrations_sqlite");
+    pub use self::embedded_migrations::run_with_output;
+}
+
+/// Connection to one of the supported databases
+pub enum GalaxyConnection {
+    Pg(PgConnection),
+    Sqlite(SqliteConnection),
+}
+
+impl GalaxyConnection {
+    /// Connects to the database, choosing backend by url scheme:
+    /// `postgres://` and `postgresql://` for Postgres, `sqlite://` followed
+    /// by file path (or `:memory:`) for SQLite.
+    pub fn establish(database_url: &str) -> Result<GalaxyConnection, ConnectionError> {
+        if database_url.starts_with("postgres://") || database_url.starts_with("postgresql://") {
+            PgConnection::establish(database_url).map(GalaxyConnection::Pg)
+        } else if let Some(path) = database_url.strip_prefix("sqlite://") {
+            let conn = SqliteConnection::establish(path)?;
+            // SQLite doesn't check foreign keys unless asked to, and fails
+            // instead of waiting for other writers
+            conn.batch_execute("PRAGMA foreign_keys = ON; PRAGMA busy_timeout = 10000")
+                .map_err(ConnectionError::CouldntSetupConfiguration)?;
+            Ok(GalaxyConnection::Sqlite(conn))
+        } else {
+            Err(ConnectionError::InvalidConnectionUrl(format!(
+                "Unsupported database url: {}",
+                database_url
+            )))
+        }
+    }
+
+    /// Runs migrations of the connection's backend
+    pub fn run_migrations<W: Write>(&self, out: &mut W) -> Result<(), RunMigrationsError> {
+        match *self {
+            GalaxyConnection::Pg(ref conn) => pg_migrations::run_with_output(conn, out),
+            GalaxyConnection::Sqlite(ref conn) => sqlite_migrations::run_with_output(conn, out),
+        }
+    }
+}
+
+macro_rules! delegate {
+    ($self:ident . $method:ident ( $($arg:expr),* )) => {
+        match *$self {
+            GalaxyConnection::Pg(ref conn) => conn.$method($($arg),*),
+            GalaxyConnection::Sqlite(ref conn) => conn.$method($($arg),*),
+        }
+    };
+}
+
+impl GalaxyStore for GalaxyConnection {
+    fn transaction<T, F>(&self, f: F) -> GalaxyResult<T>
+    where
+        F: FnOnce() -> GalaxyResult<T>,
+    {
+        match *self {
+            GalaxyConnection::Pg(ref conn) => GalaxyStore::transaction(conn, f),
+            GalaxyConnection::Sqlite(ref conn) => GalaxyStore::transaction(conn, f),
+        }
+    }
+
+    fn snapshot_transaction<T, F>(&self, f: F) -> GalaxyResult<T>
+    where
+        F: FnOnce() -> GalaxyResult<T>,
+    {
+        match *self {
+            GalaxyConnection::Pg(ref conn) => conn.snapshot_transaction(f),
+            GalaxyConnection::Sqlite(ref conn) => conn.snapshot_transaction(f),
+        }
+    }
+
+    fn create_galaxy_objects(&self, types: &[GalaxyObjectType]) -> GalaxyResult<Vec<GalaxyObject>> {
+        delegate!(self.create_galaxy_objects(types))
+    }
+
+    fn insert_galaxy_objects(&self, objects: &[GalaxyObject]) -> GalaxyResult<usize> {
+        delegate!(self.insert_galaxy_objects(objects))
+    }
+
+    fn get_galaxy_object(&self, id: i32) -> GalaxyResult<Option<GalaxyObject>> {
+        delegate!(self.get_galaxy_object(id))
+    }
+
+    fn list_galaxy_objects(&self) -> GalaxyResult<Vec<GalaxyObject>> {
+        delegate!(self.list_galaxy_objects())
+    }
+
+    fn update_galaxy_object_type(&self, object: &GalaxyObject, obj_type: GalaxyObjectType) -> GalaxyResult<()> {
+        delegate!(self.update_galaxy_object_type(object, obj_type))
+    }
+
+    fn delete_galaxy_objects(&self, ids: &[i32]) -> GalaxyResult<usize> {
+        delegate!(self.delete_galaxy_objects(ids))
+    }
+
+    fn insert_generation_config(&self, config: &GenerationConfig) -> GalaxyResult<i32> {
+        delegate!(self.insert_generation_config(config))
+    }
+
+    fn get_generation_config(&self, config_id: i32) -> GalaxyResult<GenerationConfig> {
+        delegate!(self.get_generation_config(config_id))
+    }
+
+    fn insert_sector(&self, sector: &NewStarSector) -> GalaxyResult<StarSector> {
+        delegate!(self.insert_sector(sector))
+    }
+
+    fn get_sector(&self, id: i32) -> GalaxyResult<StarSector> {
+        delegate!(self.get_sector(id))
+    }
+
+    fn get_sectors(&self, ids: &[i32]) -> GalaxyResult<Vec<StarSector>> {
+        delegate!(self.get_sectors(ids))
+    }
+
+    fn get_root_sectors(&self) -> GalaxyResult<Vec<StarSector>> {
+        delegate!(self.get_root_sectors())
+    }
+
+    fn list_sectors(&self) -> GalaxyResult<Vec<StarSector>> {
+        delegate!(self.list_sectors())
+    }
+
+    fn get_child_sectors(&self, parent_id: i32) -> GalaxyResult<Vec<StarSector>> {
+        delegate!(self.get_child_sectors(parent_id))
+    }
+
+    fn get_sector_subtree(&self, sector_id: i32) -> GalaxyResult<Vec<GalaxyObject>> {
+        delegate!(self.get_sector_subtree(sector_id))
+    }
+
+    fn count_sector_subtree(&self, sector_id: i32) -> GalaxyResult<SectorStats> {
+        delegate!(self.count_sector_subtree(sector_id))
+    }
+
+    fn delete_sector_subtree(&self, sector_id: i32, keep_sector_object: bool) -> GalaxyResult<DeletedRows> {
+        delegate!(self.delete_sector_subtree(sector_id, keep_sector_object))
+    }
+
+    fn delete_sectors(&self, ids: &[i32]) -> GalaxyResult<usize> {
+        delegate!(self.delete_sectors(ids))
+    }
+
+    fn insert_futures(&self, futures: &[NewStarSectorFuture]) -> GalaxyResult<Vec<StarSectorFuture>> {
+        delegate!(self.insert_futures(futures))
+    }
+
+    fn lock_futures(&self, ids: &[i32]) -> GalaxyResult<Vec<StarSectorFuture>> {
+        delegate!(self.lock_futures(ids))
+    }
+
+    fn try_lock_futures(&self, ids: &[i32]) -> GalaxyResult<Vec<StarSectorFuture>> {
+        delegate!(self.try_lock_futures(ids))
+    }
+
+    fn get_futures(&self, ids: &[i32]) -> GalaxyResult<Vec<StarSectorFuture>> {
+        delegate!(self.get_futures(ids))
+    }
+
+    fn get_child_futures(&self, sector_id: i32) -> GalaxyResult<Vec<StarSectorFuture>> {
+        delegate!(self.get_child_futures(sector_id))
+    }
+
+    fn list_futures(&self) -> GalaxyResult<Vec<StarSectorFuture>> {
+        delegate!(self.list_futures())
+    }
+
+    fn delete_futures(&self, ids: &[i32]) -> GalaxyResult<usize> {
+        delegate!(self.delete_futures(ids))
+    }
+
+    fn insert_systems(&self, systems: &[NewStarSystem]) -> GalaxyResult<Vec<StarSystem>> {
+        delegate!(self.insert_systems(systems))
+    }
+
+    fn get_systems(&self, ids: &[i32]) -> GalaxyResult<Vec<StarSystem>> {
+        delegate!(self.get_systems(ids))
+    }
+
+    fn list_systems(&self, limit: i64) -> GalaxyResult<Vec<StarSystem>> {
+        delegate!(self.list_systems(limit))
+    }
+
+    fn get_child_systems(&self, sector_id: i32) -> GalaxyResult<Vec<StarSystem>> {
+        delegate!(self.get_child_systems(sector_id))
+    }
+
+    fn delete_systems(&self, ids: &[i32]) -> GalaxyResult<usize> {
+        delegate!(self.delete_systems(ids))
+    }
+
+    fn get_taken_star_names(&self, config_id: i32, names: &[String]) -> GalaxyResult<HashSet<String>> {
+        delegate!(self.get_taken_star_names(config_id, names))
+    }
+
+    fn insert_links(&self, links: &[NewStarLink]) -> GalaxyResult<usize> {
+        delegate!(self.insert_links(links))
+    }
+
+    fn get_links_for_objects(&self, ids: &[i32]) -> GalaxyResult<Vec<StarLink>> {
+        delegate!(self.get_links_for_objects(ids))
+    }
+
+    fn delete_links_for_objects(&self, ids: &[i32]) -> GalaxyResult<usize> {
+        delegate!(self.delete_links_for_objects(ids))
+    }
+
+    fn list_links(&self) -> GalaxyResult<Vec<StarLink>> {
+        delegate!(self.list_links())
+    }
+
+    fn delete_links(&self, ids: &[i32]) -> GalaxyResult<usize> {
+        delegate!(self.delete_links(ids))
+    }
+
+    fn insert_player_if_missing(&self, player: &NewPlayer) -> GalaxyResult<Player> {
+        delegate!(self.insert_player_if_missing(player))
+    }
+
+    fn get_player(&self, telegram_id: i64) -> GalaxyResult<Option<Player>> {
+        delegate!(self.get_player(telegram_id))
+    }
+
+    fn get_located_players(&self) -> GalaxyResult<Vec<Player>> {
+        delegate!(self.get_located_players())
+    }
+
+    fn get_players_in_sector(&self, sector_id: i32) -> GalaxyResult<Vec<Player>> {
+        delegate!(self.get_players_in_sector(sector_id))
+    }
+
+    fn update_player_location(&self, telegram_id: i64, location_id: i32) -> GalaxyResult<Player> {
+        delegate!(self.update_player_location(telegram_id, location_id))
+    }
+
+    fn insert_jump(&self, jump: &Jump) -> GalaxyResult<()> {
+        delegate!(self.insert_jump(jump))
+    }
+
+    fn get_jump(&self, telegram_id: i64) -> GalaxyResult<Option<Jump>> {
+        delegate!(self.get_jump(telegram_id))
+    }
+
+    fn get_arrived_jumps(&self, time: NaiveDateTime) -> GalaxyResult<Vec<Jump>> {
+        delegate!(self.get_arrived_jumps(time))
+    }
+
+    fn list_jumps(&self) -> GalaxyResult<Vec<Jump>> {
+        delegate!(self.list_jumps())
+    }
+
+    fn get_next_arrival(&self) -> GalaxyResult<Option<NaiveDateTime>> {
+        delegate!(self.get_next_arrival())
+    }
+
+    fn delete_jumps(&self, telegram_ids: &[i64]) -> GalaxyResult<usize> {
+        delegate!(self.delete_jumps(telegram_ids))
+    }
+}
@@ -0,0 +1,188 @@
+use super::*;
+
+use config::ConfigError;
+use export::ExportError;
+use diesel::ConnectionError;
+use std::error;
+use std::fmt;
+
+/// Error of any galaxy operation
+#[derive(Debug)]
+pub enum GalaxyError {
+    /// Object doesn't exist, or isn't of the type it was expected to be
+    ObjectNotFound(GalaxyObject),
+    /// Object exists, but operation needs an object of another type
+    WrongObjectType {
+        object: GalaxyObject,
+        expected: GalaxyObjectType,
+    },
+    /// Future was already turned into a sector
+    FutureAlreadyFulfilled(i32),
+    InvalidGenerationParams(&'static str),
+    /// Telegram user isn't registered as a player
+    PlayerNotFound(i64),
+    /// Sector has no star systems to put a player to
+    NoStarSystems(i32),
+    /// Player wasn't spawned in the galaxy yet
+    PlayerNotSpawned(i64),
+    /// Player's ship is in flight and can't start another jump
+    AlreadyJumping(i64),
+    /// There is no star link between the objects
+    NotLinked { from: i32, to: i32 },
+    /// Integrity check found violations, that weren't repaired
+    InvalidGalaxy(usize),
+    /// Sector can't be turned back into a future
+    CantCollapse { sector_id: i32, reason: &'static str },
+    Config(ConfigError),
+    Export(ExportError),
+    Connection(ConnectionError),
+    Database(diesel::result::Error),
+    Migration(RunMigrationsError),
+}
+
+pub type GalaxyResult<T> = Result<T, GalaxyError>;
+
+impl GalaxyError {
+    /// Whether the error is caused by the request itself, and not by
+    /// storage or environment. Such errors can be shown to players as is.
+    pub fn is_user_error(&self) -> bool {
+        match *self {
+            // Missing variable is a problem of the environment, not of the config
+            GalaxyError::Config(ConfigError::MissingVar(_)) => false,
+            GalaxyError::ObjectNotFound(_)
+            | GalaxyError::WrongObjectType { .. }
+            | GalaxyError::FutureAlreadyFulfilled(_)
+            | GalaxyError::InvalidGenerationParams(_)
+            | GalaxyError::PlayerNotFound(_)
+            | GalaxyError::NoStarSystems(_)
+            | GalaxyError::PlayerNotSpawned(_)
+            | GalaxyError::AlreadyJumping(_)
+            | GalaxyError::NotLinked { .. }
+            | GalaxyError::InvalidGalaxy(_)
+            | GalaxyError::CantCollapse { .. }
+            | GalaxyError::Config(_)
+            | GalaxyError::Export(_) => true,
+            GalaxyError::Connection(_) | GalaxyError::Database(_) | GalaxyError::Migration(_) => {
+                false
+            }
+        }
+    }
+
+    /// Turns missing row into `ObjectNotFound` for the object that was queried
+    pub fn not_found(object: GalaxyObject) -> impl FnOnce(diesel::result::Error) -> GalaxyError {
+        move |err| match err {
+            diesel::result::Error::NotFound => GalaxyError::ObjectNotFound(object),
+            err => GalaxyError::Database(err),
+        }
+    }
+}
+
+impl fmt::Display for GalaxyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            GalaxyError::ObjectNotFound(ref object) => {
+                write!(f, "No {} with id {}", object.obj_type.as_str(), object.id)
+            }
+            GalaxyError::WrongObjectType {
+                ref object,
+                expected,
+            } => write!(
+                f,
+                "Object {} is a {}, not a {}",
+                object.id,
+                object.obj_type.as_str(),
+                expected.as_str()
+            ),
+            GalaxyError::FutureAlreadyFulfilled(id) => {
+                write!(f, "Sector future {} is already fulfilled", id)
+            }
+            GalaxyError::InvalidGenerationParams(reason) => {
+                write!(f, "Invalid generation parameters: {}", reason)
+            }
+            GalaxyError::PlayerNotFound(id) => write!(f, "No player with Telegram id {}", id),
+            GalaxyError::NoStarSystems(id) => write!(f, "Sector {} has no star systems", id),
+            GalaxyError::PlayerNotSpawned(id) => write!(f, "Player {} is not in the galaxy yet", id),
+            GalaxyError::AlreadyJumping(id) => write!(f, "Player {} is already jumping", id),
+            GalaxyError::NotLinked { from, to } => write!(f, "{} can't be reached from {}", to, from),
+            GalaxyError::InvalidGalaxy(count) => write!(f, "Galaxy has {} integrity violations", count),
+            GalaxyError::CantCollapse { sector_id, reason } => {
+                write!(f, "Sector {} can't be collapsed: {}", sector_id, reason)
+            }
+            GalaxyError::Config(ref err) => err.fmt(f),
+            GalaxyError::Export(ref err) => err.fmt(f),
+            GalaxyError::Connection(ref err) => write!(f, "Error connecting to database: {}", err),
+            GalaxyError::Database(ref err) => write!(f, "Database error: {}", err),
+            GalaxyError::Migration(ref err) => write!(f, "Error running migrations: {}", err),
+        }
+    }
+}
+
+impl error::Error for GalaxyError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            GalaxyError::Connection(ref err) => Some(err),
+            GalaxyError::Database(ref err) => Some(err),
+            GalaxyError::Migration(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<diesel::result::Error> for GalaxyError {
+    fn from(err: diesel::result::Error) -> Self {
+        GalaxyError::Database(err)
+    }
+}
+
+impl From<ConnectionError> for GalaxyError {
+    fn from(err: ConnectionError) -> Self {
+        GalaxyError::Connection(err)
+    }
+}
+
+impl From<RunMigrationsError> for GalaxyError {
+    fn from(err: RunMigrationsError) -> Self {
+        GalaxyError::Migration(err)
+    }
+}
+
+impl From<ConfigError> for GalaxyError {
+    fn from(err: ConfigError) -> Self {
+        GalaxyError::Config(err)
+    }
+}
+
+impl From<ExportError> for GalaxyError {
+    fn from(err: ExportError) -> Self {
+        GalaxyError::Export(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn not_found_keeps_other_database_errors() {
+        let object = GalaxyObject {
+            id: 1,
+            obj_type: GalaxyObjectType::System,
+        };
+        match GalaxyError::not_found(object.clone())(diesel::result::Error::NotFound) {
+            GalaxyError::ObjectNotFound(o) => assert_eq!(o, object),
+            err => panic!("Unexpected error {:?}", err),
+        }
+        match GalaxyError::not_found(object)(diesel::result::Error::RollbackTransaction) {
+            GalaxyError::Database(_) => {}
+            err => panic!("Unexpected error {:?}", err),
+        }
+    }
+
+    #[test]
+    fn infrastructure_errors_are_not_user_errors() {
+        assert!(GalaxyError::FutureAlreadyFulfilled(1).is_user_error());
+        assert!(!GalaxyError::Database(diesel::result::Error::NotFound).is_user_error());
+        assert!(!GalaxyError::Config(ConfigError::MissingVar("DATABASE_URL")).is_user_error());
+        assert!(GalaxyError::Config(ConfigError::Invalid("test")).is_user_error());
+    }
+}
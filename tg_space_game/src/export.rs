@@ -0,0 +1,327 @@
+//! Portable JSON documents of galaxies.
+//!
+//! A document holds a sector with everything inside, and the config of its
+//! galaxy. Ids in it are the ones the objects had where they were exported
+//! from: importing saves every object under a fresh galaxy object id, so
+//! the same document can be imported into any database any amount of times.
+
+use super::*;
+
+use serde_json;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+use store::GalaxyStore;
+
+/// Version of the document format, bumped on incompatible changes
+pub const EXPORT_VERSION: u32 = 1;
+
+/// Sector subtree that doesn't depend on the database it was exported from
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct GalaxyExport {
+    pub version: u32,
+    /// Sector that the subtree starts at. It becomes a root when imported.
+    pub root_id: i32,
+    pub config: GenerationConfig,
+    pub sectors: Vec<StarSector>,
+    pub futures: Vec<StarSectorFuture>,
+    pub systems: Vec<StarSystem>,
+    /// Links between objects of the document
+    pub links: Vec<StarLink>,
+}
+
+#[derive(Debug)]
+pub enum ExportError {
+    Io(io::Error),
+    Json(serde_json::Error),
+    /// Version of the document, that may be too big for `u32`
+    UnsupportedVersion(u64),
+    /// Document refers to objects it doesn't have
+    Invalid(String),
+}
+
+impl fmt::Display for ExportError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ExportError::Io(ref err) => write!(f, "Error reading galaxy: {}", err),
+            ExportError::Json(ref err) => write!(f, "Error parsing galaxy: {}", err),
+            ExportError::UnsupportedVersion(version) => write!(
+                f,
+                "Galaxy format version {} is not supported, expected {}",
+                version, EXPORT_VERSION
+            ),
+            ExportError::Invalid(ref reason) => write!(f, "Invalid galaxy: {}", reason),
+        }
+    }
+}
+
+impl From<io::Error> for ExportError {
+    fn from(err: io::Error) -> Self {
+        ExportError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for ExportError {
+    fn from(err: serde_json::Error) -> Self {
+        ExportError::Json(err)
+    }
+}
+
+impl GalaxyExport {
+    /// Parses the document, checking its version before anything else
+    pub fn from_json(source: &str) -> Result<GalaxyExport, ExportError> {
+        let value: serde_json::Value = serde_json::from_str(source)?;
+        match value.get("version").and_then(|v| v.as_u64()) {
+            Some(version) if version == u64::from(EXPORT_VERSION) => {}
+            Some(version) => return Err(ExportError::UnsupportedVersion(version)),
+            None => return Err(ExportError::Invalid("version is missing".to_string())),
+        }
+        Ok(serde_json::from_value(value)?)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<GalaxyExport, ExportError> {
+        let mut source = String::new();
+        File::open(path)?.read_to_string(&mut source)?;
+        GalaxyExport::from_json(&source)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("Error serializing galaxy")
+    }
+}
+
+/// Collects the sector with everything inside into a document. Objects are
+/// ordered by id, so exporting the same galaxy always gives the same text.
+pub fn export_galaxy<S: GalaxyStore>(store: &S, sector_id: i32) -> GalaxyResult<GalaxyExport> {
+    let root = store.get_sector(sector_id)?;
+    let objects = store.get_sector_subtree(sector_id)?;
+    let ids_of = |obj_type| {
+        objects
+            .iter()
+            .filter(|o| o.obj_type == obj_type)
+            .map(|o| o.id)
+            .collect::<Vec<_>>()
+    };
+
+    let mut sectors = store.get_sectors(&ids_of(GalaxyObjectType::Sector))?;
+    sectors.sort_by_key(|s| s.id);
+    let mut futures = store.get_futures(&ids_of(GalaxyObjectType::SectorFuture))?;
+    futures.sort_by_key(|f| f.id);
+    let mut systems = store.get_systems(&ids_of(GalaxyObjectType::System))?;
+    systems.sort_by_key(|s| s.id);
+    let inside = objects.iter().cloned().collect::<HashSet<_>>();
+    let mut links = store
+        .get_links_for_objects(&objects.iter().map(|o| o.id).collect::<Vec<_>>())?
+        .into_iter()
+        .filter(|link| inside.contains(&link.side_a()) && inside.contains(&link.side_b()))
+        .collect::<Vec<_>>();
+    links.sort_by_key(|l| l.id);
+
+    Ok(GalaxyExport {
+        version: EXPORT_VERSION,
+        root_id: root.id,
+        config: store.get_generation_config(root.config_id)?,
+        sectors,
+        futures,
+        systems,
+        links,
+    })
+}
+
+/// Saves the document as a new galaxy and returns its root. Everything is
+/// saved in one transaction, and nothing is saved if the document is
+/// inconsistent.
+pub fn import_galaxy<S: GalaxyStore>(store: &S, export: &GalaxyExport) -> GalaxyResult<StarSector> {
+    if export.version != EXPORT_VERSION {
+        return Err(ExportError::UnsupportedVersion(u64::from(export.version)).into());
+    }
+    export.config.validate()?;
+
+    store.transaction(|| {
+        let old_objects = export
+            .sectors
+            .iter()
+            .map(GalaxyObject::from)
+            .chain(export.futures.iter().map(GalaxyObject::from))
+            .chain(export.systems.iter().map(GalaxyObject::from))
+            .collect::<Vec<_>>();
+        let new_objects =
+            store.create_galaxy_objects(&old_objects.iter().map(|o| o.obj_type).collect::<Vec<_>>())?;
+        let mut ids = HashMap::new();
+        for (old, new) in old_objects.iter().zip(new_objects) {
+            if ids.insert(old.id, new).is_some() {
+                return Err(invalid(format!("id {} is used twice", old.id)));
+            }
+        }
+        let config_id = store.insert_generation_config(&export.config)?;
+
+        // Parents are saved before their children
+        let mut pending = export.sectors.iter().collect::<Vec<_>>();
+        let mut saved = HashMap::new();
+        while !pending.is_empty() {
+            let (ready, rest): (Vec<_>, Vec<_>) = pending.into_iter().partition(|s| {
+                s.id == export.root_id || s.parent_id.is_some_and(|id| saved.contains_key(&id))
+            });
+            if ready.is_empty() {
+                return Err(invalid(format!("sector {} is not inside of sector {}", rest[0].id, export.root_id)));
+            }
+            for sector in ready {
+                let saved_sector = store.insert_sector(&NewStarSector {
+                    id: remap(&ids, sector.id, GalaxyObjectType::Sector)?,
+                    parent_id: match sector.parent_id {
+                        Some(parent_id) if sector.id != export.root_id => Some(saved[&parent_id]),
+                        _ => None,
+                    },
+                    seed: sector.seed,
+                    config_id,
+                    x: sector.x,
+                    y: sector.y,
+                    z: sector.z,
+                    radius: sector.radius,
+                    stars: sector.stars,
+                })?;
+                saved.insert(sector.id, saved_sector.id);
+            }
+            pending = rest;
+        }
+
+        let futures = export
+            .futures
+            .iter()
+            .map(|f| {
+                Ok(NewStarSectorFuture {
+                    id: remap(&ids, f.id, GalaxyObjectType::SectorFuture)?,
+                    parent_id: remap(&ids, f.parent_id, GalaxyObjectType::Sector)?,
+                    radius: f.radius,
+                    stars: f.stars,
+                    seed: f.seed,
+                    x: f.x,
+                    y: f.y,
+                    z: f.z,
+                })
+            })
+            .collect::<GalaxyResult<Vec<_>>>()?;
+        store.insert_futures(&futures)?;
+
+        let systems = export
+            .systems
+            .iter()
+            .map(|s| {
+                Ok(NewStarSystem {
+                    id: remap(&ids, s.id, GalaxyObjectType::System)?,
+                    name: s.name.clone(),
+                    sector_id: remap(&ids, s.sector_id, GalaxyObjectType::Sector)?,
+                    x: s.x,
+                    y: s.y,
+                    z: s.z,
+                    radius: s.radius,
+                })
+            })
+            .collect::<GalaxyResult<Vec<_>>>()?;
+        store.insert_systems(&systems)?;
+
+        let links = export
+            .links
+            .iter()
+            .map(|l| {
+                let a = new_object(&ids, l.a_id, l.a_obj_type)?;
+                let b = new_object(&ids, l.b_id, l.b_obj_type)?;
+                Ok(NewStarLink::new(&a, &b, l.length, l.link_type))
+            })
+            .collect::<GalaxyResult<Vec<_>>>()?;
+        store.insert_links(&links)?;
+
+        match saved.get(&export.root_id) {
+            Some(root_id) => store.get_sector(*root_id),
+            None => Err(invalid(format!("root sector {} is missing", export.root_id))),
+        }
+    })
+}
+
+fn invalid(reason: String) -> GalaxyError {
+    ExportError::Invalid(reason).into()
+}
+
+/// New object for an id of the document, which must be of the type
+fn new_object(ids: &HashMap<i32, GalaxyObject>, id: i32, obj_type: GalaxyObjectType) -> GalaxyResult<GalaxyObject> {
+    match ids.get(&id) {
+        Some(object) if object.obj_type == obj_type => Ok(object.clone()),
+        _ => Err(invalid(format!("no {} with id {}", obj_type.as_str(), id))),
+    }
+}
+
+fn remap(ids: &HashMap<i32, GalaxyObject>, id: i32, obj_type: GalaxyObjectType) -> GalaxyResult<i32> {
+    new_object(ids, id, obj_type).map(|o| o.id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use store::fixtures::fulfilled_galaxy;
+    use store::MemoryStore;
+
+    fn exported() -> GalaxyExport {
+        let store = MemoryStore::new();
+        let sector = fulfilled_galaxy(&store, 9);
+        export_galaxy(&store, sector.id).unwrap()
+    }
+
+    #[test]
+    fn imported_galaxy_exports_the_same_layout() {
+        let export = exported();
+        let json = export.to_json();
+        assert_eq!(GalaxyExport::from_json(&json).unwrap().to_json(), json);
+
+        // Objects already there make imported ids differ from exported ones
+        let store = MemoryStore::new();
+        store.create_galaxy_objects(&[GalaxyObjectType::System; 3]).unwrap();
+        let root = import_galaxy(&store, &export).unwrap();
+        let again = export_galaxy(&store, root.id).unwrap();
+
+        assert_ne!(again.root_id, export.root_id);
+        assert_eq!(again.config, export.config);
+        assert_eq!(again.sectors.len(), export.sectors.len());
+        assert_eq!(again.futures.len(), export.futures.len());
+        assert_eq!(again.links.len(), export.links.len());
+        let layout = |e: &GalaxyExport| e.systems.iter().map(|s| (s.name.clone(), s.x, s.y, s.z)).collect::<Vec<_>>();
+        assert_eq!(layout(&again), layout(&export));
+    }
+
+    #[test]
+    fn inconsistent_documents_are_not_imported() {
+        let mut export = exported();
+        export.systems[0].sector_id = -1;
+        let store = MemoryStore::new();
+
+        match import_galaxy(&store, &export) {
+            Err(GalaxyError::Export(ExportError::Invalid(_))) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
+        assert_eq!(store.galaxy_object_count(), 0);
+    }
+
+    #[test]
+    fn other_versions_are_rejected() {
+        let mut export = exported();
+        export.version = EXPORT_VERSION + 1;
+
+        match GalaxyExport::from_json(&export.to_json()) {
+            Err(ExportError::UnsupportedVersion(version)) => assert_eq!(version, u64::from(EXPORT_VERSION + 1)),
+            result => panic!("Unexpected result {:?}", result),
+        }
+
+        // Versions that don't fit into u32 are reported as they are
+        let json = export.to_json().replacen(
+            &format!("\"version\": {}", EXPORT_VERSION + 1),
+            "\"version\": 4294967297",
+            1,
+        );
+        match GalaxyExport::from_json(&json) {
+            Err(ExportError::UnsupportedVersion(version)) => assert_eq!(version, 4_294_967_297),
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+}
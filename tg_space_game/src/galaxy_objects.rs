@@ -1,96 +1,261 @@
 use super::*;
 
-fn update_galaxy_object_type(
-    conn: &PgConnection,
-    object_id: i32,
-    object_type: GalaxyObjectType,
-) -> Result<(), Error> {
-    use schema::galaxy_objects::dsl::*;
-    match diesel::update(galaxy_objects.filter(id.eq(object_id)))
-        .set(obj_type.eq(object_type))
-        .execute(conn)
-    {
-        Ok(1) => Ok(()),
-        _ => Err(Error::NotFound),
+use schema::types::LinkType;
+use std::collections::HashMap;
+use std::slice;
+use store::GalaxyStore;
+
+pub use routing::{find_route, find_route_with_options, Route, RouteOptions};
+pub use tools::random_seed;
+
+pub fn fulfill_star_sector_future<S: GalaxyStore>(
+    store: &S,
+    future_id: i32,
+) -> GalaxyResult<StarSector> {
+    loop {
+        let sector = store.transaction(|| match lock_future_with_neighbours(store, future_id, true)? {
+            Locked::Future(future) => fulfill_locked_future(store, future).map(Some),
+            Locked::Missing => Err(missing_future_error(store, future_id)?),
+            Locked::Retry => Ok(None),
+        })?;
+        if let Some(sector) = sector {
+            return Ok(sector);
+        }
     }
 }
 
-pub fn fulfill_star_sector_future(
-    conn: &PgConnection,
+/// Turns future into a sector, unless it's being fulfilled by another
+/// transaction right now, or doesn't exist anymore. Background workers use
+/// it to share futures without waiting for each other.
+pub fn try_fulfill_star_sector_future<S: GalaxyStore>(
+    store: &S,
     future_id: i32,
-) -> Result<StarSector, Error> {
-    conn.transaction::<StarSector, Error, _>(|| {
-        use schema::star_sector_futures::dsl::*;
+) -> GalaxyResult<Option<StarSector>> {
+    store.transaction(|| match lock_future_with_neighbours(store, future_id, false)? {
+        Locked::Future(future) => fulfill_locked_future(store, future).map(Some),
+        Locked::Missing | Locked::Retry => Ok(None),
+    })
+}
 
-        // Find and delete old future
-        let future = star_sector_futures
-            .for_update()
-            .find(future_id)
-            .get_result::<StarSectorFuture>(conn)?;
+enum Locked {
+    Future(StarSectorFuture),
+    /// Future doesn't exist, or is locked by another transaction
+    Missing,
+    /// Links of the future changed while locking
+    Retry,
+}
 
-        delete_links_for_objects(conn, vec![future_id])?;
+/// Locks the future together with futures on the other side of its links,
+/// because fulfilling any of them re-attaches the same links. Locks are
+/// taken in the order of ids, so concurrent fulfillments don't deadlock.
+/// Without `wait`, futures locked by others are not waited for, and the
+/// future is reported missing instead.
+fn lock_future_with_neighbours<S: GalaxyStore>(store: &S, future_id: i32, wait: bool) -> GalaxyResult<Locked> {
+    let mut ids = linked_future_ids(store, future_id)?;
+    ids.push(future_id);
+    ids.sort();
+    ids.dedup();
+    let locked = if wait {
+        store.lock_futures(&ids)?
+    } else {
+        store.try_lock_futures(&ids)?
+    };
+    let future = match locked.iter().find(|f| f.id == future_id) {
+        Some(future) => future.clone(),
+        None => return Ok(Locked::Missing),
+    };
+
+    // Neighbours fulfilled before they were locked have left new links
+    let locked_ids = locked.iter().map(|f| f.id).collect::<Vec<_>>();
+    if linked_future_ids(store, future_id)?
+        .iter()
+        .all(|id| locked_ids.contains(id))
+    {
+        Ok(Locked::Future(future))
+    } else if wait {
+        Ok(Locked::Retry)
+    } else {
+        Ok(Locked::Missing)
+    }
+}
 
-        diesel::delete(&future).execute(conn)?;
+fn linked_future_ids<S: GalaxyStore>(store: &S, future_id: i32) -> GalaxyResult<Vec<i32>> {
+    let future = GalaxyObject {
+        id: future_id,
+        obj_type: GalaxyObjectType::SectorFuture,
+    };
+    let mut result = Vec::new();
+    for link in store.get_links_for_objects(&[future_id])? {
+        if let Some(other) = link.other_side(&future) {
+            if other != future && other.obj_type == GalaxyObjectType::SectorFuture {
+                result.push(other.id);
+            }
+        }
+    }
+    Ok(result)
+}
 
-        // Change galaxy object type
-        update_galaxy_object_type(conn, future_id, GalaxyObjectType::Sector)?;
+fn fulfill_locked_future<S: GalaxyStore>(store: &S, future: StarSectorFuture) -> GalaxyResult<StarSector> {
+    let future_id = future.id;
+
+    // Links to the siblings are re-attached to the children later
+    let external_links = store.get_links_for_objects(&[future_id])?;
+    store.delete_links_for_objects(&[future_id])?;
+
+    store.delete_futures(&[future_id])?;
+
+    // Change galaxy object type
+    store.update_galaxy_object_type(&GalaxyObject::from(&future), GalaxyObjectType::Sector)?;
+
+    // Create new sector with the config of its galaxy
+    let parent = store.get_sector(future.parent_id)?;
+    let sector = store.insert_sector(&NewStarSector {
+        id: future_id,
+        parent_id: Some(future.parent_id),
+        seed: future.seed,
+        config_id: parent.config_id,
+        x: future.x,
+        y: future.y,
+        z: future.z,
+        radius: future.radius,
+        stars: Some(future.stars),
+    })?;
+
+    // Fill this new sector
+    let config = get_generation_config(store, &sector)?;
+    let children = fill_star_sector(store, &sector, &config, future.stars)?;
+    reattach_links(store, &sector, &external_links, &children, config.link_reattach)?;
+
+    Ok(sector)
+}
 
-        // Create new sector
-        use schema::star_sectors::dsl::*;
-        let sector = diesel::insert_into(star_sectors)
-            .values(&NewStarSector {
-                id: future_id,
-                parent_id: Some(future.parent_id),
-            })
-            .get_result(conn)?;
+/// Turns future into a sector, unless it's already fulfilled. Concurrent
+/// requests for the same future wait for the one that locked it first, and
+/// then get the sector it generated.
+pub fn ensure_future_fulfilled<S: GalaxyStore>(store: &S, future_id: i32) -> GalaxyResult<StarSector> {
+    match fulfill_star_sector_future(store, future_id) {
+        Err(GalaxyError::FutureAlreadyFulfilled(_)) => store.get_sector(future_id),
+        result => result,
+    }
+}
 
-        // Fill this new sector
-        fill_star_sector(conn, &sector, future.stars, future.radius)?;
+/// Returns star system that the object leads to. Systems are returned as
+/// is, sectors and futures are descended down to their first star system,
+/// fulfilling futures on the way. Every future is fulfilled in its own
+/// transaction, so no locks are held while descending.
+pub fn resolve_object<S: GalaxyStore>(store: &S, object: &GalaxyObject) -> GalaxyResult<StarSystem> {
+    let mut sector = match object.obj_type {
+        GalaxyObjectType::System => {
+            return store
+                .get_systems(&[object.id])?
+                .pop()
+                .ok_or_else(|| GalaxyError::ObjectNotFound(object.clone()))
+        }
+        GalaxyObjectType::Sector => store.get_sector(object.id)?,
+        GalaxyObjectType::SectorFuture => ensure_future_fulfilled(store, object.id)?,
+    };
+    loop {
+        if let Some(system) = store.get_child_systems(sector.id)?.into_iter().next() {
+            return Ok(system);
+        }
+        sector = match store.get_child_sectors(sector.id)?.into_iter().next() {
+            Some(child) => child,
+            None => match store.get_child_futures(sector.id)?.first() {
+                Some(future) => ensure_future_fulfilled(store, future.id)?,
+                None => return Err(GalaxyError::NoStarSystems(object.id)),
+            },
+        };
+    }
+}
 
-        Ok(sector)
+/// Explains why there's no future with given id
+fn missing_future_error<S: GalaxyStore>(store: &S, future_id: i32) -> GalaxyResult<GalaxyError> {
+    let future = GalaxyObject {
+        id: future_id,
+        obj_type: GalaxyObjectType::SectorFuture,
+    };
+    Ok(match store.get_galaxy_object(future_id)? {
+        Some(GalaxyObject {
+            obj_type: GalaxyObjectType::Sector,
+            ..
+        }) => GalaxyError::FutureAlreadyFulfilled(future_id),
+        Some(ref object) if *object != future => GalaxyError::WrongObjectType {
+            object: object.clone(),
+            expected: GalaxyObjectType::SectorFuture,
+        },
+        _ => GalaxyError::ObjectNotFound(future),
     })
 }
 
-fn create_star_sector(conn: &PgConnection, parent: Option<i32>) -> Result<StarSector, Error> {
-    conn.transaction::<StarSector, Error, _>(|| {
-        use schema::galaxy_objects::dsl::*;
-        use schema::star_sectors::dsl::*;
-
-        let galaxy_object: GalaxyObject = diesel::insert_into(galaxy_objects)
-            .values(&NewGalaxyObject {
-                obj_type: GalaxyObjectType::Sector,
-            })
-            .get_result(conn)?;
-
-        diesel::insert_into(star_sectors)
-            .values(&NewStarSector {
-                id: galaxy_object.id,
-                parent_id: parent,
-            })
-            .get_result(conn)
+fn create_star_sector<S: GalaxyStore>(
+    store: &S,
+    parent: Option<i32>,
+    seed: i64,
+    config: &GenerationConfig,
+    radius: f32,
+    stars: f32,
+) -> GalaxyResult<StarSector> {
+    store.transaction(|| {
+        let config_id = store.insert_generation_config(config)?;
+        let galaxy_object = store
+            .create_galaxy_objects(&[GalaxyObjectType::Sector])?
+            .remove(0);
+
+        store.insert_sector(&NewStarSector {
+            id: galaxy_object.id,
+            parent_id: parent,
+            seed,
+            config_id,
+            x: 0f32,
+            y: 0f32,
+            z: 0f32,
+            radius,
+            stars: Some(stars),
+        })
     })
 }
 
-use rand::distributions::Weighted;
+use rand::distributions::{Distribution, Weighted, WeightedChoice};
+use std::cmp::Ordering;
 
+use rand::Rng;
+use space::{random_in_sphere, Located, Position};
+use std::collections::HashSet;
 use std::iter::Iterator;
 
-fn fill_star_sector(
-    conn: &PgConnection,
+/// Mixed into sector seed to get a separate random stream for names
+const NAMES_STREAM: i64 = 0x6e61_6d65;
+/// Mixed into sector seed to get a separate random stream for re-attached links
+const REATTACH_STREAM: i64 = 0x6c69_6e6b;
+
+/// Child of a freshly filled sector
+struct FilledChild {
+    object: GalaxyObject,
+    position: Position,
+    weight: u32,
+}
+
+/// Fills sector with children, placed inside the sector's sphere.
+/// Everything random here comes from the sector's seed, so the same sector
+/// is always filled the same way.
+fn fill_star_sector<S: GalaxyStore>(
+    store: &S,
     sector: &StarSector,
+    config: &GenerationConfig,
     stars: f32,
-    rad: f32,
-) -> Result<(), Error> {
-    conn.transaction::<(), Error, _>(|| {
+) -> GalaxyResult<Vec<FilledChild>> {
+    store.transaction(|| {
+        let mut rng = tools::seeded_rng(sector.seed);
+
         // Amount of sub-sectors
-        let sub_amount = 10;
+        let sub_amount = config.branching_factor;
         // Amount of stars in each of sub-sector
         let sub_stars = stars / (sub_amount as f32);
         // Amount of links between stars inside this sector
-        let links = stars * 4f32;
+        let links = stars * config.link_density;
 
         // We decide whether we create concrete stars or a sub-future
-        let create_stars = sub_stars < 10.0;
+        let create_stars = sub_stars < config.leaf_threshold;
 
         let child_amount = if create_stars {
             stars.round() as i32
@@ -98,215 +263,739 @@ fn fill_star_sector(
             sub_amount
         };
 
-        // Create galaxy objects
-        let star_galaxy_objects: Vec<GalaxyObject> = {
-            let new_star_galaxy_objects = (0..child_amount)
-                .map(|_| NewGalaxyObject {
-                    obj_type: if create_stars {
-                            GalaxyObjectType::System 
-                        } else {
-                            GalaxyObjectType::SectorFuture
-                        }
-                })
-                .collect::<Vec<_>>();
+        // Children are placed so that they fit inside of this sector
+        let child_radius = sector.radius / (child_amount as f32).powf(config.radius_scaling);
+        let placement_radius = (sector.radius - child_radius).max(0f32);
+        let positions = (0..child_amount)
+            .map(|_| random_in_sphere(&mut rng, sector.position(), placement_radius))
+            .collect::<Vec<_>>();
 
-            use schema::galaxy_objects::dsl::*;
-            diesel::insert_into(galaxy_objects)
-                .values(&new_star_galaxy_objects)
-                .get_results(conn)?
+        // Create galaxy objects
+        let child_type = if create_stars {
+            GalaxyObjectType::System
+        } else {
+            GalaxyObjectType::SectorFuture
         };
+        let star_galaxy_objects =
+            store.create_galaxy_objects(&vec![child_type; child_amount as usize])?;
 
         // Create children
         let children = if create_stars {
+            // Names come from their own stream, so that they don't
+            // affect the rest of the layout
+            let mut names_rng = tools::seeded_rng(sector.seed ^ NAMES_STREAM);
+            let mut generator = names::name_generator(config.name_style, &mut names_rng);
+            let mut unique_names = names::UniqueNames::new(&mut *generator, HashSet::new());
+            let mut star_names = (0..child_amount)
+                .map(|_| unique_names.generate(&mut names_rng))
+                .collect::<Vec<_>>();
+            rename_taken_star_names(store, sector, &mut star_names)?;
+
             // Create stars themselves
             let new_stars = star_galaxy_objects
                 .iter()
-                .map(|g: &GalaxyObject| NewStarSystem {
-                    id: g.id,
-                    name: "StarName".to_string(),
-                    sector_id: sector.id,
+                .zip(positions.iter())
+                .zip(star_names)
+                .map(|((g, position), name): ((&GalaxyObject, &Position), String)| {
+                    NewStarSystem {
+                        id: g.id,
+                        name,
+                        sector_id: sector.id,
+                        x: position.x,
+                        y: position.y,
+                        z: position.z,
+                        radius: child_radius,
+                    }
                 })
                 .collect::<Vec<_>>();
-            use schema::star_systems::dsl::*;
-            diesel::insert_into(star_systems)
-                .values(&new_stars)
-                .get_results::<StarSystem>(conn)?
+            store
+                .insert_systems(&new_stars)?
                 .iter()
                 .map(GalaxyObject::from)
                 .collect::<Vec<GalaxyObject>>()
         } else {
-            use std::f32;
-            let sub_radius = rad / (sub_amount as f32).cbrt();
-
             // Create sub futures themselves
             let new_futures = star_galaxy_objects
                 .iter()
-                .map(|g: &GalaxyObject| NewStarSectorFuture {
-                    id: g.id,
-                    parent_id: sector.id,
-                    radius: sub_radius,
-                    stars: sub_stars,
+                .zip(positions.iter())
+                .map(|(g, position): (&GalaxyObject, &Position)| {
+                    NewStarSectorFuture {
+                        id: g.id,
+                        parent_id: sector.id,
+                        radius: child_radius,
+                        stars: sub_stars,
+                        seed: rng.gen(),
+                        x: position.x,
+                        y: position.y,
+                        z: position.z,
+                    }
                 })
                 .collect::<Vec<_>>();
-            use schema::star_sector_futures::dsl::*;
-            diesel::insert_into(star_sector_futures)
-                .values(&new_futures)
-                .get_results::<StarSectorFuture>(conn)?
+            store
+                .insert_futures(&new_futures)?
                 .iter()
                 .map(GalaxyObject::from)
                 .collect::<Vec<GalaxyObject>>()
         };
 
+        let filled_children = children
+            .into_iter()
+            .zip(positions)
+            .zip(tools::weights(config.weight_distribution, child_amount as usize, &mut rng))
+            .map(|((object, position), weight)| FilledChild {
+                object,
+                position,
+                weight,
+            })
+            .collect::<Vec<_>>();
+
         // Generate links
-        let mut children_weighted = children
+        let mut children_weighted = filled_children
             .iter()
-            .zip(self::tools::exp_weights(children.len()))
-            .map(|pair: (&GalaxyObject, u32)| {
-                let (child, weight) = pair;
-                Weighted::<GalaxyObject> {
-                    weight: weight,
-                    item: child.clone(),
-                }
+            .map(|child: &FilledChild| Weighted::<(GalaxyObject, Position)> {
+                weight: child.weight,
+                item: (child.object.clone(), child.position),
             })
             .collect::<Vec<_>>();
 
-        let new_links = generate_links(
-            &mut children_weighted.as_mut_slice(),
+        let new_links = link_strategies::link_generator(config.link_strategy).generate(
+            children_weighted.as_mut_slice(),
             links as usize,
             create_stars,
-            rand::thread_rng(),
+            &mut rng,
         );
+        if let Some(shortfall) = new_links.shortfall {
+            debug!(
+                "Sector {} got {} of {} links: {:?}",
+                sector.id,
+                new_links.produced(),
+                new_links.requested,
+                shortfall
+            );
+        }
 
-        use schema::star_links::dsl::*;
-        diesel::insert_into(star_links)
-            .values(&new_links)
-            .execute(conn)?;
+        store.insert_links(&new_links.links)?;
 
-        Ok(())
+        Ok(filled_children)
     })
 }
 
-pub fn generate_star_sector(
-    conn: &PgConnection,
+/// Gives names that other sectors of the galaxy already have a suffix from
+/// this sector's seed. Only the sector's own names are looked up, so filling
+/// doesn't get slower as the galaxy grows.
+fn rename_taken_star_names<S: GalaxyStore>(store: &S, sector: &StarSector, names: &mut [String]) -> GalaxyResult<()> {
+    let taken = store.get_taken_star_names(sector.config_id, names)?;
+    for i in 0..names.len() {
+        if !taken.contains(&names[i]) {
+            continue;
+        }
+        for alternative in names::alternatives(&names[i], sector.seed) {
+            let alternative = vec![alternative];
+            if !names.contains(&alternative[0])
+                && store.get_taken_star_names(sector.config_id, &alternative)?.is_empty()
+            {
+                names[i] = alternative.into_iter().next().unwrap();
+                break;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Connects every external link of a fulfilled future to one of the
+/// sector's new children, so that the galaxy graph stays connected
+fn reattach_links<S: GalaxyStore>(
+    store: &S,
+    sector: &StarSector,
+    links: &[StarLink],
+    children: &[FilledChild],
+    policy: LinkReattachPolicy,
+) -> GalaxyResult<usize> {
+    if children.is_empty() {
+        return Ok(0);
+    }
+
+    let mut rng = tools::seeded_rng(sector.seed ^ REATTACH_STREAM);
+    let mut children_weighted = children
+        .iter()
+        .enumerate()
+        .map(|(i, child)| Weighted {
+            weight: child.weight,
+            item: i,
+        })
+        .collect::<Vec<_>>();
+    let wc = WeightedChoice::new(&mut children_weighted);
+
+    // Links still lead to the future that the sector was made of
+    let future = GalaxyObject {
+        id: sector.id,
+        obj_type: GalaxyObjectType::SectorFuture,
+    };
+    let mut new_links = Vec::new();
+    for link in links {
+        let other = match link.other_side(&future) {
+            Some(other) => other,
+            None => continue,
+        };
+        // Loops to itself are inside of this sector now
+        if other == future {
+            continue;
+        }
+
+        let other_position = get_object_position(store, &other)?;
+        let child = match policy {
+            LinkReattachPolicy::Weighted => &children[wc.sample(&mut rng)],
+            LinkReattachPolicy::Nearest => {
+                children
+                    .iter()
+                    .min_by(|c1, c2| {
+                        c1.position
+                            .distance(&other_position)
+                            .partial_cmp(&c2.position.distance(&other_position))
+                            .unwrap_or(Ordering::Equal)
+                    })
+                    .unwrap()
+            }
+        };
+        // Link keeps its type, but now it's as long as to the child
+        new_links.push(NewStarLink::new(
+            &child.object,
+            &other,
+            child.position.distance(&other_position),
+            link.link_type,
+        ));
+    }
+
+    store.insert_links(&new_links)
+}
+
+/// Returns position of an object of any type
+pub fn get_object_position<S: GalaxyStore>(
+    store: &S,
+    object: &GalaxyObject,
+) -> GalaxyResult<Position> {
+    load_entity(store, object).map(|entity| entity.position())
+}
+
+/// Loads the row that the object stands for
+pub fn load_entity<S: GalaxyStore>(store: &S, object: &GalaxyObject) -> GalaxyResult<GalaxyEntity> {
+    load_entities(store, slice::from_ref(object)).map(|mut entities| entities.remove(0))
+}
+
+/// Loads rows that the objects stand for, in the same order, with one
+/// query per object type. Fails if any of the objects is not found.
+pub fn load_entities<S: GalaxyStore>(store: &S, objects: &[GalaxyObject]) -> GalaxyResult<Vec<GalaxyEntity>> {
+    let ids_of = |obj_type: GalaxyObjectType| {
+        objects
+            .iter()
+            .filter(|o| o.obj_type == obj_type)
+            .map(|o| o.id)
+            .collect::<Vec<_>>()
+    };
+    let mut loaded = HashMap::new();
+    let system_ids = ids_of(GalaxyObjectType::System);
+    if !system_ids.is_empty() {
+        for system in store.get_systems(&system_ids)? {
+            loaded.insert(GalaxyObject::from(&system), GalaxyEntity::System(system));
+        }
+    }
+    let sector_ids = ids_of(GalaxyObjectType::Sector);
+    if !sector_ids.is_empty() {
+        for sector in store.get_sectors(&sector_ids)? {
+            loaded.insert(GalaxyObject::from(&sector), GalaxyEntity::Sector(sector));
+        }
+    }
+    let future_ids = ids_of(GalaxyObjectType::SectorFuture);
+    if !future_ids.is_empty() {
+        for future in store.get_futures(&future_ids)? {
+            loaded.insert(GalaxyObject::from(&future), GalaxyEntity::SectorFuture(future));
+        }
+    }
+
+    objects
+        .iter()
+        .map(|object| {
+            loaded
+                .get(object)
+                .cloned()
+                .ok_or_else(|| GalaxyError::ObjectNotFound(object.clone()))
+        })
+        .collect()
+}
+
+/// Generates new sector centered at the origin. Generating sectors with
+/// the same seed and parameters always produces the same layout. Config is
+/// saved with the sector and used for all of its futures.
+pub fn generate_star_sector<S: GalaxyStore>(
+    store: &S,
     stars: f32,
     radius: f32,
     parent: Option<i32>,
-) -> Result<StarSector, Error> {
-    conn.transaction::<StarSector, Error, _>(|| {
-        let result = create_star_sector(conn, parent)?;
-        fill_star_sector(conn, &result, stars, radius)?;
+    seed: i64,
+    config: &GenerationConfig,
+) -> GalaxyResult<StarSector> {
+    if !stars.is_finite() || stars < 1f32 {
+        return Err(GalaxyError::InvalidGenerationParams("stars must be at least 1"));
+    }
+    if !radius.is_finite() || radius <= 0f32 {
+        return Err(GalaxyError::InvalidGenerationParams("radius must be positive"));
+    }
+    config.validate()?;
+
+    store.transaction(|| {
+        let result = create_star_sector(store, parent, seed, config, radius, stars)?;
+        fill_star_sector(store, &result, config, stars)?;
         Ok(result)
     })
 }
 
-pub fn get_star_sector_children_futures(
-    conn: &PgConnection,
+/// Returns config that sector's galaxy was generated with
+pub fn get_generation_config<S: GalaxyStore>(
+    store: &S,
     sector: &StarSector,
-) -> Result<Vec<StarSectorFuture>, Error> {
-    StarSectorFuture::belonging_to(sector).load(conn)
+) -> GalaxyResult<GenerationConfig> {
+    store.get_generation_config(sector.config_id)
 }
 
-pub fn get_links_for_object_ids(conn: &PgConnection, objects: Vec<i32>
-    ) -> Result<Vec<StarLink>, Error> {
-    use schema::star_links::dsl::*;
-    star_links
-        .filter(a_id.eq_any(objects.to_vec()))
-        .or_filter(b_id.eq_any(objects.to_vec()))
-        .load::<StarLink>(conn)
+pub fn get_star_sector_children_futures<S: GalaxyStore>(
+    store: &S,
+    sector: &StarSector,
+) -> GalaxyResult<Vec<StarSectorFuture>> {
+    store.get_child_futures(sector.id)
 }
 
-pub fn get_links_for_objects(conn: &PgConnection, objects: Vec<GalaxyObject>
-    ) -> Result<Vec<StarLink>, Error> {
+pub fn get_links_for_object_ids<S: GalaxyStore>(
+    store: &S,
+    objects: Vec<i32>,
+) -> GalaxyResult<Vec<StarLink>> {
+    store.get_links_for_objects(&objects)
+}
 
+pub fn get_links_for_objects<S: GalaxyStore>(
+    store: &S,
+    objects: Vec<GalaxyObject>,
+) -> GalaxyResult<Vec<StarLink>> {
     let ids = objects
         .iter()
-        .map(|obj| obj.id.clone())
+        .map(|obj| obj.id)
         .collect::<Vec<i32>>();
 
-    get_links_for_object_ids(conn, ids)
+    get_links_for_object_ids(store, ids)
 }
 
-fn delete_links_for_objects(conn: &PgConnection, objects: Vec<i32>) -> Result<usize, Error> {
-    use schema::star_links::dsl::*;
+/// Amounts of objects in a sector and all of its sub-sectors
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct SectorStats {
+    /// Sectors, including the sector itself
+    pub sectors: usize,
+    pub futures: usize,
+    pub systems: usize,
+    /// Links with futures or systems of the sector on both sides
+    pub links: usize,
+}
 
-    diesel::delete(
-        star_links
-            .filter(a_id.eq_any(objects.to_vec()))
-            .or_filter(b_id.eq_any(objects.to_vec())),
-    ).execute(conn)
+/// Counts everything inside of the sector with one query
+pub fn get_sector_stats<S: GalaxyStore>(store: &S, sector_id: i32) -> GalaxyResult<SectorStats> {
+    store.transaction(|| {
+        // Fails for missing sectors instead of counting nothing
+        store.get_sector(sector_id)?;
+        store.count_sector_subtree(sector_id)
+    })
 }
 
-fn delete_galaxy_objects(conn: &PgConnection, objects: Vec<i32>) -> Result<usize, Error> {
-    use schema::galaxy_objects::dsl::*;
+/// Amounts of rows removed by `delete_sector`
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct DeletedRows {
+    pub sectors: usize,
+    pub futures: usize,
+    pub systems: usize,
+    pub links: usize,
+    pub galaxy_objects: usize,
+}
 
-    diesel::delete(galaxy_objects.filter(id.eq_any(objects))).execute(conn)
+/// Deletes the sector with everything inside. Every table is cleaned with
+/// one statement, so the amount of queries doesn't depend on the size of
+/// the sector.
+pub fn delete_sector<S: GalaxyStore>(store: &S, sector_id: i32) -> GalaxyResult<DeletedRows> {
+    store.transaction(|| {
+        // Fails for missing sectors instead of deleting nothing
+        store.get_sector(sector_id)?;
+        store.delete_sector_subtree(sector_id, false)
+    })
 }
 
-fn delete_sector_futures(conn: &PgConnection, sector_id: i32) -> Result<usize, Error> {
-    use schema::star_sector_futures::dsl::*;
+/// Turns fulfilled sector back into the future it was made of, deleting
+/// everything inside. The future keeps the sector's id, seed, radius and
+/// amount of stars, so fulfilling it again generates the same content.
+/// Links that lead out of the sector are attached to the future. Sectors
+/// with players inside or flying to them are not collapsed.
+pub fn collapse_sector<S: GalaxyStore>(store: &S, sector_id: i32) -> GalaxyResult<StarSectorFuture> {
+    loop {
+        if let Some(future) = store.transaction(|| try_collapse_sector(store, sector_id))? {
+            return Ok(future);
+        }
+    }
+}
 
-    conn.transaction::<usize, Error, _>(|| {
-        let ids = star_sector_futures
-            .filter(parent_id.eq(sector_id))
-            .select(id)
-            .load(conn)?;
+/// Other ends of links that lead out of the subtree, in the order links
+/// were made in, so that the future re-attaches them the same way when
+/// it's fulfilled again
+fn external_ends<S: GalaxyStore>(store: &S, objects: &[GalaxyObject]) -> GalaxyResult<Vec<(GalaxyObject, LinkType)>> {
+    let ids = objects.iter().map(|o| o.id).collect::<HashSet<_>>();
+    let mut links = store.get_links_for_objects(&ids.iter().cloned().collect::<Vec<_>>())?;
+    links.sort_by_key(|link| link.id);
+    let mut result = Vec::new();
+    for link in links {
+        let (a, b) = (link.side_a(), link.side_b());
+        match (ids.contains(&a.id), ids.contains(&b.id)) {
+            (true, false) => result.push((b, link.link_type)),
+            (false, true) => result.push((a, link.link_type)),
+            _ => {}
+        }
+    }
+    Ok(result)
+}
 
-        delete_links_for_objects(conn, ids.to_vec())?;
+/// Ids of futures inside of the subtree and on the other side of its links
+fn subtree_future_ids(objects: &[GalaxyObject], external: &[(GalaxyObject, LinkType)]) -> Vec<i32> {
+    let mut ids = objects
+        .iter()
+        .chain(external.iter().map(|(other, _)| other))
+        .filter(|o| o.obj_type == GalaxyObjectType::SectorFuture)
+        .map(|o| o.id)
+        .collect::<Vec<_>>();
+    ids.sort();
+    ids.dedup();
+    ids
+}
 
-        diesel::delete(star_sector_futures
-                .filter(id.eq_any(ids.to_vec())))
-            .execute(conn)?;
+/// Collapses the sector, or returns nothing if futures it needs to lock
+/// changed while locking
+fn try_collapse_sector<S: GalaxyStore>(store: &S, sector_id: i32) -> GalaxyResult<Option<StarSectorFuture>> {
+    let cant_collapse = |reason| GalaxyError::CantCollapse { sector_id, reason };
+    let sector = store.get_sector(sector_id)?;
+    let parent_id = sector.parent_id.ok_or_else(|| cant_collapse("it's a root of a galaxy"))?;
+    let stars = sector.stars.ok_or_else(|| cant_collapse("its amount of stars is unknown"))?;
+
+    // Futures inside are locked, so that they aren't fulfilled meanwhile,
+    // and so are futures outside, because fulfilling them re-attaches the
+    // same links. As in `lock_future_with_neighbours`, locks are taken in
+    // the order of ids, and futures fulfilled before they were locked
+    // leave changes that need another try.
+    let objects = store.get_sector_subtree(sector_id)?;
+    let future_ids = subtree_future_ids(&objects, &external_ends(store, &objects)?);
+    let locked = store
+        .lock_futures(&future_ids)?
+        .iter()
+        .map(|f| f.id)
+        .collect::<HashSet<_>>();
+    let objects = store.get_sector_subtree(sector_id)?;
+    let external = external_ends(store, &objects)?;
+    if !subtree_future_ids(&objects, &external).iter().all(|id| locked.contains(id)) {
+        return Ok(None);
+    }
 
-        delete_galaxy_objects(conn, ids)
-    })
+    if !store.get_players_in_sector(sector_id)?.is_empty() {
+        return Err(cant_collapse("players are inside"));
+    }
+
+    store.delete_sector_subtree(sector_id, true)?;
+    store.update_galaxy_object_type(&GalaxyObject::from(&sector), GalaxyObjectType::SectorFuture)?;
+    let future = store
+        .insert_futures(&[NewStarSectorFuture {
+            id: sector.id,
+            parent_id,
+            radius: sector.radius,
+            stars,
+            seed: sector.seed,
+            x: sector.x,
+            y: sector.y,
+            z: sector.z,
+        }])?
+        .remove(0);
+
+    let future_object = GalaxyObject::from(&future);
+    let others = external.iter().map(|(other, _)| other.clone()).collect::<Vec<_>>();
+    let new_links = load_entities(store, &others)?
+        .iter()
+        .zip(&external)
+        .map(|(entity, (other, link_type))| {
+            NewStarLink::new(
+                &future_object,
+                other,
+                future.position().distance(&entity.position()),
+                *link_type,
+            )
+        })
+        .collect::<Vec<_>>();
+    store.insert_links(&new_links)?;
+    Ok(Some(future))
 }
 
-fn delete_sector_systems(conn: &PgConnection, sector: i32) -> Result<usize, Error> {
-    use schema::star_systems::dsl::*;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use store::MemoryStore;
 
-    conn.transaction::<usize, Error, _>(|| {
-        let ids = star_systems
-            .filter(sector_id.eq(sector))
-            .select(id)
-            .load(conn)?;
+    fn generate(store: &MemoryStore, seed: i64) -> StarSector {
+        generate_star_sector(store, 200f32, 1000f32, None, seed, &GenerationConfig::default()).unwrap()
+    }
 
-        delete_links_for_objects(conn, ids.to_vec())?;
+    #[test]
+    fn generates_in_memory() {
+        let store = MemoryStore::new();
+        let sector = generate(&store, 1);
 
-        diesel::delete(star_systems
-                .filter(id.eq_any(ids.to_vec())))
-            .execute(conn)?;
+        let futures = get_star_sector_children_futures(&store, &sector).unwrap();
+        assert_eq!(futures.len(), 10);
+        // Sector itself and its futures
+        assert_eq!(store.galaxy_object_count(), 11);
+        assert!(store.link_count() >= futures.len() - 1);
+    }
 
-        delete_galaxy_objects(conn, ids)
-    })
-}
+    /// Names and positions of systems inside of the sector
+    fn systems_inside(store: &MemoryStore, sector_id: i32) -> Vec<(String, Position)> {
+        let ids = store
+            .get_sector_subtree(sector_id)
+            .unwrap()
+            .iter()
+            .filter(|o| o.obj_type == GalaxyObjectType::System)
+            .map(|o| o.id)
+            .collect::<Vec<_>>();
+        let mut systems = store
+            .get_systems(&ids)
+            .unwrap()
+            .iter()
+            .map(|s| (s.name.clone(), s.position()))
+            .collect::<Vec<_>>();
+        systems.sort_by(|a, b| a.0.cmp(&b.0));
+        systems
+    }
 
-pub fn delete_sector(conn: &PgConnection, sector_id: i32) -> Result<(), Error> {
-    conn.transaction::<_, Error, _>(|| {
-        use schema::star_sectors::dsl::*;
+    /// Ids of objects on the other side of the object's links, except loops
+    fn neighbour_ids(store: &MemoryStore, object: &GalaxyObject) -> Vec<i32> {
+        let mut ids = store
+            .get_links_for_objects(&[object.id])
+            .unwrap()
+            .iter()
+            .filter_map(|link| link.other_side(object))
+            .filter(|other| other != object)
+            .map(|other| other.id)
+            .collect::<Vec<_>>();
+        ids.sort();
+        ids
+    }
 
-        // Delete child futures and systems
-        delete_sector_futures(conn, sector_id)?;
-        delete_sector_systems(conn, sector_id)?;
+    #[test]
+    fn collapsed_sector_is_fulfilled_the_same_again() {
+        let store = MemoryStore::new();
+        let root = generate_star_sector(&store, 2000f32, 1000f32, None, 5, &GenerationConfig::default()).unwrap();
+        let future = get_star_sector_children_futures(&store, &root).unwrap()[0].clone();
+        let objects = store.galaxy_object_count();
+        let links = neighbour_ids(&store, &GalaxyObject::from(&future));
+        let sector = fulfill_star_sector_future(&store, future.id).unwrap();
+        let child = get_star_sector_children_futures(&store, &sector).unwrap()[0].clone();
+        fulfill_star_sector_future(&store, child.id).unwrap();
+        let systems = systems_inside(&store, sector.id);
+        assert!(!systems.is_empty());
+
+        let collapsed = collapse_sector(&store, sector.id).unwrap();
+        assert_eq!(
+            (collapsed.id, collapsed.parent_id, collapsed.seed, collapsed.stars, collapsed.radius),
+            (future.id, future.parent_id, future.seed, future.stars, future.radius)
+        );
+        assert_eq!(store.get_galaxy_object(future.id).unwrap(), Some(GalaxyObject::from(&future)));
+        assert_eq!(store.galaxy_object_count(), objects);
+        assert_eq!(neighbour_ids(&store, &GalaxyObject::from(&future)), links);
+        assert_eq!(validation::validate_galaxy(&store).unwrap(), vec![]);
+
+        fulfill_star_sector_future(&store, future.id).unwrap();
+        fulfill_star_sector_future(&store, get_star_sector_children_futures(&store, &sector).unwrap()[0].id).unwrap();
+        assert_eq!(systems_inside(&store, sector.id), systems);
+    }
 
-        // Find child sectors
-        let child_sectors: Vec<StarSector> = star_sectors
-            .filter(parent_id.eq(sector_id))
-            .for_update()
-            .load(conn)?;
+    #[test]
+    fn roots_and_visited_sectors_are_not_collapsed() {
+        let store = MemoryStore::new();
+        let root = generate(&store, 6);
+        match collapse_sector(&store, root.id) {
+            Err(GalaxyError::CantCollapse { sector_id, .. }) if sector_id == root.id => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
 
-        // Recursively delete child sectors;
-        for c in child_sectors {
-            try!(delete_sector(conn, c.id));
+        let future = get_star_sector_children_futures(&store, &root).unwrap()[0].clone();
+        let sector = fulfill_star_sector_future(&store, future.id).unwrap();
+        players::register_player(&store, 1, "Tester").unwrap();
+        players::spawn_player(&store, 1, sector.id).unwrap();
+        match collapse_sector(&store, sector.id) {
+            Err(GalaxyError::CantCollapse { sector_id, .. }) if sector_id == sector.id => {}
+            result => panic!("Unexpected result {:?}", result),
         }
+    }
 
-        delete_links_for_objects(conn, vec![sector_id])?;
+    #[test]
+    fn sectors_with_arriving_players_are_not_collapsed() {
+        let store = MemoryStore::new();
+        let root = generate(&store, 6);
+        let futures = get_star_sector_children_futures(&store, &root).unwrap();
+        let sector = fulfill_star_sector_future(&store, futures[0].id).unwrap();
+        let other = fulfill_star_sector_future(&store, futures[1].id).unwrap();
+        players::register_player(&store, 1, "Tester").unwrap();
+        let from = players::spawn_player(&store, 1, other.id).unwrap();
+        let to = store.get_child_systems(sector.id).unwrap()[0].clone();
+        store
+            .insert_jump(&Jump {
+                telegram_id: 1,
+                from_id: from.id,
+                to_id: to.id,
+                departed_at: chrono::Utc::now().naive_utc(),
+                arrives_at: chrono::Utc::now().naive_utc(),
+            })
+            .unwrap();
+
+        assert_eq!(store.get_players_in_sector(sector.id).unwrap().len(), 1);
+        assert_eq!(store.get_players_in_sector(other.id).unwrap().len(), 1);
+        assert!(collapse_sector(&store, sector.id).is_err());
+        store.delete_jumps(&[1]).unwrap();
+        assert!(store.get_players_in_sector(sector.id).unwrap().is_empty());
+        collapse_sector(&store, sector.id).unwrap();
+    }
 
-        // Delete sector
-        diesel::delete(star_sectors.filter(id.eq(sector_id))).execute(conn)?;
+    #[test]
+    fn taken_names_get_sector_suffix() {
+        let store = MemoryStore::new();
+        let root = generate(&store, 4);
+        let futures = get_star_sector_children_futures(&store, &root).unwrap();
+        let sector = fulfill_star_sector_future(&store, futures[0].id).unwrap();
+        let other = fulfill_star_sector_future(&store, futures[1].id).unwrap();
+        let taken = store
+            .get_child_systems(sector.id)
+            .unwrap()
+            .into_iter()
+            .map(|s| s.name)
+            .collect::<Vec<_>>();
 
-        // Delete sector's galaxy object
-        delete_galaxy_objects(conn, vec![sector_id])?;
+        let mut names = taken.clone();
+        rename_taken_star_names(&store, &other, &mut names).unwrap();
 
-        Ok(())
-    })
-}
\ No newline at end of file
+        let suffix = &names::designation(other.seed)[..2];
+        for (name, taken) in names.iter().zip(&taken) {
+            assert!(name.starts_with(&format!("{} {}", taken, suffix)), "{}", name);
+        }
+    }
+
+    #[test]
+    fn names_are_unique_across_galaxy() {
+        let store = MemoryStore::new();
+        let sector = generate(&store, 4);
+        for future in get_star_sector_children_futures(&store, &sector).unwrap() {
+            fulfill_star_sector_future(&store, future.id).unwrap();
+        }
+        let names = systems_inside(&store, sector.id)
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect::<Vec<_>>();
+        assert_eq!(names.iter().collect::<HashSet<_>>().len(), names.len());
+    }
+
+    #[test]
+    fn same_seed_generates_same_sector_in_memory() {
+        let layout = |seed| {
+            let store = MemoryStore::new();
+            let sector = generate(&store, seed);
+            let future = get_star_sector_children_futures(&store, &sector).unwrap()[0].clone();
+            fulfill_star_sector_future(&store, future.id).unwrap();
+            store
+                .get_child_systems(future.id)
+                .unwrap()
+                .iter()
+                .map(|s| (s.name.clone(), s.position()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(layout(7), layout(7));
+        assert_ne!(layout(7), layout(8));
+    }
+
+    #[test]
+    fn resolve_object_fulfills_future_once() {
+        let store = MemoryStore::new();
+        let sector = generate(&store, 3);
+        let future = GalaxyObject::from(&get_star_sector_children_futures(&store, &sector).unwrap()[0]);
+
+        let system = resolve_object(&store, &future).unwrap();
+        assert_eq!(system.sector_id, future.id);
+        let objects = store.galaxy_object_count();
+
+        // Already fulfilled future is resolved to the same system
+        assert_eq!(resolve_object(&store, &future).unwrap().id, system.id);
+        assert_eq!(resolve_object(&store, &GalaxyObject::from(&system)).unwrap().id, system.id);
+        assert_eq!(store.galaxy_object_count(), objects);
+    }
+
+    #[test]
+    fn get_sector_stats_counts_whole_galaxy() {
+        let store = MemoryStore::new();
+        let sector = generate(&store, 5);
+        let future = get_star_sector_children_futures(&store, &sector).unwrap()[0].clone();
+        fulfill_star_sector_future(&store, future.id).unwrap();
+
+        let stats = get_sector_stats(&store, sector.id).unwrap();
+        assert_eq!(stats.sectors, 2);
+        assert_eq!(stats.futures, 9);
+        assert_eq!(stats.systems, 20);
+        assert_eq!(stats.links, store.link_count());
+        assert_eq!(stats.sectors + stats.futures + stats.systems, store.galaxy_object_count());
+    }
+
+    #[test]
+    fn get_sector_stats_counts_links_inside_only() {
+        let store = MemoryStore::new();
+        let sector = generate(&store, 5);
+        let future = get_star_sector_children_futures(&store, &sector).unwrap()[0].clone();
+        let child = fulfill_star_sector_future(&store, future.id).unwrap();
+        let systems = store.get_child_systems(child.id).unwrap();
+        let ids = systems.iter().map(|s| s.id).collect::<Vec<_>>();
+        let links = store.get_links_for_objects(&ids).unwrap();
+
+        let stats = get_sector_stats(&store, child.id).unwrap();
+        assert_eq!(stats.systems, systems.len());
+        let inside = links
+            .iter()
+            .filter(|l| ids.contains(&l.a_id) && ids.contains(&l.b_id))
+            .count();
+        assert!(inside < links.len());
+        assert_eq!(stats.links, inside);
+    }
+
+    #[test]
+    fn delete_sector_empties_memory_store() {
+        let store = MemoryStore::new();
+        let sector = generate(&store, 3);
+        let future = get_star_sector_children_futures(&store, &sector).unwrap()[0].clone();
+        fulfill_star_sector_future(&store, future.id).unwrap();
+
+        delete_sector(&store, sector.id).unwrap();
+        assert_eq!(store.galaxy_object_count(), 0);
+        assert_eq!(store.link_count(), 0);
+    }
+
+    #[test]
+    fn links_are_as_long_as_distance_between_their_ends() {
+        let store = MemoryStore::new();
+        let sector = generate(&store, 4);
+        let futures = get_star_sector_children_futures(&store, &sector).unwrap();
+        fulfill_star_sector_future(&store, futures[0].id).unwrap();
+
+        let mut ids = futures.iter().map(|f| f.id).collect::<Vec<_>>();
+        ids.extend(store.get_child_systems(futures[0].id).unwrap().iter().map(|s| s.id));
+        let links = store.get_links_for_objects(&ids).unwrap();
+        assert!(!links.is_empty());
+        for link in links {
+            let distance = get_object_position(&store, &link.side_a())
+                .unwrap()
+                .distance(&get_object_position(&store, &link.side_b()).unwrap());
+            assert!((link.length - distance).abs() < 1e-3, "{:?} is not {} long", link, distance);
+            assert_eq!(link.cost, link.link_type.cost(link.length));
+        }
+    }
+}
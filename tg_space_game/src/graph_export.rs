@@ -0,0 +1,301 @@
+//! Link graph of a sector in Graphviz DOT and GraphML.
+//!
+//! Futures and systems are nodes, links are edges, and sectors are drawn as
+//! clusters (nested graphs in GraphML) around their children. Only links
+//! with both ends inside of the sector are included.
+
+use super::*;
+
+use export::{export_galaxy, GalaxyExport};
+use schema::types::LinkType;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::str::FromStr;
+use store::GalaxyStore;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum GraphFormat {
+    Dot,
+    GraphMl,
+}
+
+impl FromStr for GraphFormat {
+    type Err = GalaxyError;
+
+    fn from_str(s: &str) -> GalaxyResult<GraphFormat> {
+        match s {
+            "dot" => Ok(GraphFormat::Dot),
+            "graphml" => Ok(GraphFormat::GraphMl),
+            _ => Err(config::ConfigError::Invalid("graph format must be dot or graphml").into()),
+        }
+    }
+}
+
+/// Renders the sector's subgraph in the format
+pub fn export_graph<S: GalaxyStore>(store: &S, sector_id: i32, format: GraphFormat) -> GalaxyResult<String> {
+    let export = export_galaxy(store, sector_id)?;
+    let tree = Tree::new(&export);
+    Ok(match format {
+        GraphFormat::Dot => dot(&tree),
+        GraphFormat::GraphMl => graphml(&tree),
+    })
+}
+
+/// Shape and color of a node of the type
+fn node_style(obj_type: GalaxyObjectType) -> (&'static str, &'static str) {
+    match obj_type {
+        GalaxyObjectType::System => ("ellipse", "#f5d76e"),
+        GalaxyObjectType::SectorFuture => ("box", "#a9cce3"),
+        GalaxyObjectType::Sector => ("folder", "#d5d8dc"),
+    }
+}
+
+/// Line style and color of an edge of the type
+fn edge_style(link_type: LinkType) -> (&'static str, &'static str) {
+    match link_type {
+        LinkType::Hyperlane => ("solid", "#566573"),
+        LinkType::Wormhole => ("dashed", "#8e44ad"),
+        LinkType::UnstableRoute => ("dotted", "#c0392b"),
+    }
+}
+
+struct Node {
+    id: i32,
+    obj_type: GalaxyObjectType,
+    label: String,
+}
+
+/// Exported sector with children grouped by the sectors they are inside of
+struct Tree<'a> {
+    export: &'a GalaxyExport,
+    sectors: HashMap<i32, Vec<&'a StarSector>>,
+    nodes: HashMap<i32, Vec<Node>>,
+}
+
+impl<'a> Tree<'a> {
+    fn new(export: &'a GalaxyExport) -> Tree<'a> {
+        let mut sectors: HashMap<i32, Vec<&StarSector>> = HashMap::new();
+        for sector in &export.sectors {
+            if let (Some(parent_id), false) = (sector.parent_id, sector.id == export.root_id) {
+                sectors.entry(parent_id).or_default().push(sector);
+            }
+        }
+        let mut nodes: HashMap<i32, Vec<Node>> = HashMap::new();
+        for future in &export.futures {
+            nodes.entry(future.parent_id).or_default().push(Node {
+                id: future.id,
+                obj_type: GalaxyObjectType::SectorFuture,
+                label: format!("future {}\n{:.0} stars", future.id, future.stars),
+            });
+        }
+        for system in &export.systems {
+            nodes.entry(system.sector_id).or_default().push(Node {
+                id: system.id,
+                obj_type: GalaxyObjectType::System,
+                label: system.name.clone(),
+            });
+        }
+        Tree {
+            export,
+            sectors,
+            nodes,
+        }
+    }
+
+    fn root(&self) -> Option<&'a StarSector> {
+        self.export.sectors.iter().find(|s| s.id == self.export.root_id)
+    }
+
+    fn child_sectors(&self, sector_id: i32) -> &[&'a StarSector] {
+        self.sectors.get(&sector_id).map_or(&[], |s| s.as_slice())
+    }
+
+    fn child_nodes(&self, sector_id: i32) -> &[Node] {
+        self.nodes.get(&sector_id).map_or(&[], |n| n.as_slice())
+    }
+}
+
+fn dot_escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+fn dot(tree: &Tree) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "graph \"sector {}\" {{", tree.export.root_id);
+    let _ = writeln!(out, "  node [style=filled];");
+    if let Some(root) = tree.root() {
+        dot_cluster(tree, root, 1, &mut out);
+    }
+    for link in &tree.export.links {
+        let (style, color) = edge_style(link.link_type);
+        let _ = writeln!(
+            out,
+            "  n{} -- n{} [style={}, color=\"{}\", label=\"{:.1}\", tooltip=\"{} {:.1}\"];",
+            link.a_id,
+            link.b_id,
+            style,
+            color,
+            link.cost,
+            link.link_type.as_str(),
+            link.length
+        );
+    }
+    out.push_str("}\n");
+    out
+}
+
+fn dot_cluster(tree: &Tree, sector: &StarSector, depth: usize, out: &mut String) {
+    let pad = "  ".repeat(depth);
+    let (_, color) = node_style(GalaxyObjectType::Sector);
+    let _ = writeln!(out, "{}subgraph cluster_{} {{", pad, sector.id);
+    let _ = writeln!(out, "{}  label=\"sector {}\"; color=\"{}\";", pad, sector.id, color);
+    for node in tree.child_nodes(sector.id) {
+        let (shape, color) = node_style(node.obj_type);
+        let _ = writeln!(
+            out,
+            "{}  n{} [label=\"{}\", shape={}, fillcolor=\"{}\"];",
+            pad,
+            node.id,
+            dot_escape(&node.label),
+            shape,
+            color
+        );
+    }
+    for child in tree.child_sectors(sector.id) {
+        dot_cluster(tree, child, depth + 1, out);
+    }
+    let _ = writeln!(out, "{}}}", pad);
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+fn graphml(tree: &Tree) -> String {
+    let mut out = String::new();
+    out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str("<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n");
+    for &(id, target, attr_type) in &[
+        ("type", "node", "string"),
+        ("label", "node", "string"),
+        ("shape", "node", "string"),
+        ("color", "all", "string"),
+        ("link_type", "edge", "string"),
+        ("style", "edge", "string"),
+        ("length", "edge", "double"),
+        ("cost", "edge", "double"),
+    ] {
+        let _ = writeln!(
+            out,
+            "  <key id=\"{0}\" for=\"{1}\" attr.name=\"{0}\" attr.type=\"{2}\"/>",
+            id, target, attr_type
+        );
+    }
+    let _ = writeln!(out, "  <graph id=\"g{}\" edgedefault=\"undirected\">", tree.export.root_id);
+    if let Some(root) = tree.root() {
+        graphml_sector(tree, root, 2, &mut out);
+    }
+    for link in &tree.export.links {
+        let (style, color) = edge_style(link.link_type);
+        let _ = writeln!(out, "    <edge id=\"e{}\" source=\"n{}\" target=\"n{}\">", link.id, link.a_id, link.b_id);
+        graphml_data(&mut out, 3, "link_type", link.link_type.as_str());
+        graphml_data(&mut out, 3, "style", style);
+        graphml_data(&mut out, 3, "color", color);
+        graphml_data(&mut out, 3, "length", &link.length.to_string());
+        graphml_data(&mut out, 3, "cost", &link.cost.to_string());
+        out.push_str("    </edge>\n");
+    }
+    out.push_str("  </graph>\n</graphml>\n");
+    out
+}
+
+fn graphml_sector(tree: &Tree, sector: &StarSector, depth: usize, out: &mut String) {
+    let pad = "  ".repeat(depth);
+    let _ = writeln!(out, "{}<node id=\"n{}\">", pad, sector.id);
+    graphml_node_data(out, depth + 1, GalaxyObjectType::Sector, &format!("sector {}", sector.id));
+    let _ = writeln!(out, "{}  <graph id=\"n{}:\" edgedefault=\"undirected\">", pad, sector.id);
+    for node in tree.child_nodes(sector.id) {
+        let _ = writeln!(out, "{}    <node id=\"n{}\">", pad, node.id);
+        graphml_node_data(out, depth + 3, node.obj_type, &node.label);
+        let _ = writeln!(out, "{}    </node>", pad);
+    }
+    for child in tree.child_sectors(sector.id) {
+        graphml_sector(tree, child, depth + 2, out);
+    }
+    let _ = writeln!(out, "{}  </graph>", pad);
+    let _ = writeln!(out, "{}</node>", pad);
+}
+
+fn graphml_node_data(out: &mut String, depth: usize, obj_type: GalaxyObjectType, label: &str) {
+    let (shape, color) = node_style(obj_type);
+    graphml_data(out, depth, "type", obj_type.as_str());
+    graphml_data(out, depth, "label", label);
+    graphml_data(out, depth, "shape", shape);
+    graphml_data(out, depth, "color", color);
+}
+
+fn graphml_data(out: &mut String, depth: usize, key: &str, value: &str) {
+    let _ = writeln!(out, "{}<data key=\"{}\">{}</data>", "  ".repeat(depth), key, xml_escape(value));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use store::fixtures::fulfilled_galaxy;
+    use store::MemoryStore;
+
+    /// Amount of objects of the type in the galaxy
+    fn count(store: &MemoryStore, sector: &StarSector, obj_type: GalaxyObjectType) -> usize {
+        let subtree = store.get_sector_subtree(sector.id).unwrap();
+        subtree.iter().filter(|o| o.obj_type == obj_type).count()
+    }
+
+    #[test]
+    fn dot_has_clusters_nodes_and_edges() {
+        let store = MemoryStore::new();
+        let sector = fulfilled_galaxy(&store, 4);
+        let dot = export_graph(&store, sector.id, GraphFormat::Dot).unwrap();
+
+        assert!(dot.starts_with(&format!("graph \"sector {}\" {{", sector.id)));
+        let sectors = count(&store, &sector, GalaxyObjectType::Sector);
+        assert_eq!(dot.matches("subgraph cluster_").count(), sectors);
+        assert_eq!(dot.matches(" -- ").count(), store.link_count());
+        let futures = count(&store, &sector, GalaxyObjectType::SectorFuture);
+        assert_eq!(dot.matches("shape=box").count(), futures);
+        assert_eq!(dot.matches("{").count(), dot.matches("}").count());
+    }
+
+    #[test]
+    fn graphml_nests_sectors() {
+        let store = MemoryStore::new();
+        let sector = fulfilled_galaxy(&store, 4);
+        let graphml = export_graph(&store, sector.id, GraphFormat::GraphMl).unwrap();
+
+        // Every sector has a graph inside, and the root one is inside another
+        let sectors = count(&store, &sector, GalaxyObjectType::Sector);
+        assert_eq!(graphml.matches("<graph ").count(), sectors + 1);
+        assert_eq!(graphml.matches("<graph ").count(), graphml.matches("</graph>").count());
+        assert_eq!(graphml.matches("<node ").count(), graphml.matches("</node>").count());
+        assert_eq!(graphml.matches("<edge ").count(), store.link_count());
+        assert_eq!(graphml.matches("<data key=\"type\">sector</data>").count(), sectors);
+    }
+
+    #[test]
+    fn unknown_format_is_config_error() {
+        assert_eq!("dot".parse::<GraphFormat>().unwrap(), GraphFormat::Dot);
+        match "svg".parse::<GraphFormat>() {
+            Err(GalaxyError::Config(config::ConfigError::Invalid(_))) => {}
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn labels_are_escaped() {
+        assert_eq!(dot_escape("a \"b\"\n"), "a \\\"b\\\"\\n");
+        assert_eq!(xml_escape("<a & 'b'>"), "&lt;a &amp; &apos;b&apos;&gt;");
+    }
+}
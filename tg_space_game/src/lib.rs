@@ -1,5 +1,8 @@
+#![allow(non_local_definitions)]
+
 #[macro_use]
 extern crate diesel;
+extern crate chrono;
 #[macro_use]
 extern crate diesel_migrations;
 extern crate dotenv;
@@ -7,6 +10,14 @@ extern crate rand;
 #[macro_use]
 extern crate log;
 extern crate env_logger;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+extern crate toml;
+extern crate ureq;
+#[cfg(test)]
+extern crate proptest;
 
 use diesel::pg::PgConnection;
 use diesel::prelude::*;
@@ -15,27 +26,51 @@ use diesel_migrations::RunMigrationsError;
 use dotenv::dotenv;
 use std::env;
 
+mod connection;
+pub mod error;
+pub mod export;
 pub mod models;
 pub mod schema;
+pub mod config;
 pub mod galaxy_objects;
+pub mod graph_export;
+pub mod link_strategies;
+pub mod movement;
+pub mod names;
+pub mod players;
+pub mod pregeneration;
+pub mod routing;
+pub mod space;
+pub mod store;
+pub mod telegram;
+pub mod validation;
 
 mod tools;
 
-use self::schema::types::GalaxyObjectType;
+use self::config::GenerationConfig;
+use self::schema::types::{
+    GalaxyObjectType, LinkReattachPolicy, LinkStrategy, NameStyle, WeightDistribution,
+};
 
-embed_migrations!();
+pub use self::connection::GalaxyConnection;
+pub use self::error::{GalaxyError, GalaxyResult};
 
-pub fn run_migrations(
-    connection: &PgConnection,
-    out: &mut std::io::Stdout,
+pub fn run_migrations<W: std::io::Write>(
+    connection: &GalaxyConnection,
+    out: &mut W,
 ) -> Result<(), RunMigrationsError> {
-    embedded_migrations::run_with_output(connection, out)
+    connection.run_migrations(out)
 }
 
-pub fn establish_connection() -> PgConnection {
+/// Reads `DATABASE_URL` from environment or `.env`
+pub fn database_url() -> GalaxyResult<String> {
     dotenv().ok();
-    let database_url = env::var("DATABASE_URL").expect("Please set DATABASE_URL");
-    PgConnection::establish(&database_url).expect(&format!("Error connecting to {}", database_url))
+    env::var("DATABASE_URL").map_err(|_| config::ConfigError::MissingVar("DATABASE_URL").into())
+}
+
+/// Connects to `DATABASE_URL`, which can point to either Postgres or SQLite
+pub fn establish_connection() -> GalaxyResult<GalaxyConnection> {
+    Ok(GalaxyConnection::establish(&database_url()?)?)
 }
 
 use self::models::*;
\ No newline at end of file
@@ -0,0 +1,419 @@
+//! Strategies of linking children of a sector to each other.
+//!
+//! Every strategy keeps the children connected. Spatial strategies link
+//! children that are close to each other, so that links don't criss-cross
+//! the whole sector. All randomness comes from the rng they're given, so
+//! links of a sector are determined by its seed.
+
+use super::*;
+
+use rand::distributions::{Distribution, Weighted, WeightedChoice};
+use rand::{Rng, RngCore};
+use space::Position;
+use std::cmp::Ordering;
+use std::collections::BTreeSet;
+
+/// Child of a sector with its position and link weight
+pub type LinkElement = Weighted<(GalaxyObject, Position)>;
+
+pub trait LinkGenerator {
+    /// Links `elements` with about `link_amount` links. Unless links are
+    /// `unique`, several links may connect the same elements. Elements
+    /// are always connected, even if it takes more links than requested.
+    fn generate(
+        &self,
+        elements: &mut [LinkElement],
+        link_amount: usize,
+        unique: bool,
+        rng: &mut dyn RngCore,
+    ) -> GeneratedLinks;
+}
+
+/// Chain of elements in random order and extra links between elements
+/// chosen by weight, no matter how far they are
+pub struct RandomLinks;
+
+impl LinkGenerator for RandomLinks {
+    fn generate(
+        &self,
+        elements: &mut [LinkElement],
+        link_amount: usize,
+        unique: bool,
+        rng: &mut dyn RngCore,
+    ) -> GeneratedLinks {
+        generate_links(elements, link_amount, unique, rng)
+    }
+}
+
+/// Minimum spanning tree and extra links from elements chosen by weight to
+/// their nearest elements that they aren't linked to yet
+pub struct SpanningTreeLinks;
+
+impl LinkGenerator for SpanningTreeLinks {
+    fn generate(
+        &self,
+        elements: &mut [LinkElement],
+        link_amount: usize,
+        unique: bool,
+        rng: &mut dyn RngCore,
+    ) -> GeneratedLinks {
+        let positions = positions(elements);
+        let mut edges = spanning_tree(&positions).into_iter().collect::<BTreeSet<_>>();
+
+        let wanted = link_amount.min(max_unique_links(positions.len()));
+        if edges.len() < wanted {
+            let mut indices = elements
+                .iter()
+                .enumerate()
+                .map(|(i, e)| Weighted {
+                    weight: e.weight.max(1),
+                    item: i,
+                })
+                .collect::<Vec<_>>();
+            let wc = WeightedChoice::new(&mut indices);
+            let mut attempts = wanted * wanted;
+            while edges.len() < wanted && attempts > 0 {
+                let from = wc.sample(rng);
+                let to = nearest(&positions, from)
+                    .into_iter()
+                    .find(|&to| !edges.contains(&edge(from, to)));
+                if let Some(to) = to {
+                    edges.insert(edge(from, to));
+                }
+                attempts -= 1;
+            }
+        }
+
+        links_for_edges(elements, &edges, link_amount, unique, rng)
+    }
+}
+
+/// Every element is linked to its nearest elements, as many of them as
+/// needed for `link_amount`. Minimum spanning tree is added, so that
+/// distant clusters are linked too.
+pub struct NearestNeighboursLinks;
+
+impl LinkGenerator for NearestNeighboursLinks {
+    fn generate(
+        &self,
+        elements: &mut [LinkElement],
+        link_amount: usize,
+        unique: bool,
+        rng: &mut dyn RngCore,
+    ) -> GeneratedLinks {
+        let positions = positions(elements);
+        let neighbours = neighbour_count(positions.len(), link_amount);
+        let mut edges = spanning_tree(&positions).into_iter().collect::<BTreeSet<_>>();
+        for from in 0..positions.len() {
+            for to in nearest(&positions, from).into_iter().take(neighbours) {
+                edges.insert(edge(from, to));
+            }
+        }
+
+        links_for_edges(elements, &edges, link_amount, unique, rng)
+    }
+}
+
+/// Gabriel graph: two elements are linked when no other element lies in
+/// the sphere that has the link as its diameter. Amount of links is
+/// defined by the layout, `link_amount` only adds parallel links.
+pub struct GabrielLinks;
+
+impl LinkGenerator for GabrielLinks {
+    fn generate(
+        &self,
+        elements: &mut [LinkElement],
+        link_amount: usize,
+        unique: bool,
+        rng: &mut dyn RngCore,
+    ) -> GeneratedLinks {
+        let positions = positions(elements);
+        // Spanning tree is a part of Gabriel graph anyway, it's added
+        // against rounding errors
+        let mut edges = spanning_tree(&positions).into_iter().collect::<BTreeSet<_>>();
+        for a in 0..positions.len() {
+            for b in a + 1..positions.len() {
+                let middle = Position::new(
+                    (positions[a].x + positions[b].x) / 2.0,
+                    (positions[a].y + positions[b].y) / 2.0,
+                    (positions[a].z + positions[b].z) / 2.0,
+                );
+                let radius = positions[a].distance(&positions[b]) * 0.5;
+                let empty = (0..positions.len())
+                    .filter(|&c| c != a && c != b)
+                    .all(|c| positions[c].distance(&middle) >= radius);
+                if empty {
+                    edges.insert((a, b));
+                }
+            }
+        }
+
+        links_for_edges(elements, &edges, link_amount, unique, rng)
+    }
+}
+
+/// Creates generator for the strategy
+pub fn link_generator(strategy: LinkStrategy) -> Box<dyn LinkGenerator> {
+    match strategy {
+        LinkStrategy::Random => Box::new(RandomLinks),
+        LinkStrategy::SpanningTree => Box::new(SpanningTreeLinks),
+        LinkStrategy::NearestNeighbours => Box::new(NearestNeighboursLinks),
+        LinkStrategy::Gabriel => Box::new(GabrielLinks),
+    }
+}
+
+fn positions(elements: &[LinkElement]) -> Vec<Position> {
+    elements.iter().map(|e| e.item.1).collect()
+}
+
+/// Edge between two elements, smaller index first
+fn edge(a: usize, b: usize) -> (usize, usize) {
+    (a.min(b), a.max(b))
+}
+
+/// Neighbours of every element, so that there are about `link_amount`
+/// links in total
+fn neighbour_count(elements: usize, link_amount: usize) -> usize {
+    if elements < 2 {
+        return 0;
+    }
+    link_amount.div_ceil(elements).clamp(1, elements - 1)
+}
+
+/// Other elements, nearest first
+fn nearest(positions: &[Position], from: usize) -> Vec<usize> {
+    let mut others = (0..positions.len()).filter(|&i| i != from).collect::<Vec<_>>();
+    others.sort_by(|&a, &b| {
+        positions[a]
+            .distance(&positions[from])
+            .partial_cmp(&positions[b].distance(&positions[from]))
+            .unwrap_or(Ordering::Equal)
+            .then(a.cmp(&b))
+    });
+    others
+}
+
+/// Euclidean minimum spanning tree by Prim's algorithm
+fn spanning_tree(positions: &[Position]) -> Vec<(usize, usize)> {
+    let mut result = Vec::new();
+    if positions.is_empty() {
+        return result;
+    }
+    let mut in_tree = vec![false; positions.len()];
+    // Closest tree element and distance to it for every element
+    let mut closest = vec![(0, f32::INFINITY); positions.len()];
+    let mut current = 0;
+    in_tree[current] = true;
+    for _ in 1..positions.len() {
+        for i in 0..positions.len() {
+            let distance = positions[i].distance(&positions[current]);
+            if !in_tree[i] && distance < closest[i].1 {
+                closest[i] = (current, distance);
+            }
+        }
+        let next = (0..positions.len())
+            .filter(|&i| !in_tree[i])
+            .min_by(|&a, &b| {
+                closest[a]
+                    .1
+                    .partial_cmp(&closest[b].1)
+                    .unwrap_or(Ordering::Equal)
+            })
+            .unwrap();
+        in_tree[next] = true;
+        result.push(edge(closest[next].0, next));
+        current = next;
+    }
+    result
+}
+
+/// Turns edges into links. Unless links are unique, random edges get
+/// parallel links until there are `link_amount` of them.
+fn links_for_edges(
+    elements: &[LinkElement],
+    edges: &BTreeSet<(usize, usize)>,
+    link_amount: usize,
+    unique: bool,
+    rng: &mut dyn RngCore,
+) -> GeneratedLinks {
+    let mut edges = edges.iter().cloned().collect::<Vec<_>>();
+    if !unique && !edges.is_empty() {
+        while edges.len() < link_amount {
+            let parallel = edges[rng.gen_range(0, edges.len())];
+            edges.push(parallel);
+        }
+    }
+    let links = edges
+        .into_iter()
+        .map(|(a, b)| NewStarLink::between(&elements[a].item, &elements[b].item, rng))
+        .collect();
+    GeneratedLinks::new(links, link_amount, elements.len(), unique)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use std::collections::HashMap;
+
+    const STRATEGIES: &[LinkStrategy] = &[
+        LinkStrategy::Random,
+        LinkStrategy::SpanningTree,
+        LinkStrategy::NearestNeighbours,
+        LinkStrategy::Gabriel,
+    ];
+
+    fn elements(points: &[(f32, f32, f32, u32)]) -> Vec<LinkElement> {
+        points
+            .iter()
+            .enumerate()
+            .map(|(i, &(x, y, z, weight))| Weighted {
+                weight,
+                item: (
+                    GalaxyObject {
+                        id: i as i32,
+                        obj_type: GalaxyObjectType::System,
+                    },
+                    Position::new(x, y, z),
+                ),
+            })
+            .collect()
+    }
+
+    fn degrees(links: &[NewStarLink]) -> HashMap<i32, usize> {
+        let mut result = HashMap::new();
+        for link in links {
+            *result.entry(link.a_id).or_insert(0) += 1;
+            *result.entry(link.b_id).or_insert(0) += 1;
+        }
+        result
+    }
+
+    fn connected(amount: usize, links: &[NewStarLink]) -> bool {
+        let mut component = (0..amount).collect::<Vec<_>>();
+        fn root(component: &mut [usize], i: usize) -> usize {
+            let mut i = i;
+            while component[i] != i {
+                component[i] = component[component[i]];
+                i = component[i];
+            }
+            i
+        }
+        for link in links {
+            let a = root(&mut component, link.a_id as usize);
+            let b = root(&mut component, link.b_id as usize);
+            component[a] = b;
+        }
+        let first = root(&mut component, 0);
+        (0..amount).all(|i| root(&mut component, i) == first)
+    }
+
+    /// Points on a line, one unit apart
+    fn line(amount: usize) -> Vec<LinkElement> {
+        elements(&(0..amount).map(|i| (i as f32, 0.0, 0.0, 1)).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn spatial_strategies_link_only_neighbours_on_a_line() {
+        for strategy in &[LinkStrategy::SpanningTree, LinkStrategy::Gabriel] {
+            let mut rng = tools::seeded_rng(1);
+            let links = link_generator(*strategy).generate(&mut line(10), 9, true, &mut rng).links;
+            assert_eq!(links.len(), 9);
+            assert!(links.iter().all(|l| (l.length - 1.0).abs() < 1e-6), "{:?}", strategy);
+        }
+    }
+
+    #[test]
+    fn same_seed_links_the_same_way() {
+        let points = (0..20)
+            .map(|i| ((i * 7 % 13) as f32, (i * 5 % 11) as f32, (i % 3) as f32, i + 1))
+            .collect::<Vec<_>>();
+        for strategy in STRATEGIES {
+            let generate = || {
+                link_generator(*strategy)
+                    .generate(&mut elements(&points), 40, true, &mut tools::seeded_rng(5))
+                    .links
+                    .iter()
+                    .map(|l| (l.a_id, l.b_id, l.link_type))
+                    .collect::<Vec<_>>()
+            };
+            assert_eq!(generate(), generate());
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn every_strategy_keeps_elements_connected(
+            points in prop::collection::vec((-100f32..100f32, -100f32..100f32, -100f32..100f32, 1u32..10), 1..30),
+            link_amount in 0usize..120,
+            unique in any::<bool>(),
+            seed in any::<i64>(),
+        ) {
+            for strategy in STRATEGIES {
+                let result = link_generator(*strategy).generate(
+                    &mut elements(&points),
+                    link_amount,
+                    unique,
+                    &mut tools::seeded_rng(seed),
+                );
+                prop_assert_eq!(result.shortfall.is_some(), result.produced() < link_amount);
+                prop_assert!(connected(points.len(), &result.links), "{:?} left elements apart", strategy);
+                for link in &result.links {
+                    prop_assert!((link.cost - link.link_type.cost(link.length)).abs() < 1e-3);
+                }
+            }
+        }
+
+        #[test]
+        fn unique_links_have_sane_degrees(
+            points in prop::collection::vec((-100f32..100f32, -100f32..100f32, -100f32..100f32, 1u32..10), 2..30),
+            link_amount in 0usize..120,
+            seed in any::<i64>(),
+        ) {
+            let amount = points.len();
+            for strategy in STRATEGIES {
+                let links = link_generator(*strategy).generate(
+                    &mut elements(&points),
+                    link_amount,
+                    true,
+                    &mut tools::seeded_rng(seed),
+                ).links;
+                let degrees = degrees(&links);
+                let unique_links = links.iter().collect::<std::collections::HashSet<_>>();
+                prop_assert_eq!(unique_links.len(), links.len());
+                prop_assert!(links.iter().all(|l| l.a_id != l.b_id));
+                prop_assert_eq!(degrees.len(), amount);
+                prop_assert!(degrees.values().all(|&d| d >= 1 && d < amount));
+
+                match *strategy {
+                    // A tree unless more links are asked for
+                    LinkStrategy::SpanningTree if link_amount < amount => {
+                        prop_assert_eq!(links.len(), amount - 1);
+                    }
+                    LinkStrategy::NearestNeighbours => {
+                        let neighbours = neighbour_count(amount, link_amount);
+                        prop_assert!(degrees.values().all(|&d| d >= neighbours));
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        #[test]
+        fn non_unique_links_fill_link_amount(
+            points in prop::collection::vec((-100f32..100f32, -100f32..100f32, -100f32..100f32, 1u32..10), 2..30),
+            link_amount in 0usize..120,
+            seed in any::<i64>(),
+        ) {
+            for strategy in STRATEGIES {
+                let links = link_generator(*strategy).generate(
+                    &mut elements(&points),
+                    link_amount,
+                    false,
+                    &mut tools::seeded_rng(seed),
+                ).links;
+                prop_assert!(links.len() >= link_amount.max(points.len() - 1));
+            }
+        }
+    }
+}
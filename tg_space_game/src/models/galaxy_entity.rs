@@ -0,0 +1,45 @@
+use super::*;
+
+/// Row that a galaxy object stands for
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GalaxyEntity {
+    System(StarSystem),
+    Sector(StarSector),
+    SectorFuture(StarSectorFuture),
+}
+
+impl GalaxyEntity {
+    pub fn obj_type(&self) -> GalaxyObjectType {
+        match *self {
+            GalaxyEntity::System(_) => GalaxyObjectType::System,
+            GalaxyEntity::Sector(_) => GalaxyObjectType::Sector,
+            GalaxyEntity::SectorFuture(_) => GalaxyObjectType::SectorFuture,
+        }
+    }
+
+    /// Sector that the entity is inside of, none for galaxy roots
+    pub fn parent_id(&self) -> Option<i32> {
+        match *self {
+            GalaxyEntity::System(ref s) => Some(s.sector_id),
+            GalaxyEntity::Sector(ref s) => s.parent_id,
+            GalaxyEntity::SectorFuture(ref f) => Some(f.parent_id),
+        }
+    }
+}
+
+impl From<&GalaxyEntity> for GalaxyObject {
+    fn from(entity: &GalaxyEntity) -> Self {
+        match *entity {
+            GalaxyEntity::System(ref s) => GalaxyObject::from(s),
+            GalaxyEntity::Sector(ref s) => GalaxyObject::from(s),
+            GalaxyEntity::SectorFuture(ref f) => GalaxyObject::from(f),
+        }
+    }
+}
+
+impl ToGalaxyObject for GalaxyEntity {
+    fn to_galaxy_object(&self) -> GalaxyObject {
+        GalaxyObject::from(self)
+    }
+}
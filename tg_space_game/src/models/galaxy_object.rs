@@ -1,6 +1,7 @@
 use super::*;
 
-#[derive(Queryable, Hash, PartialEq, Eq, Clone, Debug)]
+#[derive(Queryable, QueryableByName, Hash, PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
+#[table_name = "galaxy_objects"]
 pub struct GalaxyObject {
     pub id: i32,
     pub obj_type: GalaxyObjectType,
@@ -16,7 +17,7 @@ impl ToGalaxyObject for GalaxyObject {
     }
 }
 
-impl<'a> From<&'a StarSector> for GalaxyObject {
+impl From<&StarSector> for GalaxyObject {
     fn from(sector: &StarSector) -> Self {
         GalaxyObject {
             id: sector.id,
@@ -31,7 +32,7 @@ impl ToGalaxyObject for StarSector {
     }
 }
 
-impl<'a> From<&'a StarSectorFuture> for GalaxyObject {
+impl From<&StarSectorFuture> for GalaxyObject {
     fn from(sector: &StarSectorFuture) -> Self {
         GalaxyObject {
             id: sector.id,
@@ -46,7 +47,7 @@ impl ToGalaxyObject for StarSectorFuture {
     }
 }
 
-impl<'a> From<&'a StarSystem> for GalaxyObject {
+impl From<&StarSystem> for GalaxyObject {
     fn from(sector: &StarSystem) -> Self {
         GalaxyObject {
             id: sector.id,
@@ -2,13 +2,17 @@ use super::schema::types::*;
 use super::schema::*;
 
 pub use self::galaxy_object::*;
+pub use self::galaxy_entity::*;
 pub use self::star_sector::*;
 pub use self::star_system::*;
 pub use self::star_sector_future::*;
 pub use self::star_link::*;
+pub use self::player::*;
 
 mod galaxy_object;
+mod galaxy_entity;
 mod star_sector;
 mod star_system;
 mod star_sector_future;
-mod star_link;
\ No newline at end of file
+mod star_link;
+mod player;
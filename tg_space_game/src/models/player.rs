@@ -0,0 +1,34 @@
+use super::*;
+
+use chrono::NaiveDateTime;
+
+#[derive(Identifiable, Queryable, QueryableByName, Clone, Debug, Serialize)]
+#[primary_key(telegram_id)]
+#[table_name = "players"]
+pub struct Player {
+    pub telegram_id: i64,
+    pub name: String,
+    pub created_at: NaiveDateTime,
+    /// Galaxy object where the player is, none until spawned
+    pub location_id: Option<i32>,
+}
+
+#[derive(Insertable)]
+#[table_name = "players"]
+pub struct NewPlayer {
+    pub telegram_id: i64,
+    pub name: String,
+    pub created_at: NaiveDateTime,
+}
+
+/// Jump of a player's ship along a star link, that is still in flight
+#[derive(Identifiable, Queryable, Insertable, Clone, Debug, PartialEq, Serialize)]
+#[primary_key(telegram_id)]
+#[table_name = "jumps"]
+pub struct Jump {
+    pub telegram_id: i64,
+    pub from_id: i32,
+    pub to_id: i32,
+    pub departed_at: NaiveDateTime,
+    pub arrives_at: NaiveDateTime,
+}
@@ -1,12 +1,17 @@
 use super::*;
 
-#[derive(Identifiable, Queryable, Debug)]
+#[derive(Identifiable, Queryable, Clone, Debug, Serialize, Deserialize)]
 pub struct StarLink {
     pub id: i32,
     pub a_id: i32,
     pub a_obj_type: GalaxyObjectType,
     pub b_id: i32,
     pub b_obj_type: GalaxyObjectType,
+    /// Distance between the ends
+    pub length: f32,
+    pub link_type: LinkType,
+    /// Price of traversing the link, used for routing and travel time
+    pub cost: f32,
 }
 
 #[derive(Insertable, Debug)]
@@ -16,40 +21,45 @@ pub struct NewStarLink {
     pub a_obj_type: GalaxyObjectType,
     pub b_id: i32,
     pub b_obj_type: GalaxyObjectType,
+    /// Distance between the ends
+    pub length: f32,
+    pub link_type: LinkType,
+    /// Price of traversing the link, used for routing and travel time
+    pub cost: f32,
 }
 
+use rand::distributions::{Distribution, Weighted, WeightedChoice};
+use rand::Rng;
+use space::Position;
+use std::cmp;
 use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
 
-impl NewStarLink {
-    pub fn new(a: &GalaxyObject, b: &GalaxyObject) -> NewStarLink {
-        NewStarLink {
-            a_id: a.id,
-            a_obj_type: a.obj_type,
-            b_id: b.id,
-            b_obj_type: b.obj_type
-        }
-    }
+/// Ends of a star link. Links are undirected, so links with swapped
+/// sides are the same link.
+pub trait LinkEnds {
+    fn side_a(&self) -> GalaxyObject;
 
-    // TODO make these methods a trait and implement this trait for both NewStarLink and StarLink
-    pub fn side_a(&self) -> GalaxyObject {
-        GalaxyObject {
-            id: self.a_id,
-            obj_type: self.a_obj_type
-        }
-    }
+    fn side_b(&self) -> GalaxyObject;
 
-    pub fn side_b(&self) -> GalaxyObject {
-        GalaxyObject {
-            id: self.b_id,
-            obj_type: self.b_obj_type
+    /// Returns the end opposite to `object`, or none if the link doesn't
+    /// touch it. Loops lead back to the object itself.
+    fn other_side(&self, object: &GalaxyObject) -> Option<GalaxyObject> {
+        if self.side_a() == *object {
+            Some(self.side_b())
+        } else if self.side_b() == *object {
+            Some(self.side_a())
+        } else {
+            None
         }
     }
-}
 
-use std::cmp;
+    fn touches(&self, object: &GalaxyObject) -> bool {
+        self.side_a() == *object || self.side_b() == *object
+    }
 
-impl PartialEq for NewStarLink {
-    fn eq(&self, other: &NewStarLink) -> bool {
+    /// Compares ends regardless of their order
+    fn same_ends<L: LinkEnds>(&self, other: &L) -> bool {
         (
             self.side_a() == other.side_a() &&
             self.side_b() == other.side_b()
@@ -59,12 +69,9 @@ impl PartialEq for NewStarLink {
             self.side_b() == other.side_a()
         )
     }
-}
-
-use std::hash::{Hash, Hasher};
 
-impl Hash for NewStarLink {
-    fn hash<H: Hasher>(&self, state: &mut H) {
+    /// Hashes ends regardless of their order
+    fn hash_ends<H: Hasher>(&self, state: &mut H) {
         // Create side hashers
         let mut hasher_a = DefaultHasher::new();
         let mut hasher_b = DefaultHasher::new();
@@ -83,71 +90,269 @@ impl Hash for NewStarLink {
     }
 }
 
-impl Eq for NewStarLink {}
+/// Implements undirected equality and hashing for a link type
+macro_rules! link_ends_eq {
+    ($link:ty) => {
+        impl PartialEq for $link {
+            fn eq(&self, other: &$link) -> bool {
+                self.same_ends(other)
+            }
+        }
 
-use rand::distributions::{Distribution, Weighted, WeightedChoice};
-use rand::Rng;
+        impl Eq for $link {}
+
+        impl Hash for $link {
+            fn hash<H: Hasher>(&self, state: &mut H) {
+                self.hash_ends(state)
+            }
+        }
+    };
+}
+
+/// Chance of a generated link to be a wormhole
+const WORMHOLE_CHANCE: f32 = 0.05;
+/// Chance of a generated link to be an unstable route
+const UNSTABLE_ROUTE_CHANCE: f32 = 0.1;
+
+/// No link is cheaper than its length times this
+pub const MIN_COST_FACTOR: f32 = 0.25;
+
+impl LinkType {
+    /// Cost of a unit of length
+    pub fn cost_factor(&self) -> f32 {
+        match *self {
+            LinkType::Hyperlane => 1.0,
+            LinkType::Wormhole => MIN_COST_FACTOR,
+            LinkType::UnstableRoute => 2.0,
+        }
+    }
+
+    pub fn cost(&self, length: f32) -> f32 {
+        length * self.cost_factor()
+    }
+
+    fn random<R: Rng + ?Sized>(rng: &mut R) -> LinkType {
+        let roll = rng.gen::<f32>();
+        if roll < WORMHOLE_CHANCE {
+            LinkType::Wormhole
+        } else if roll < WORMHOLE_CHANCE + UNSTABLE_ROUTE_CHANCE {
+            LinkType::UnstableRoute
+        } else {
+            LinkType::Hyperlane
+        }
+    }
+}
+
+impl NewStarLink {
+    pub fn new(a: &GalaxyObject, b: &GalaxyObject, length: f32, link_type: LinkType) -> NewStarLink {
+        NewStarLink {
+            a_id: a.id,
+            a_obj_type: a.obj_type,
+            b_id: b.id,
+            b_obj_type: b.obj_type,
+            length,
+            link_type,
+            cost: link_type.cost(length)
+        }
+    }
+
+    /// Link between positioned objects with a random type
+    pub fn between<R: Rng + ?Sized>(
+        a: &(GalaxyObject, Position),
+        b: &(GalaxyObject, Position),
+        rng: &mut R,
+    ) -> NewStarLink {
+        NewStarLink::new(&a.0, &b.0, a.1.distance(&b.1), LinkType::random(rng))
+    }
+}
+
+impl LinkEnds for NewStarLink {
+    fn side_a(&self) -> GalaxyObject {
+        GalaxyObject {
+            id: self.a_id,
+            obj_type: self.a_obj_type
+        }
+    }
+
+    fn side_b(&self) -> GalaxyObject {
+        GalaxyObject {
+            id: self.b_id,
+            obj_type: self.b_obj_type
+        }
+    }
+}
+
+link_ends_eq!(NewStarLink);
+
+/// Persisted links are equal when they connect the same objects, so ids
+/// aren't compared
+impl LinkEnds for StarLink {
+    fn side_a(&self) -> GalaxyObject {
+        GalaxyObject {
+            id: self.a_id,
+            obj_type: self.a_obj_type
+        }
+    }
+
+    fn side_b(&self) -> GalaxyObject {
+        GalaxyObject {
+            id: self.b_id,
+            obj_type: self.b_obj_type
+        }
+    }
+}
+
+link_ends_eq!(StarLink);
+
+/// Why fewer links were generated than requested
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkShortfall {
+    /// There is nothing to link
+    NoElements,
+    /// Every pair of elements is linked already, and links must be unique
+    NotEnoughPairs,
+    /// Strategy doesn't link elements that are far from each other
+    StrategyLimit,
+}
+
+/// Links generated for a sector along with the budget they were made for
+#[derive(Debug)]
+pub struct GeneratedLinks {
+    pub links: Vec<NewStarLink>,
+    /// Amount of links that was asked for
+    pub requested: usize,
+    /// Set when there are fewer links than requested
+    pub shortfall: Option<LinkShortfall>,
+}
+
+impl GeneratedLinks {
+    /// Explains the difference between requested and generated links
+    pub fn new(links: Vec<NewStarLink>, requested: usize, elements: usize, unique: bool) -> GeneratedLinks {
+        let shortfall = if links.len() >= requested {
+            None
+        } else if elements == 0 {
+            Some(LinkShortfall::NoElements)
+        } else if unique && links.len() >= max_unique_links(elements) {
+            Some(LinkShortfall::NotEnoughPairs)
+        } else {
+            Some(LinkShortfall::StrategyLimit)
+        };
+        GeneratedLinks {
+            links,
+            requested,
+            shortfall,
+        }
+    }
+
+    pub fn produced(&self) -> usize {
+        self.links.len()
+    }
 
+    /// Amount of requested links that weren't generated
+    pub fn missing(&self) -> usize {
+        self.requested.saturating_sub(self.produced())
+    }
+}
+
+/// Amount of pairs of elements
+pub fn max_unique_links(elements: usize) -> usize {
+    elements * elements.saturating_sub(1) / 2
+}
+
+/// Links positioned objects, so that every object is reachable, and adds
+/// extra links between objects chosen by weight, until there are
+/// `link_amount` links. Links get their lengths from the positions and
+/// random types.
+///
+/// Objects are always linked in a chain, so with `link_amount` below
+/// that there are more links than requested. Unique links never connect
+/// an object to itself or the same objects twice, so there are no more
+/// of them than pairs of objects. Other links may be loops, even of a
+/// single object. Without objects there are no links at all.
 pub fn generate_links<R: Rng>(
-    mut elements: &mut [Weighted<GalaxyObject>],
+    elements: &mut [Weighted<(GalaxyObject, Position)>],
     link_amount: usize,
     unique: bool,
     mut rng: R,
-) -> Vec<NewStarLink> 
+) -> GeneratedLinks
 {
     info!("Elements: {}", elements.len());
 
     let mut result: Vec<NewStarLink> = Vec::new();
+    if elements.is_empty() {
+        return GeneratedLinks::new(result, link_amount, 0, unique);
+    }
     rng.shuffle(elements);
 
     // Required links, so that graph is linked
     let min_links = elements.len() - 1;
     for i in 0..min_links {
-        result.push(NewStarLink::new(&elements[i].item, &elements[i + 1].item));
+        let link = NewStarLink::between(&elements[i].item, &elements[i + 1].item, &mut rng);
+        result.push(link);
     }
 
     info!("Min links created: {}", min_links);
     info!("Result with min links: {:?}", result);
     // Extra links
     let mut links_left = if unique {
-        let max_links = elements.len() * (elements.len() - 1) / 2;
-        cmp::min(link_amount, max_links) - min_links
+        cmp::min(link_amount, max_unique_links(elements.len())).saturating_sub(min_links)
     } else {
         info!("Link amount: {}", link_amount);
-        match link_amount.checked_sub(cmp::max(min_links, 0)) {
-            Some(x) => x,
-            None => 0
-        }
+        link_amount.saturating_sub(min_links)
     };
 
     info!("Links left: {}", links_left);
-    let mut attempts = links_left * links_left;
+    let mut attempts = links_left.saturating_mul(links_left);
+
+    {
+        let wc = WeightedChoice::new(elements);
 
-    let wc = WeightedChoice::new(&mut elements);
+        while links_left > 0 && attempts > 0 {
+            let side_a = wc.sample(&mut rng);
+            let side_b = wc.sample(&mut rng);
+            let link = NewStarLink::between(&side_a, &side_b, &mut rng);
+            info!("Link candidate: {:?}", link);
 
-    while links_left > 0 && attempts > 0 {
-        let side_a = wc.sample(&mut rng);
-        let side_b = wc.sample(&mut rng);
-        let link = NewStarLink::new(&side_a, &side_b);
-        info!("Link candidate: {:?}", link);
+            if !unique || (side_a != side_b && !result.contains(&link)) {
+                result.push(link);
+                links_left -= 1;
+                info!("Candidate suitable");
+            }
 
-        if !unique || (side_a != side_b && !result.contains(&link)) {
-            result.push(link);
-            links_left -= 1;
-            info!("Candidate suitable");
+            attempts -= 1;
+            info!("Attempts left: {}", attempts);
         }
+    }
 
-        attempts -= 1;
-        info!("Attempts left: {}", attempts);
+    // Only unique links get here: when few pairs are left, weighted
+    // choice keeps hitting linked ones, so the rest is taken from the
+    // pairs that aren't linked yet
+    if links_left > 0 {
+        info!("Attempts exhausted, {} links left", links_left);
+        let mut unlinked = Vec::new();
+        for a in 0..elements.len() {
+            for b in a + 1..elements.len() {
+                let ends = NewStarLink::new(&elements[a].item.0, &elements[b].item.0, 0.0, LinkType::Hyperlane);
+                if !result.contains(&ends) {
+                    unlinked.push((a, b));
+                }
+            }
+        }
+        rng.shuffle(&mut unlinked);
+        for (a, b) in unlinked.into_iter().take(links_left) {
+            result.push(NewStarLink::between(&elements[a].item, &elements[b].item, &mut rng));
+        }
     }
 
-    result
+    GeneratedLinks::new(result, link_amount, elements.len(), unique)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use super::super::super::env_logger;
+    use proptest::prelude::*;
     use rand::rngs::mock::StepRng;
 
     #[test]
@@ -162,24 +367,26 @@ mod tests {
             obj_type: GalaxyObjectType::Sector
         };
 
-        let mut elements: [Weighted<GalaxyObject>; 2] = [
-            Weighted::<GalaxyObject>{
+        let mut elements: [Weighted<(GalaxyObject, Position)>; 2] = [
+            Weighted::<(GalaxyObject, Position)>{
                 weight: 1,
-                item: item1.clone()
+                item: (item1.clone(), Position::new(0.0, 0.0, 0.0))
             },
-            Weighted::<GalaxyObject>{
+            Weighted::<(GalaxyObject, Position)>{
                 weight: 1,
-                item: item2.clone()
+                item: (item2.clone(), Position::new(3.0, 4.0, 0.0))
             }
         ];
-        let result = generate_links(&mut elements, 0usize, false, rng);
+        let result = generate_links(&mut elements, 0usize, false, rng).links;
         assert_eq!(result.len(), 1);
-        assert_eq!(result[0], NewStarLink::new(&item1, &item2));
+        assert_eq!(result[0], NewStarLink::new(&item1, &item2, 5.0, LinkType::Hyperlane));
+        assert_eq!(result[0].length, 5.0);
+        assert_eq!(result[0].cost, result[0].link_type.cost(5.0));
     }
 
     #[test]
     fn generate_links_creates_non_unique_links() {
-        let _ = env_logger::init();
+        let _ = env_logger::try_init();
         let rng = StepRng::new(0, 1);
         let item = GalaxyObject {
             id: 1,
@@ -187,12 +394,129 @@ mod tests {
         };
 
         let mut elements = vec![
-            Weighted::<GalaxyObject>{
+            Weighted::<(GalaxyObject, Position)>{
                 weight: 1,
-                item: item
+                item: (item, Position::new(0.0, 0.0, 0.0))
             }
         ];
         let result = generate_links(&mut elements, 10usize, false, rng);
-        assert_eq!(result.len(), 10);
+        assert_eq!(result.links.len(), 10);
+        assert_eq!(result.shortfall, None);
+    }
+
+    #[test]
+    fn link_ends_are_undirected() {
+        use std::collections::HashSet;
+
+        let a = GalaxyObject {
+            id: 1,
+            obj_type: GalaxyObjectType::System
+        };
+        let b = GalaxyObject {
+            id: 2,
+            obj_type: GalaxyObjectType::SectorFuture
+        };
+        let c = GalaxyObject {
+            id: 3,
+            obj_type: GalaxyObjectType::System
+        };
+        let saved = StarLink {
+            id: 10,
+            a_id: b.id,
+            a_obj_type: b.obj_type,
+            b_id: a.id,
+            b_obj_type: a.obj_type,
+            length: 1.0,
+            link_type: LinkType::Hyperlane,
+            cost: 1.0
+        };
+        let same = StarLink {
+            id: 11,
+            ..saved.clone()
+        };
+
+        assert_eq!(saved.other_side(&a), Some(b.clone()));
+        assert_eq!(saved.other_side(&b), Some(a.clone()));
+        assert_eq!(saved.other_side(&c), None);
+        assert!(saved.touches(&a) && !saved.touches(&c));
+        assert!(saved.same_ends(&NewStarLink::new(&a, &b, 2.0, LinkType::Wormhole)));
+        assert_eq!(saved, same);
+
+        let links: HashSet<NewStarLink> = vec![
+            NewStarLink::new(&a, &b, 1.0, LinkType::Hyperlane),
+            NewStarLink::new(&b, &a, 1.0, LinkType::Hyperlane),
+        ]
+            .into_iter()
+            .collect();
+        assert_eq!(links.len(), 1);
+    }
+
+    fn positioned(amount: usize) -> Vec<Weighted<(GalaxyObject, Position)>> {
+        (0..amount)
+            .map(|i| Weighted {
+                weight: i as u32 % 3 + 1,
+                item: (
+                    GalaxyObject {
+                        id: i as i32,
+                        obj_type: GalaxyObjectType::System
+                    },
+                    Position::new(i as f32, (i * i) as f32, 0.0)
+                )
+            })
+            .collect()
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn generate_links_without_elements_is_empty() {
+        let result = generate_links(&mut [], 5, true, StepRng::new(0, 1));
+        assert!(result.links.is_empty());
+        assert_eq!(result.missing(), 5);
+        assert_eq!(result.shortfall, Some(LinkShortfall::NoElements));
+    }
+
+    #[test]
+    fn generate_links_uses_every_pair_when_asked_for_more() {
+        let result = generate_links(&mut positioned(5), 100, true, ::tools::seeded_rng(1));
+        assert_eq!(result.produced(), 10);
+        assert_eq!(result.shortfall, Some(LinkShortfall::NotEnoughPairs));
+    }
+
+    proptest! {
+        #[test]
+        fn generate_links_follows_budget_policy(
+            amount in 0usize..12,
+            link_amount in 0usize..80,
+            unique in any::<bool>(),
+            seed in any::<i64>(),
+        ) {
+            let result = generate_links(&mut positioned(amount), link_amount, unique, ::tools::seeded_rng(seed));
+            let expected = match amount {
+                0 => 0,
+                _ if unique => cmp::max(amount - 1, cmp::min(link_amount, max_unique_links(amount))),
+                _ => cmp::max(amount - 1, link_amount),
+            };
+            prop_assert_eq!(result.produced(), expected);
+            prop_assert_eq!(result.requested, link_amount);
+            prop_assert_eq!(result.missing(), link_amount.saturating_sub(expected));
+            let shortfall = match result.missing() {
+                0 => None,
+                _ if amount == 0 => Some(LinkShortfall::NoElements),
+                _ => Some(LinkShortfall::NotEnoughPairs),
+            };
+            prop_assert_eq!(result.shortfall, shortfall);
+
+            if unique {
+                let links = result.links.iter().collect::<::std::collections::HashSet<_>>();
+                prop_assert_eq!(links.len(), result.produced());
+                prop_assert!(result.links.iter().all(|l| l.a_id != l.b_id));
+            }
+            // Chain goes through every element
+            let linked = result
+                .links
+                .iter()
+                .flat_map(|l| vec![l.a_id, l.b_id])
+                .collect::<::std::collections::HashSet<_>>();
+            prop_assert_eq!(linked.len(), if amount > 1 || (!unique && link_amount > 0) { amount } else { 0 });
+        }
+    }
+}
@@ -1,10 +1,19 @@
 use super::*;
 
-#[derive(Identifiable, Queryable, PartialEq, Associations)]
+#[derive(Identifiable, Queryable, PartialEq, Associations, Clone, Debug, Serialize, Deserialize)]
 #[belongs_to(StarSector, foreign_key = "parent_id")]
 pub struct StarSector {
     pub id: i32,
     pub parent_id: Option<i32>,
+    pub seed: i64,
+    pub config_id: i32,
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub radius: f32,
+    /// Amount of stars the sector was filled with, unknown for sectors
+    /// fulfilled before it was saved
+    pub stars: Option<f32>,
 }
 
 #[derive(Insertable)]
@@ -12,4 +21,13 @@ pub struct StarSector {
 pub struct NewStarSector {
     pub id: i32,
     pub parent_id: Option<i32>,
+    pub seed: i64,
+    pub config_id: i32,
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub radius: f32,
+    /// Amount of stars the sector was filled with, unknown for sectors
+    /// fulfilled before it was saved
+    pub stars: Option<f32>,
 }
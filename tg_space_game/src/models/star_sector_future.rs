@@ -1,12 +1,16 @@
 use super::*;
 
-#[derive(Identifiable, Queryable, Associations)]
+#[derive(Identifiable, Queryable, Associations, Clone, Debug, Serialize, Deserialize)]
 #[belongs_to(StarSector, foreign_key = "parent_id")]
 pub struct StarSectorFuture {
     pub id: i32,
     pub parent_id: i32,
     pub radius: f32,
     pub stars: f32,
+    pub seed: i64,
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
 }
 
 #[derive(Insertable)]
@@ -16,4 +20,8 @@ pub struct NewStarSectorFuture {
     pub parent_id: i32,
     pub radius: f32,
     pub stars: f32,
+    pub seed: i64,
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
 }
\ No newline at end of file
@@ -1,11 +1,15 @@
 use super::*;
 
-#[derive(Identifiable, Queryable, Associations)]
+#[derive(Identifiable, Queryable, Associations, Clone, Debug, Serialize, Deserialize)]
 #[belongs_to(StarSector, foreign_key = "sector_id")]
 pub struct StarSystem {
     pub id: i32,
     pub name: String,
     pub sector_id: i32,
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub radius: f32,
 }
 
 #[derive(Insertable)]
@@ -14,4 +18,8 @@ pub struct NewStarSystem {
     pub id: i32,
     pub name: String,
     pub sector_id: i32,
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub radius: f32,
 }
\ No newline at end of file
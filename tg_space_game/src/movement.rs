@@ -0,0 +1,239 @@
+use super::*;
+
+use chrono::{Duration, NaiveDateTime, Timelike};
+use diesel::result::DatabaseErrorKind;
+use galaxy_objects::{ensure_future_fulfilled, load_entity};
+use players::get_player_location;
+use std::cmp::Ordering;
+use store::GalaxyStore;
+
+/// Distance that a ship flies in a second
+pub const SHIP_SPEED: f32 = 1.0;
+
+/// Time that a jump over a link of given cost takes. Hyperlanes cost as
+/// much as they are long.
+pub fn travel_time(cost: f32) -> Duration {
+    Duration::milliseconds((cost / SHIP_SPEED * 1000f32).round() as i64)
+}
+
+/// Returns objects that are linked to the object
+pub fn get_neighbours<S: GalaxyStore>(store: &S, object: &GalaxyObject) -> GalaxyResult<Vec<GalaxyObject>> {
+    let mut result = Vec::new();
+    for link in store.get_links_for_objects(&[object.id])? {
+        if let Some(other) = link.other_side(object) {
+            if other != *object && !result.contains(&other) {
+                result.push(other);
+            }
+        }
+    }
+    Ok(result)
+}
+
+pub fn get_player_jump<S: GalaxyStore>(store: &S, telegram_id: i64) -> GalaxyResult<Option<Jump>> {
+    store.get_jump(telegram_id)
+}
+
+/// Sends player's ship over the star link to `target_id`. Player stays
+/// where they are until the jump is landed by `land_arrived_jumps`.
+/// Links to uncharted regions are followed by fulfilling futures until
+/// they lead to a star system.
+pub fn start_jump<S: GalaxyStore>(
+    store: &S,
+    telegram_id: i64,
+    target_id: i32,
+    now: NaiveDateTime,
+) -> GalaxyResult<Jump> {
+    let player = store
+        .get_player(telegram_id)?
+        .ok_or(GalaxyError::PlayerNotFound(telegram_id))?;
+    if store.get_jump(telegram_id)?.is_some() {
+        return Err(GalaxyError::AlreadyJumping(telegram_id));
+    }
+    let location = get_player_location(store, &player)?.ok_or(GalaxyError::PlayerNotSpawned(telegram_id))?;
+    let target = get_neighbours(store, &location)?
+        .into_iter()
+        .find(|n| n.id == target_id)
+        .ok_or(GalaxyError::NotLinked {
+            from: location.id,
+            to: target_id,
+        })?;
+    // Futures are fulfilled before the jump transaction, so that their
+    // locks aren't held until the jump is saved
+    let destination = resolve_neighbour(store, &location, target)?;
+
+    store.transaction(|| {
+        if store.get_jump(telegram_id)?.is_some() {
+            return Err(GalaxyError::AlreadyJumping(telegram_id));
+        }
+        let link = cheapest_link(store, &location, &destination)?;
+        // Databases keep microseconds, so returned jump is the same as saved one
+        let now = now.with_nanosecond(now.nanosecond() / 1000 * 1000).unwrap_or(now);
+        let jump = Jump {
+            telegram_id,
+            from_id: location.id,
+            to_id: destination.id,
+            departed_at: now,
+            arrives_at: now + travel_time(link.cost),
+        };
+        save_jump(store, &jump)?;
+        Ok(jump)
+    })
+}
+
+/// Saves the jump. Player can have one jump only, so a jump that another
+/// request saved after the check means the player is already jumping.
+fn save_jump<S: GalaxyStore>(store: &S, jump: &Jump) -> GalaxyResult<()> {
+    match store.insert_jump(jump) {
+        Err(GalaxyError::Database(Error::DatabaseError(DatabaseErrorKind::UniqueViolation, _))) => {
+            Err(GalaxyError::AlreadyJumping(jump.telegram_id))
+        }
+        result => result,
+    }
+}
+
+/// Moves players, whose jumps arrive by `now`, to their destinations.
+/// Returns landed jumps.
+pub fn land_arrived_jumps<S: GalaxyStore>(store: &S, now: NaiveDateTime) -> GalaxyResult<Vec<Jump>> {
+    store.transaction(|| {
+        let arrived = store.get_arrived_jumps(now)?;
+        for jump in &arrived {
+            store.update_player_location(jump.telegram_id, jump.to_id)?;
+        }
+        store.delete_jumps(&arrived.iter().map(|j| j.telegram_id).collect::<Vec<_>>())?;
+        Ok(arrived)
+    })
+}
+
+/// Returns when the next of ships in flight arrives
+pub fn get_next_arrival<S: GalaxyStore>(store: &S) -> GalaxyResult<Option<NaiveDateTime>> {
+    store.get_next_arrival()
+}
+
+/// Returns the cheapest of links between two objects
+fn cheapest_link<S: GalaxyStore>(store: &S, from: &GalaxyObject, to: &GalaxyObject) -> GalaxyResult<StarLink> {
+    store
+        .get_links_for_objects(&[from.id])?
+        .into_iter()
+        .filter(|link| link.other_side(from).as_ref() == Some(to))
+        .min_by(|a, b| a.cost.partial_cmp(&b.cost).unwrap_or(Ordering::Equal))
+        .ok_or(GalaxyError::NotLinked {
+            from: from.id,
+            to: to.id,
+        })
+}
+
+/// Fulfills futures until the link from `from` leads to a star system
+fn resolve_neighbour<S: GalaxyStore>(store: &S, from: &GalaxyObject, target: GalaxyObject) -> GalaxyResult<GalaxyObject> {
+    let mut target = target;
+    while target.obj_type == GalaxyObjectType::SectorFuture {
+        let sector = ensure_future_fulfilled(store, target.id)?;
+        // The link to the future was re-attached to one of its children
+        let mut next = None;
+        for neighbour in get_neighbours(store, from)? {
+            if load_entity(store, &neighbour)?.parent_id() == Some(sector.id) {
+                next = Some(neighbour);
+                break;
+            }
+        }
+        target = next.ok_or(GalaxyError::ObjectNotFound(target))?;
+    }
+    Ok(target)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use players::*;
+    use store::fixtures::*;
+    use store::MemoryStore;
+
+    /// Spawns player 1 in a new galaxy and returns where
+    fn spawned(store: &MemoryStore) -> GalaxyObject {
+        let sector = galaxy(store, 200f32, 1);
+        GalaxyObject::from(&spawn_players(store, &sector, 1..2)[0])
+    }
+
+    fn location(store: &MemoryStore) -> GalaxyObject {
+        let player = find_player(store, 1).unwrap().unwrap();
+        get_player_location(store, &player).unwrap().unwrap()
+    }
+
+    #[test]
+    fn jump_lands_at_a_linked_system_after_travel_time() {
+        let store = MemoryStore::new();
+        let from = spawned(&store);
+        let now = Utc::now().naive_utc();
+
+        // Jumping to every neighbour, including uncharted ones, ends up in a system
+        for neighbour in get_neighbours(&store, &from).unwrap() {
+            store.update_player_location(1, from.id).unwrap();
+            let jump = start_jump(&store, 1, neighbour.id, now).unwrap();
+            assert!(jump.arrives_at > now);
+
+            assert!(land_arrived_jumps(&store, now).unwrap().is_empty());
+            assert_eq!(location(&store), from);
+
+            assert_eq!(land_arrived_jumps(&store, jump.arrives_at).unwrap(), vec![jump]);
+            let to = location(&store);
+            assert_eq!(to.obj_type, GalaxyObjectType::System);
+            assert!(get_neighbours(&store, &from).unwrap().contains(&to));
+        }
+    }
+
+    #[test]
+    fn travel_time_depends_on_link_cost() {
+        let store = MemoryStore::new();
+        let from = spawned(&store);
+        let now = Utc::now().naive_utc();
+        let target = get_neighbours(&store, &from)
+            .unwrap()
+            .into_iter()
+            .find(|n| n.obj_type == GalaxyObjectType::System)
+            .unwrap();
+        let link = cheapest_link(&store, &from, &target).unwrap();
+
+        let jump = start_jump(&store, 1, target.id, now).unwrap();
+        assert_eq!(jump.arrives_at - jump.departed_at, travel_time(link.cost));
+    }
+
+    #[test]
+    fn jump_needs_a_link() {
+        let store = MemoryStore::new();
+        spawned(&store);
+
+        match start_jump(&store, 1, -1, Utc::now().naive_utc()) {
+            Err(GalaxyError::NotLinked { to: -1, .. }) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+
+    #[test]
+    fn ship_in_flight_cant_jump() {
+        let store = MemoryStore::new();
+        let from = spawned(&store);
+        let now = Utc::now().naive_utc();
+        let target = get_neighbours(&store, &from).unwrap()[0].clone();
+        start_jump(&store, 1, target.id, now).unwrap();
+
+        match start_jump(&store, 1, target.id, now) {
+            Err(GalaxyError::AlreadyJumping(1)) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+
+    #[test]
+    fn jump_saved_concurrently_is_already_jumping() {
+        let store = MemoryStore::new();
+        let from = spawned(&store);
+        let now = Utc::now().naive_utc();
+        let target = get_neighbours(&store, &from).unwrap()[0].clone();
+        let jump = start_jump(&store, 1, target.id, now).unwrap();
+
+        // The other request passed the check before this jump was saved
+        match save_jump(&store, &jump) {
+            Err(GalaxyError::AlreadyJumping(1)) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+}
@@ -0,0 +1,300 @@
+//! Procedural star system names.
+//!
+//! Generators draw all their randomness from the rng they're given, so
+//! names of a sector are determined by its seed. The only exception is a
+//! name that another sector of the galaxy already has: it gets a short
+//! suffix from the sector's seed, like `Kavoria 1Y`.
+
+use super::*;
+
+use rand::{Rng, RngCore};
+use std::collections::{HashMap, HashSet};
+
+pub trait NameGenerator {
+    fn generate(&mut self, rng: &mut dyn RngCore) -> String;
+}
+
+/// Builds names from random syllables, like `Kavoria` or `Tesh`
+pub struct SyllableNameGenerator {
+    pub min_syllables: usize,
+    pub max_syllables: usize,
+}
+
+const ONSETS: &[&str] = &[
+    "b", "c", "d", "f", "g", "h", "k", "l", "m", "n", "p", "r", "s", "t", "v", "z", "br", "ch",
+    "dr", "gr", "kr", "ph", "sh", "st", "th", "tr", "",
+];
+const NUCLEI: &[&str] = &["a", "e", "i", "o", "u", "ae", "ai", "ia", "io", "ou"];
+const CODAS: &[&str] = &["", "", "", "n", "r", "s", "l", "x", "th", "sh"];
+
+impl Default for SyllableNameGenerator {
+    fn default() -> Self {
+        SyllableNameGenerator {
+            min_syllables: 2,
+            max_syllables: 3,
+        }
+    }
+}
+
+impl NameGenerator for SyllableNameGenerator {
+    fn generate(&mut self, rng: &mut dyn RngCore) -> String {
+        let syllables = rng.gen_range(self.min_syllables, self.max_syllables + 1);
+        let mut name = String::new();
+        for i in 0..syllables {
+            name.push_str(rng.choose(ONSETS).unwrap());
+            name.push_str(rng.choose(NUCLEI).unwrap());
+            if i + 1 == syllables {
+                name.push_str(rng.choose(CODAS).unwrap());
+            }
+        }
+        capitalize(&name)
+    }
+}
+
+/// Real star names that Markov generator learns from
+const STAR_NAMES: &[&str] = &[
+    "achernar", "acrux", "adhara", "albireo", "alcor", "alcyone", "aldebaran", "alderamin",
+    "algenib", "algol", "alhena", "alioth", "alkaid", "almach", "alnilam", "alnitak", "alphard",
+    "alphecca", "alpheratz", "altair", "ankaa", "antares", "arcturus", "arneb", "atria",
+    "avior", "bellatrix", "betelgeuse", "canopus", "capella", "caph", "castor", "deneb",
+    "denebola", "diphda", "dubhe", "elnath", "eltanin", "enif", "fomalhaut", "gacrux",
+    "gienah", "hadar", "hamal", "izar", "kochab", "markab", "menkar", "merak", "mimosa",
+    "mintaka", "mirach", "mirfak", "mizar", "nunki", "peacock", "polaris", "pollux",
+    "procyon", "rasalhague", "regulus", "rigel", "sabik", "sadr", "saiph", "scheat",
+    "shaula", "sirius", "spica", "suhail", "thuban", "vega", "wezen", "zosma", "zubenelgenubi",
+];
+
+/// Chains letters by probabilities learnt from real star names
+pub struct MarkovNameGenerator {
+    order: usize,
+    transitions: HashMap<String, Vec<char>>,
+    min_length: usize,
+    max_length: usize,
+}
+
+const END: char = '$';
+
+impl MarkovNameGenerator {
+    pub fn new(corpus: &[&str], order: usize) -> MarkovNameGenerator {
+        let mut transitions: HashMap<String, Vec<char>> = HashMap::new();
+        for word in corpus {
+            let padded = format!("{}{}{}", "^".repeat(order), word.to_lowercase(), END);
+            let chars = padded.chars().collect::<Vec<_>>();
+            for window in chars.windows(order + 1) {
+                let prefix = window[..order].iter().collect::<String>();
+                transitions.entry(prefix).or_default().push(window[order]);
+            }
+        }
+        MarkovNameGenerator {
+            order,
+            transitions,
+            min_length: 4,
+            max_length: 10,
+        }
+    }
+
+    fn attempt(&self, rng: &mut dyn RngCore) -> String {
+        let mut name = "^".repeat(self.order);
+        loop {
+            let prefix = name.chars().skip(name.chars().count() - self.order).collect::<String>();
+            let next = match self.transitions.get(&prefix) {
+                Some(candidates) => *rng.choose(candidates).unwrap(),
+                None => END,
+            };
+            if next == END || name.len() > self.max_length + self.order {
+                break;
+            }
+            name.push(next);
+        }
+        name[self.order..].to_string()
+    }
+}
+
+impl Default for MarkovNameGenerator {
+    fn default() -> Self {
+        MarkovNameGenerator::new(STAR_NAMES, 2)
+    }
+}
+
+impl NameGenerator for MarkovNameGenerator {
+    fn generate(&mut self, rng: &mut dyn RngCore) -> String {
+        // Most attempts are good; bound the loop in case corpus is degenerate
+        let mut name = self.attempt(rng);
+        for _ in 0..100 {
+            if name.len() >= self.min_length && name.len() <= self.max_length {
+                break;
+            }
+            name = self.attempt(rng);
+        }
+        capitalize(&name)
+    }
+}
+
+/// Catalogue designations: sector prefix and a number, like `KTR-1042`
+pub struct CatalogueNameGenerator {
+    pub prefix: String,
+}
+
+impl CatalogueNameGenerator {
+    /// Creates generator with a random three letter prefix
+    pub fn random(rng: &mut dyn RngCore) -> CatalogueNameGenerator {
+        let prefix = (0..3)
+            .map(|_| (b'A' + rng.gen_range(0, 26)) as char)
+            .collect();
+        CatalogueNameGenerator { prefix }
+    }
+}
+
+impl NameGenerator for CatalogueNameGenerator {
+    fn generate(&mut self, rng: &mut dyn RngCore) -> String {
+        format!("{}-{}", self.prefix, rng.gen_range(1, 10000))
+    }
+}
+
+/// Creates generator for the sector's names
+pub fn name_generator(style: NameStyle, rng: &mut dyn RngCore) -> Box<dyn NameGenerator> {
+    match style {
+        NameStyle::Syllable => Box::new(SyllableNameGenerator::default()),
+        NameStyle::Markov => Box::new(MarkovNameGenerator::default()),
+        NameStyle::Catalogue => Box::new(CatalogueNameGenerator::random(rng)),
+    }
+}
+
+/// Wraps generator so that it never returns a name that's already taken
+pub struct UniqueNames<'a> {
+    generator: &'a mut dyn NameGenerator,
+    taken: HashSet<String>,
+}
+
+impl<'a> UniqueNames<'a> {
+    pub fn new(generator: &'a mut dyn NameGenerator, taken: HashSet<String>) -> UniqueNames<'a> {
+        UniqueNames { generator, taken }
+    }
+
+    pub fn generate(&mut self, rng: &mut dyn RngCore) -> String {
+        let mut name = self.generator.generate(rng);
+        for _ in 0..20 {
+            if !self.taken.contains(&name) {
+                break;
+            }
+            name = self.generator.generate(rng);
+        }
+
+        // Generator keeps colliding, so number the name instead
+        let base = name.clone();
+        let mut number = 2;
+        while self.taken.contains(&name) {
+            name = format!("{} {}", base, number);
+            number += 1;
+        }
+
+        self.taken.insert(name.clone());
+        name
+    }
+}
+
+/// Alternatives for a name that's already taken in the galaxy: the name
+/// followed by a growing part of the sector's designation, like `Kavoria 1Y`
+/// or `Kavoria 1Y2`, and then by a numbered full designation
+pub fn alternatives(name: &str, sector_seed: i64) -> impl Iterator<Item = String> {
+    let name = name.to_string();
+    let tag = designation(sector_seed);
+    let prefixes = (2..tag.len() + 1)
+        .map(|len| tag[..len].to_string())
+        .collect::<Vec<_>>();
+    let numbered = (2..).map({
+        let tag = tag.clone();
+        move |number| format!("{}-{}", tag, number)
+    });
+    prefixes
+        .into_iter()
+        .chain(numbered)
+        .map(move |suffix| format!("{} {}", name, suffix))
+}
+
+/// Seed written in base 36
+pub fn designation(seed: i64) -> String {
+    const DIGITS: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+    let mut value = seed as u64;
+    let mut result = Vec::new();
+    loop {
+        result.push(DIGITS[(value % 36) as usize]);
+        value /= 36;
+        if value == 0 {
+            break;
+        }
+    }
+    result.reverse();
+    String::from_utf8(result).unwrap()
+}
+
+fn capitalize(name: &str) -> String {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn generate_names(style: NameStyle, seed: i64, amount: usize) -> Vec<String> {
+        let mut rng = tools::seeded_rng(seed);
+        let mut generator = name_generator(style, &mut rng);
+        let mut names = UniqueNames::new(&mut *generator, HashSet::new());
+        (0..amount).map(|_| names.generate(&mut rng)).collect()
+    }
+
+    #[test]
+    fn names_are_reproducible() {
+        for style in &[NameStyle::Syllable, NameStyle::Markov, NameStyle::Catalogue] {
+            assert_eq!(generate_names(*style, 5, 20), generate_names(*style, 5, 20));
+        }
+    }
+
+    #[test]
+    fn unique_names_dont_repeat() {
+        let names = generate_names(NameStyle::Syllable, 1, 500);
+        let distinct = names.iter().collect::<HashSet<_>>();
+        assert_eq!(distinct.len(), names.len());
+    }
+
+    #[test]
+    fn unique_names_avoid_taken_names() {
+        let mut rng = tools::seeded_rng(3);
+        let taken = generate_names(NameStyle::Markov, 3, 1);
+        let mut generator = MarkovNameGenerator::default();
+        let mut names = UniqueNames::new(&mut generator, taken.iter().cloned().collect());
+
+        assert_ne!(names.generate(&mut rng), taken[0]);
+    }
+
+    #[test]
+    fn alternatives_add_short_designation() {
+        let alternatives = alternatives("Kavoria", 36 * 36 * 36 + 35).take(4).collect::<Vec<_>>();
+        assert_eq!(
+            alternatives,
+            vec!["Kavoria 10", "Kavoria 100", "Kavoria 100Z", "Kavoria 100Z-2"]
+        );
+
+        assert_eq!(designation(0), "0");
+        assert_eq!(designation(-1), "3W5E11264SGSF");
+    }
+
+    #[test]
+    fn markov_names_fit_length() {
+        for name in generate_names(NameStyle::Markov, 2, 100) {
+            let base = name.split(' ').next().unwrap();
+            assert!(base.len() >= 4 && base.len() <= 10, "{}", name);
+        }
+    }
+
+    #[test]
+    fn catalogue_names_share_sector_prefix() {
+        let names = generate_names(NameStyle::Catalogue, 4, 10);
+        let prefix = names[0].split('-').next().unwrap().to_string();
+        assert!(names.iter().all(|n| n.starts_with(&prefix)));
+    }
+}
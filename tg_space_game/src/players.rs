@@ -0,0 +1,87 @@
+use super::*;
+
+use chrono::Utc;
+use galaxy_objects::resolve_object;
+use store::GalaxyStore;
+
+/// Registers Telegram user as a player. Already registered players are
+/// returned as is, even if they are registered concurrently.
+pub fn register_player<S: GalaxyStore>(store: &S, telegram_id: i64, name: &str) -> GalaxyResult<Player> {
+    store.insert_player_if_missing(&NewPlayer {
+        telegram_id,
+        name: name.to_string(),
+        created_at: Utc::now().naive_utc(),
+    })
+}
+
+pub fn find_player<S: GalaxyStore>(store: &S, telegram_id: i64) -> GalaxyResult<Option<Player>> {
+    store.get_player(telegram_id)
+}
+
+/// Returns galaxy object where the player is, if they were spawned
+pub fn get_player_location<S: GalaxyStore>(store: &S, player: &Player) -> GalaxyResult<Option<GalaxyObject>> {
+    match player.location_id {
+        Some(id) => store.get_galaxy_object(id),
+        None => Ok(None),
+    }
+}
+
+/// Moves player to a star system of the sector, which is usually a root
+/// of a galaxy. Futures are fulfilled on the way down if needed.
+pub fn spawn_player<S: GalaxyStore>(store: &S, telegram_id: i64, sector_id: i32) -> GalaxyResult<StarSystem> {
+    if store.get_player(telegram_id)?.is_none() {
+        return Err(GalaxyError::PlayerNotFound(telegram_id));
+    }
+    let system = resolve_object(
+        store,
+        &GalaxyObject {
+            id: sector_id,
+            obj_type: GalaxyObjectType::Sector,
+        },
+    )?;
+    store.update_player_location(telegram_id, system.id)?;
+    Ok(system)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use store::fixtures::galaxy;
+    use store::MemoryStore;
+
+    #[test]
+    fn register_player_is_idempotent() {
+        let store = MemoryStore::new();
+        let player = register_player(&store, 1, "Tester").unwrap();
+        let again = register_player(&store, 1, "Renamed").unwrap();
+
+        assert_eq!(again.name, "Tester");
+        assert_eq!(again.created_at, player.created_at);
+        assert!(find_player(&store, 2).unwrap().is_none());
+    }
+
+    #[test]
+    fn spawn_player_puts_player_at_star_system() {
+        let store = MemoryStore::new();
+        let sector = galaxy(&store, 200f32, 1);
+        register_player(&store, 1, "Tester").unwrap();
+
+        let system = spawn_player(&store, 1, sector.id).unwrap();
+        let player = find_player(&store, 1).unwrap().unwrap();
+        assert_eq!(
+            get_player_location(&store, &player).unwrap(),
+            Some(GalaxyObject::from(&system))
+        );
+    }
+
+    #[test]
+    fn spawn_player_needs_registration() {
+        let store = MemoryStore::new();
+        let sector = galaxy(&store, 200f32, 1);
+
+        match spawn_player(&store, 1, sector.id) {
+            Err(GalaxyError::PlayerNotFound(1)) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+}
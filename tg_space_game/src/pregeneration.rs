@@ -0,0 +1,252 @@
+use super::*;
+
+use galaxy_objects::{get_object_position, try_fulfill_star_sector_future};
+use movement::get_neighbours;
+use players::get_player_location;
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use std::sync::mpsc::sync_channel;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use store::GalaxyStore;
+
+/// Which futures are fulfilled first
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Priority {
+    /// Futures closest to any player
+    Distance,
+    /// Futures near most players, closest first among equals
+    Demand,
+}
+
+impl FromStr for Priority {
+    type Err = GalaxyError;
+
+    fn from_str(s: &str) -> GalaxyResult<Priority> {
+        match s {
+            "distance" => Ok(Priority::Distance),
+            "demand" => Ok(Priority::Demand),
+            _ => Err(config::ConfigError::Invalid("priority must be distance or demand").into()),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PregenerationOptions {
+    /// Futures up to this many links away from players are fulfilled
+    pub depth: u32,
+    /// Maximum amount of futures fulfilled in one round
+    pub queue_size: usize,
+    /// Amount of workers with their own connections
+    pub workers: usize,
+    pub priority: Priority,
+}
+
+impl Default for PregenerationOptions {
+    fn default() -> Self {
+        PregenerationOptions {
+            depth: 2,
+            queue_size: 32,
+            workers: 2,
+            priority: Priority::Distance,
+        }
+    }
+}
+
+/// Future that should be fulfilled before players get to it
+#[derive(Clone, PartialEq, Debug, Serialize)]
+pub struct PregenerationJob {
+    pub future_id: i32,
+    /// Distance to the closest player
+    pub distance: f32,
+    /// Amount of players that have the future nearby
+    pub demand: usize,
+}
+
+/// Finds futures near players, most urgent first, no more than
+/// `queue_size` of them
+pub fn find_pregeneration_jobs<S: GalaxyStore>(
+    store: &S,
+    options: &PregenerationOptions,
+) -> GalaxyResult<Vec<PregenerationJob>> {
+    let mut jobs: HashMap<i32, PregenerationJob> = HashMap::new();
+    for player in store.get_located_players()? {
+        let location = match get_player_location(store, &player)? {
+            Some(location) => location,
+            None => continue,
+        };
+        let position = get_object_position(store, &location)?;
+        for future in futures_nearby(store, &location, options.depth)? {
+            let distance = get_object_position(store, &future)?.distance(&position);
+            let job = jobs.entry(future.id).or_insert(PregenerationJob {
+                future_id: future.id,
+                distance,
+                demand: 0,
+            });
+            job.distance = job.distance.min(distance);
+            job.demand += 1;
+        }
+    }
+
+    let mut jobs = jobs.into_values().collect::<Vec<_>>();
+    jobs.sort_by(|a, b| {
+        let by_distance = a.distance
+            .partial_cmp(&b.distance)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then(a.future_id.cmp(&b.future_id));
+        match options.priority {
+            Priority::Distance => by_distance,
+            Priority::Demand => b.demand.cmp(&a.demand).then(by_distance),
+        }
+    });
+    jobs.truncate(options.queue_size);
+    Ok(jobs)
+}
+
+/// Futures up to `depth` links away from the object
+fn futures_nearby<S: GalaxyStore>(store: &S, object: &GalaxyObject, depth: u32) -> GalaxyResult<Vec<GalaxyObject>> {
+    let mut visited = HashSet::new();
+    visited.insert(object.clone());
+    let mut frontier = vec![object.clone()];
+    let mut result = Vec::new();
+    for _ in 0..depth {
+        let mut next = Vec::new();
+        for node in &frontier {
+            for neighbour in get_neighbours(store, node)? {
+                if visited.insert(neighbour.clone()) {
+                    if neighbour.obj_type == GalaxyObjectType::SectorFuture {
+                        result.push(neighbour.clone());
+                    }
+                    next.push(neighbour);
+                }
+            }
+        }
+        frontier = next;
+    }
+    Ok(result)
+}
+
+/// Runs one round of pre-generation: finds futures near players and
+/// fulfills them with `workers` connections to `database_url`. Futures
+/// that are being fulfilled by someone else are skipped, so several
+/// processes can work on the same database. Workers log failed jobs and
+/// go on with the rest. Returns amount of fulfilled futures.
+pub fn run_pregeneration(database_url: &str, options: &PregenerationOptions) -> GalaxyResult<usize> {
+    let jobs = find_pregeneration_jobs(&GalaxyConnection::establish(database_url)?, options)?;
+    if jobs.is_empty() {
+        return Ok(0);
+    }
+
+    let (sender, receiver) = sync_channel::<PregenerationJob>(options.queue_size.max(1));
+    let receiver = Arc::new(Mutex::new(receiver));
+    let workers = (0..options.workers.max(1))
+        .map(|_| {
+            let receiver = receiver.clone();
+            let database_url = database_url.to_string();
+            thread::spawn(move || -> GalaxyResult<usize> {
+                let conn = GalaxyConnection::establish(&database_url)?;
+                let mut fulfilled = 0;
+                loop {
+                    let job = match receiver.lock().expect("Worker panicked").recv() {
+                        Ok(job) => job,
+                        Err(_) => return Ok(fulfilled),
+                    };
+                    match try_fulfill_star_sector_future(&conn, job.future_id) {
+                        Ok(Some(_)) => {
+                            debug!("Fulfilled future {} at distance {}", job.future_id, job.distance);
+                            fulfilled += 1;
+                        }
+                        Ok(None) => {}
+                        Err(err) => error!("Error fulfilling future {}: {}", job.future_id, err),
+                    }
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+
+    for job in jobs {
+        // Workers are gone only if none of them could connect
+        if sender.send(job).is_err() {
+            break;
+        }
+    }
+    drop(sender);
+
+    // All workers are finished before any error is returned
+    let results = workers
+        .into_iter()
+        .map(|worker| worker.join().expect("Worker panicked"))
+        .collect::<Vec<_>>();
+    results.into_iter().sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use store::fixtures::*;
+    use store::MemoryStore;
+
+    fn galaxy_with_players(store: &MemoryStore, players: i64) {
+        let sector = galaxy(store, 2000f32, 4);
+        spawn_players(store, &sector, 0..players);
+    }
+
+    #[test]
+    fn jobs_are_futures_near_players_closest_first() {
+        let store = MemoryStore::new();
+        galaxy_with_players(&store, 1);
+
+        let jobs = find_pregeneration_jobs(&store, &PregenerationOptions::default()).unwrap();
+        assert!(!jobs.is_empty());
+        assert!(jobs.windows(2).all(|pair| pair[0].distance <= pair[1].distance));
+        for job in &jobs {
+            assert_eq!(store.get_futures(&[job.future_id]).unwrap().len(), 1);
+        }
+    }
+
+    #[test]
+    fn jobs_are_bounded_by_queue_size() {
+        let store = MemoryStore::new();
+        galaxy_with_players(&store, 1);
+        let options = PregenerationOptions {
+            queue_size: 1,
+            depth: 3,
+            ..PregenerationOptions::default()
+        };
+
+        assert_eq!(find_pregeneration_jobs(&store, &options).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn demand_counts_players_nearby() {
+        let store = MemoryStore::new();
+        // Players spawn at the same system, so they share all futures
+        galaxy_with_players(&store, 2);
+        let options = PregenerationOptions {
+            priority: Priority::Demand,
+            ..PregenerationOptions::default()
+        };
+
+        let jobs = find_pregeneration_jobs(&store, &options).unwrap();
+        assert!(jobs.iter().all(|job| job.demand == 2));
+    }
+
+    #[test]
+    fn unknown_priority_is_config_error() {
+        assert_eq!("demand".parse::<Priority>().unwrap(), Priority::Demand);
+        match "nearest".parse::<Priority>() {
+            Err(GalaxyError::Config(config::ConfigError::Invalid(_))) => {}
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn no_players_no_jobs() {
+        let store = MemoryStore::new();
+        galaxy_with_players(&store, 0);
+
+        assert!(find_pregeneration_jobs(&store, &PregenerationOptions::default())
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,301 @@
+use super::*;
+
+use galaxy_objects::{ensure_future_fulfilled, get_links_for_object_ids, load_entities};
+use space::{Located, Position};
+use store::GalaxyStore;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::slice;
+
+/// Sequence of hops over star links
+#[derive(Debug, Clone, PartialEq)]
+pub struct Route {
+    /// Every object on the way, including both ends
+    pub hops: Vec<GalaxyObject>,
+    /// Sum of traversal costs of all links on the way
+    pub cost: f32,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RouteOptions {
+    /// Fulfill futures on the way, so that the route goes through concrete
+    /// star systems only. Otherwise futures are passed as a single hop.
+    pub fulfill_futures: bool,
+}
+
+/// Graph that routes are searched in
+pub trait RouteGraph {
+    type Error;
+
+    fn position(&mut self, node: &GalaxyObject) -> Result<Position, Self::Error>;
+
+    /// Returns neighbours of the node and costs of links to them
+    fn neighbours(&mut self, node: &GalaxyObject) -> Result<Vec<(GalaxyObject, f32)>, Self::Error>;
+}
+
+struct Candidate {
+    estimate: f32,
+    node: GalaxyObject,
+}
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Candidate) -> bool {
+        self.estimate == other.estimate
+    }
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Candidate) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    // Reversed, so that binary heap pops the closest candidate first
+    fn cmp(&self, other: &Candidate) -> Ordering {
+        other
+            .estimate
+            .partial_cmp(&self.estimate)
+            .unwrap_or(Ordering::Equal)
+    }
+}
+
+/// A* search. Straight distance times `MIN_COST_FACTOR` is used as
+/// heuristic, which never overestimates as long as links don't cost less
+/// than that.
+pub fn shortest_route<G: RouteGraph>(
+    graph: &mut G,
+    from: &GalaxyObject,
+    to: &GalaxyObject,
+) -> Result<Option<Route>, G::Error> {
+    let target = graph.position(to)?;
+
+    let mut costs: HashMap<GalaxyObject, f32> = HashMap::new();
+    let mut previous: HashMap<GalaxyObject, GalaxyObject> = HashMap::new();
+    let mut visited: HashSet<GalaxyObject> = HashSet::new();
+    let mut queue = BinaryHeap::new();
+
+    costs.insert(from.clone(), 0f32);
+    queue.push(Candidate {
+        estimate: graph.position(from)?.distance(&target) * MIN_COST_FACTOR,
+        node: from.clone(),
+    });
+
+    while let Some(Candidate { node, .. }) = queue.pop() {
+        if node == *to {
+            let mut hops = vec![node.clone()];
+            while let Some(prev) = previous.get(hops.last().unwrap()) {
+                hops.push(prev.clone());
+            }
+            hops.reverse();
+            return Ok(Some(Route {
+                hops,
+                cost: costs[&node],
+            }));
+        }
+        if !visited.insert(node.clone()) {
+            continue;
+        }
+
+        let cost = costs[&node];
+        for (neighbour, link_cost) in graph.neighbours(&node)? {
+            let new_cost = cost + link_cost;
+            let improves = match costs.get(&neighbour) {
+                Some(old_cost) => new_cost < *old_cost,
+                None => true,
+            };
+            if improves {
+                queue.push(Candidate {
+                    estimate: new_cost + graph.position(&neighbour)?.distance(&target) * MIN_COST_FACTOR,
+                    node: neighbour.clone(),
+                });
+                costs.insert(neighbour.clone(), new_cost);
+                previous.insert(neighbour, node.clone());
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+/// Star links graph in the store. Positions are cached, so every object
+/// is loaded only once during a search.
+struct StoredGraph<'a, S: 'a> {
+    store: &'a S,
+    positions: HashMap<GalaxyObject, Position>,
+}
+
+impl<'a, S: GalaxyStore> StoredGraph<'a, S> {
+    fn load_positions(&mut self, objects: &[GalaxyObject]) -> GalaxyResult<()> {
+        let missing = objects
+            .iter()
+            .filter(|o| !self.positions.contains_key(o))
+            .cloned()
+            .collect::<Vec<_>>();
+        for entity in load_entities(self.store, &missing)? {
+            self.positions.insert(GalaxyObject::from(&entity), entity.position());
+        }
+        Ok(())
+    }
+}
+
+impl<'a, S: GalaxyStore> RouteGraph for StoredGraph<'a, S> {
+    type Error = GalaxyError;
+
+    fn position(&mut self, node: &GalaxyObject) -> GalaxyResult<Position> {
+        self.load_positions(slice::from_ref(node))?;
+        self.positions
+            .get(node)
+            .cloned()
+            .ok_or_else(|| GalaxyError::ObjectNotFound(node.clone()))
+    }
+
+    fn neighbours(&mut self, node: &GalaxyObject) -> GalaxyResult<Vec<(GalaxyObject, f32)>> {
+        let neighbours = get_links_for_object_ids(self.store, vec![node.id])?
+            .iter()
+            .filter_map(|link| link.other_side(node).map(|other| (other, link.cost)))
+            .filter(|(other, _)| other != node)
+            .collect::<Vec<_>>();
+        // Positions are needed for the search heuristic
+        let objects = neighbours.iter().map(|(n, _)| n.clone()).collect::<Vec<_>>();
+        self.load_positions(&objects)?;
+        Ok(neighbours)
+    }
+}
+
+/// Finds the shortest route between two systems or futures over star links.
+/// Futures on the way are treated as coarse nodes.
+pub fn find_route<S: GalaxyStore>(
+    store: &S,
+    from: &GalaxyObject,
+    to: &GalaxyObject,
+) -> GalaxyResult<Option<Route>> {
+    find_route_with_options(store, from, to, &RouteOptions::default())
+}
+
+pub fn find_route_with_options<S: GalaxyStore>(
+    store: &S,
+    from: &GalaxyObject,
+    to: &GalaxyObject,
+    options: &RouteOptions,
+) -> GalaxyResult<Option<Route>> {
+    loop {
+        let route = {
+            let mut graph = StoredGraph {
+                store,
+                positions: HashMap::new(),
+            };
+            shortest_route(&mut graph, from, to)?
+        };
+        if !options.fulfill_futures {
+            return Ok(route);
+        }
+
+        // Ends of the route stay as they are
+        let future_on_the_way = route.as_ref().and_then(|r| {
+            r.hops
+                .iter()
+                .skip(1)
+                .take(r.hops.len().saturating_sub(2))
+                .find(|hop| hop.obj_type == GalaxyObjectType::SectorFuture)
+                .cloned()
+        });
+        match future_on_the_way {
+            Some(future) => {
+                ensure_future_fulfilled(store, future.id)?;
+            }
+            None => return Ok(route),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TestGraph {
+        positions: HashMap<GalaxyObject, Position>,
+        links: Vec<(GalaxyObject, GalaxyObject)>,
+    }
+
+    impl RouteGraph for TestGraph {
+        type Error = ();
+
+        fn position(&mut self, node: &GalaxyObject) -> Result<Position, ()> {
+            self.positions.get(node).cloned().ok_or(())
+        }
+
+        fn neighbours(&mut self, node: &GalaxyObject) -> Result<Vec<(GalaxyObject, f32)>, ()> {
+            let position = self.positions[node];
+            Ok(self
+                .links
+                .iter()
+                .filter_map(|(a, b)| {
+                    if a == node {
+                        Some(b.clone())
+                    } else if b == node {
+                        Some(a.clone())
+                    } else {
+                        None
+                    }
+                })
+                .map(|n| {
+                    let length = self.positions[&n].distance(&position);
+                    (n, length)
+                })
+                .collect())
+        }
+    }
+
+    fn system(id: i32) -> GalaxyObject {
+        GalaxyObject {
+            id,
+            obj_type: GalaxyObjectType::System,
+        }
+    }
+
+    /// Square with a diagonal missing: 1 - 2 - 3 and 1 - 4 - 3, where the
+    /// way through 4 is shorter, and 5 is an island
+    fn square() -> TestGraph {
+        let mut positions = HashMap::new();
+        positions.insert(system(1), Position::new(0.0, 0.0, 0.0));
+        positions.insert(system(2), Position::new(0.0, 10.0, 0.0));
+        positions.insert(system(3), Position::new(5.0, 5.0, 0.0));
+        positions.insert(system(4), Position::new(5.0, 0.0, 0.0));
+        positions.insert(system(5), Position::new(9.0, 9.0, 9.0));
+        TestGraph {
+            positions,
+            links: vec![
+                (system(1), system(2)),
+                (system(2), system(3)),
+                (system(1), system(4)),
+                (system(4), system(3)),
+            ],
+        }
+    }
+
+    #[test]
+    fn shortest_route_picks_shorter_way() {
+        let route = shortest_route(&mut square(), &system(1), &system(3))
+            .unwrap()
+            .unwrap();
+        assert_eq!(route.hops, vec![system(1), system(4), system(3)]);
+        assert_eq!(route.cost, 10.0);
+    }
+
+    #[test]
+    fn shortest_route_to_itself_is_single_hop() {
+        let route = shortest_route(&mut square(), &system(2), &system(2))
+            .unwrap()
+            .unwrap();
+        assert_eq!(route.hops, vec![system(2)]);
+        assert_eq!(route.cost, 0.0);
+    }
+
+    #[test]
+    fn shortest_route_to_island_is_none() {
+        assert_eq!(shortest_route(&mut square(), &system(1), &system(5)), Ok(None));
+    }
+}
@@ -14,6 +14,13 @@ table! {
     star_sectors (id) {
         id -> Int4,
         parent_id -> Nullable<Int4>,
+        seed -> Int8,
+        config_id -> Int4,
+        x -> Float4,
+        y -> Float4,
+        z -> Float4,
+        radius -> Float4,
+        stars -> Nullable<Float4>,
     }
 }
 
@@ -24,6 +31,10 @@ table! {
         parent_id -> Int4,
         radius -> Float4,
         stars -> Float4,
+        seed -> Int8,
+        x -> Float4,
+        y -> Float4,
+        z -> Float4,
     }
 }
 
@@ -33,6 +44,10 @@ table! {
         id -> Int4,
         name -> Varchar,
         sector_id -> Int4,
+        x -> Float4,
+        y -> Float4,
+        z -> Float4,
+        radius -> Float4,
     }
 }
 
@@ -45,11 +60,58 @@ table! {
         a_obj_type -> GalaxyObjectTypeSql,
         b_id -> Int4,
         b_obj_type -> GalaxyObjectTypeSql,
+        length -> Float4,
+        link_type -> Varchar,
+        cost -> Float4,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    generation_configs (id) {
+        id -> Int4,
+        branching_factor -> Int4,
+        leaf_threshold -> Float4,
+        link_density -> Float4,
+        weight_distribution -> Varchar,
+        radius_scaling -> Float4,
+        name_style -> Varchar,
+        link_reattach -> Varchar,
+        link_strategy -> Varchar,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    players (telegram_id) {
+        telegram_id -> Int8,
+        name -> Varchar,
+        created_at -> Timestamp,
+        location_id -> Nullable<Int4>,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    jumps (telegram_id) {
+        telegram_id -> Int8,
+        from_id -> Int4,
+        to_id -> Int4,
+        departed_at -> Timestamp,
+        arrives_at -> Timestamp,
     }
 }
 
+joinable!(players -> galaxy_objects (location_id));
+joinable!(jumps -> players (telegram_id));
+joinable!(star_sectors -> generation_configs (config_id));
+joinable!(star_systems -> star_sectors (sector_id));
+
 allow_tables_to_appear_in_same_query!(
+    generation_configs,
     galaxy_objects,
+    jumps,
+    players,
     star_sector_futures,
     star_sectors,
     star_systems,
@@ -1,34 +1,38 @@
+use diesel::backend::Backend;
 use diesel::deserialize::{self, FromSql};
 use diesel::pg::Pg;
 use diesel::serialize::{self, IsNull, Output, ToSql};
+use diesel::sql_types::Text;
+use diesel::sqlite::Sqlite;
 use std::io::Write;
 
+/// Postgres enum, stored as text in SQLite
 #[derive(SqlType)]
 #[postgres(type_name = "galaxy_object_type")]
+#[sqlite_type = "Text"]
 pub struct GalaxyObjectTypeSql;
 
-#[derive(Debug, PartialEq, Eq, Hash, FromSqlRow, AsExpression, Copy, Clone)]
+#[derive(Debug, PartialEq, Eq, Hash, FromSqlRow, AsExpression, Copy, Clone, Serialize,
+         Deserialize)]
 #[sql_type = "GalaxyObjectTypeSql"]
+#[serde(rename_all = "snake_case")]
 pub enum GalaxyObjectType {
     System,
     Sector,
     SectorFuture,
 }
 
-impl ToSql<GalaxyObjectTypeSql, Pg> for GalaxyObjectType {
-    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+impl GalaxyObjectType {
+    pub fn as_str(&self) -> &'static str {
         match *self {
-            GalaxyObjectType::System => out.write_all(b"system")?,
-            GalaxyObjectType::Sector => out.write_all(b"sector")?,
-            GalaxyObjectType::SectorFuture => out.write_all(b"sector_future")?,
+            GalaxyObjectType::System => "system",
+            GalaxyObjectType::Sector => "sector",
+            GalaxyObjectType::SectorFuture => "sector_future",
         }
-        Ok(IsNull::No)
     }
-}
 
-impl FromSql<GalaxyObjectTypeSql, Pg> for GalaxyObjectType {
-    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
-        match not_none!(bytes) {
+    fn from_bytes(bytes: &[u8]) -> deserialize::Result<Self> {
+        match bytes {
             b"system" => Ok(GalaxyObjectType::System),
             b"sector" => Ok(GalaxyObjectType::Sector),
             b"sector_future" => Ok(GalaxyObjectType::SectorFuture),
@@ -36,3 +40,163 @@ impl FromSql<GalaxyObjectTypeSql, Pg> for GalaxyObjectType {
         }
     }
 }
+
+impl ToSql<GalaxyObjectTypeSql, Pg> for GalaxyObjectType {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+        out.write_all(self.as_str().as_bytes())?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<GalaxyObjectTypeSql, Pg> for GalaxyObjectType {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        GalaxyObjectType::from_bytes(not_none!(bytes))
+    }
+}
+
+impl ToSql<GalaxyObjectTypeSql, Sqlite> for GalaxyObjectType {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Sqlite>) -> serialize::Result {
+        ToSql::<Text, Sqlite>::to_sql(self.as_str(), out)
+    }
+}
+
+impl FromSql<GalaxyObjectTypeSql, Sqlite> for GalaxyObjectType {
+    fn from_sql(value: Option<&<Sqlite as Backend>::RawValue>) -> deserialize::Result<Self> {
+        GalaxyObjectType::from_bytes(not_none!(value).read_blob())
+    }
+}
+
+/// Maps enum to its snake case text representation in the database
+macro_rules! text_sql_enum {
+    ($name:ident { $($variant:ident => $text:expr),+ $(,)* }) => {
+        impl $name {
+            pub fn as_str(&self) -> &'static str {
+                match *self {
+                    $($name::$variant => $text),+
+                }
+            }
+        }
+
+        impl<DB: Backend> ToSql<Text, DB> for $name
+        where
+            str: ToSql<Text, DB>,
+        {
+            fn to_sql<W: Write>(&self, out: &mut Output<W, DB>) -> serialize::Result {
+                self.as_str().to_sql(out)
+            }
+        }
+
+        impl<DB: Backend> FromSql<Text, DB> for $name
+        where
+            String: FromSql<Text, DB>,
+        {
+            fn from_sql(bytes: Option<&DB::RawValue>) -> deserialize::Result<Self> {
+                match String::from_sql(bytes)?.as_str() {
+                    $($text => Ok($name::$variant),)+
+                    _ => Err(concat!("Unrecognized ", stringify!($name), " variant").into()),
+                }
+            }
+        }
+    };
+}
+
+/// Distribution of link weights inside a sector
+#[derive(Debug, PartialEq, Eq, Hash, FromSqlRow, AsExpression, Copy, Clone, Serialize,
+         Deserialize)]
+#[sql_type = "Text"]
+#[serde(rename_all = "snake_case")]
+pub enum WeightDistribution {
+    /// Few hubs with most of the links
+    Exponential,
+    /// Random weights without pronounced hubs
+    Uniform,
+    /// Every child is equally likely to be linked
+    Constant,
+}
+
+text_sql_enum!(WeightDistribution {
+    Exponential => "exponential",
+    Uniform => "uniform",
+    Constant => "constant",
+});
+
+/// How star systems are named
+#[derive(Debug, PartialEq, Eq, Hash, FromSqlRow, AsExpression, Copy, Clone, Serialize,
+         Deserialize)]
+#[sql_type = "Text"]
+#[serde(rename_all = "snake_case")]
+pub enum NameStyle {
+    /// Names built from random syllables
+    Syllable,
+    /// Names that resemble real star names
+    Markov,
+    /// Catalogue designations, like `KTR-1042`
+    Catalogue,
+}
+
+text_sql_enum!(NameStyle {
+    Syllable => "syllable",
+    Markov => "markov",
+    Catalogue => "catalogue",
+});
+
+/// Which child of a fulfilled future inherits each of the future's links
+#[derive(Debug, PartialEq, Eq, Hash, FromSqlRow, AsExpression, Copy, Clone, Serialize,
+         Deserialize)]
+#[sql_type = "Text"]
+#[serde(rename_all = "snake_case")]
+pub enum LinkReattachPolicy {
+    /// Child is chosen randomly by its link weight
+    Weighted,
+    /// Child nearest to the other side of the link
+    Nearest,
+}
+
+text_sql_enum!(LinkReattachPolicy {
+    Weighted => "weighted",
+    Nearest => "nearest",
+});
+
+/// Kind of a star link, which defines how costly it is to traverse
+#[derive(Debug, PartialEq, Eq, Hash, FromSqlRow, AsExpression, Copy, Clone, Serialize,
+         Deserialize)]
+#[sql_type = "Text"]
+#[serde(rename_all = "snake_case")]
+pub enum LinkType {
+    /// Regular route, traversed at ship speed
+    Hyperlane,
+    /// Shortcut that is much cheaper than its length
+    Wormhole,
+    /// Dangerous route that takes longer than its length
+    UnstableRoute,
+}
+
+text_sql_enum!(LinkType {
+    Hyperlane => "hyperlane",
+    Wormhole => "wormhole",
+    UnstableRoute => "unstable_route",
+});
+
+/// How children of a sector are linked to each other
+#[derive(Debug, PartialEq, Eq, Hash, FromSqlRow, AsExpression, Copy, Clone, Serialize,
+         Deserialize)]
+#[sql_type = "Text"]
+#[serde(rename_all = "snake_case")]
+pub enum LinkStrategy {
+    /// Chain of children in random order and extra links chosen by weight
+    Random,
+    /// Minimum spanning tree and extra links between close children
+    SpanningTree,
+    /// Every child is linked to its nearest children
+    NearestNeighbours,
+    /// Children are linked when no other child lies in the sphere
+    /// between them
+    Gabriel,
+}
+
+text_sql_enum!(LinkStrategy {
+    Random => "random",
+    SpanningTree => "spanning_tree",
+    NearestNeighbours => "nearest_neighbours",
+    Gabriel => "gabriel",
+});
@@ -0,0 +1,144 @@
+use super::*;
+
+use rand::Rng;
+use std::ops::{Add, Sub};
+
+/// Absolute position in the galaxy
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+pub struct Position {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+impl Position {
+    pub fn new(x: f32, y: f32, z: f32) -> Position {
+        Position { x, y, z }
+    }
+
+    pub fn origin() -> Position {
+        Position::default()
+    }
+
+    pub fn length(&self) -> f32 {
+        (self.x * self.x + self.y * self.y + self.z * self.z).sqrt()
+    }
+
+    pub fn distance(&self, other: &Position) -> f32 {
+        (*self - *other).length()
+    }
+}
+
+impl Add for Position {
+    type Output = Position;
+
+    fn add(self, other: Position) -> Position {
+        Position::new(self.x + other.x, self.y + other.y, self.z + other.z)
+    }
+}
+
+impl Sub for Position {
+    type Output = Position;
+
+    fn sub(self, other: Position) -> Position {
+        Position::new(self.x - other.x, self.y - other.y, self.z - other.z)
+    }
+}
+
+/// Object that occupies a sphere in the galaxy
+pub trait Located {
+    fn position(&self) -> Position;
+    fn radius(&self) -> f32;
+
+    fn distance_to<L: Located>(&self, other: &L) -> f32 {
+        self.position().distance(&other.position())
+    }
+}
+
+impl Located for StarSector {
+    fn position(&self) -> Position {
+        Position::new(self.x, self.y, self.z)
+    }
+
+    fn radius(&self) -> f32 {
+        self.radius
+    }
+}
+
+impl Located for StarSectorFuture {
+    fn position(&self) -> Position {
+        Position::new(self.x, self.y, self.z)
+    }
+
+    fn radius(&self) -> f32 {
+        self.radius
+    }
+}
+
+impl Located for StarSystem {
+    fn position(&self) -> Position {
+        Position::new(self.x, self.y, self.z)
+    }
+
+    fn radius(&self) -> f32 {
+        self.radius
+    }
+}
+
+impl Located for GalaxyEntity {
+    fn position(&self) -> Position {
+        match *self {
+            GalaxyEntity::System(ref s) => s.position(),
+            GalaxyEntity::Sector(ref s) => s.position(),
+            GalaxyEntity::SectorFuture(ref f) => f.position(),
+        }
+    }
+
+    fn radius(&self) -> f32 {
+        match *self {
+            GalaxyEntity::System(ref s) => s.radius(),
+            GalaxyEntity::Sector(ref s) => s.radius(),
+            GalaxyEntity::SectorFuture(ref f) => f.radius(),
+        }
+    }
+}
+
+/// Returns uniformly distributed random point inside a sphere
+pub fn random_in_sphere<R: Rng + ?Sized>(rng: &mut R, center: Position, radius: f32) -> Position {
+    loop {
+        let offset = Position::new(
+            rng.gen_range(-1.0, 1.0),
+            rng.gen_range(-1.0, 1.0),
+            rng.gen_range(-1.0, 1.0),
+        );
+        if offset.length() <= 1.0 {
+            return Position::new(
+                center.x + offset.x * radius,
+                center.y + offset.y * radius,
+                center.z + offset.z * radius,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn distance_is_euclidean() {
+        let a = Position::new(1.0, 2.0, 3.0);
+        let b = Position::new(4.0, 6.0, 3.0);
+        assert_eq!(a.distance(&b), 5.0);
+    }
+
+    #[test]
+    fn random_in_sphere_stays_inside_sphere() {
+        let mut rng = tools::seeded_rng(1);
+        let center = Position::new(10.0, -5.0, 2.0);
+        for _ in 0..1000 {
+            let point = random_in_sphere(&mut rng, center, 3.0);
+            assert!(point.distance(&center) <= 3.0 + 1e-4);
+        }
+    }
+}
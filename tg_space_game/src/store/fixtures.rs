@@ -0,0 +1,29 @@
+//! Galaxies that unit tests start with
+
+use super::*;
+
+use galaxy_objects::{fulfill_star_sector_future, generate_star_sector, get_star_sector_children_futures};
+use players::{register_player, spawn_player};
+use std::ops::Range;
+
+/// Root sector with `stars` stars, generated with default config
+pub fn galaxy(store: &MemoryStore, stars: f32, seed: i64) -> StarSector {
+    generate_star_sector(store, stars, 100f32, None, seed, &GenerationConfig::default()).unwrap()
+}
+
+/// Galaxy of 200 stars with its first future fulfilled, so that it has
+/// sectors, futures and systems at once
+pub fn fulfilled_galaxy(store: &MemoryStore, seed: i64) -> StarSector {
+    let sector = galaxy(store, 200f32, seed);
+    let future = get_star_sector_children_futures(store, &sector).unwrap()[0].clone();
+    fulfill_star_sector_future(store, future.id).unwrap();
+    sector
+}
+
+/// Registers players with the ids and spawns them in the galaxy
+pub fn spawn_players(store: &MemoryStore, sector: &StarSector, ids: Range<i64>) -> Vec<StarSystem> {
+    ids.map(|id| {
+        register_player(store, id, "Tester").unwrap();
+        spawn_player(store, id, sector.id).unwrap()
+    }).collect()
+}
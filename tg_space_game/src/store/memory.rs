@@ -0,0 +1,798 @@
+use super::*;
+
+use diesel::result::DatabaseErrorKind;
+use galaxy_objects::{DeletedRows, SectorStats};
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, HashSet};
+use std::ops::Deref;
+
+/// Galaxy that lives in memory only. Useful for tests and simulations,
+/// that run many generations and don't need to keep them.
+///
+/// Tables remember the old values of rows that transactions change, so
+/// every transaction, nested ones included, can be rolled back on its own
+/// without copying the galaxy.
+#[derive(Default)]
+pub struct MemoryStore {
+    state: RefCell<MemoryState>,
+    /// Amount of transactions that are running
+    depth: Cell<usize>,
+}
+
+#[derive(Default)]
+struct MemoryState {
+    last_object_id: i32,
+    last_config_id: i32,
+    last_link_id: i32,
+    galaxy_objects: Table<i32, GalaxyObjectType>,
+    generation_configs: Table<i32, GenerationConfig>,
+    sectors: Table<i32, StarSector>,
+    futures: Table<i32, StarSectorFuture>,
+    systems: Table<i32, StarSystem>,
+    links: Table<i32, StarLink>,
+    players: Table<i64, Player>,
+    jumps: Table<i64, Jump>,
+}
+
+/// Point that a transaction rolls back to: ids and lengths of undo logs
+/// when it started
+struct Savepoint {
+    last_ids: (i32, i32, i32),
+    undo_lengths: [usize; 8],
+}
+
+impl MemoryState {
+    fn savepoint(&self) -> Savepoint {
+        Savepoint {
+            last_ids: (self.last_object_id, self.last_config_id, self.last_link_id),
+            undo_lengths: [
+                self.galaxy_objects.undo.len(),
+                self.generation_configs.undo.len(),
+                self.sectors.undo.len(),
+                self.futures.undo.len(),
+                self.systems.undo.len(),
+                self.links.undo.len(),
+                self.players.undo.len(),
+                self.jumps.undo.len(),
+            ],
+        }
+    }
+
+    fn rollback(&mut self, savepoint: &Savepoint) {
+        let (object_id, config_id, link_id) = savepoint.last_ids;
+        self.last_object_id = object_id;
+        self.last_config_id = config_id;
+        self.last_link_id = link_id;
+        let lengths = &savepoint.undo_lengths;
+        self.galaxy_objects.rollback(lengths[0]);
+        self.generation_configs.rollback(lengths[1]);
+        self.sectors.rollback(lengths[2]);
+        self.futures.rollback(lengths[3]);
+        self.systems.rollback(lengths[4]);
+        self.links.rollback(lengths[5]);
+        self.players.rollback(lengths[6]);
+        self.jumps.rollback(lengths[7]);
+    }
+
+    /// Drops undo logs once there's no transaction that could roll back
+    fn forget_undo(&mut self) {
+        self.galaxy_objects.undo.clear();
+        self.generation_configs.undo.clear();
+        self.sectors.undo.clear();
+        self.futures.undo.clear();
+        self.systems.undo.clear();
+        self.links.undo.clear();
+        self.players.undo.clear();
+        self.jumps.undo.clear();
+    }
+}
+
+/// Map that logs old values of the rows it changes. Reads go straight to
+/// the map, writes only through methods that log.
+struct Table<K, V> {
+    rows: BTreeMap<K, V>,
+    /// Key and the value it had before the change, if it had any
+    undo: Vec<(K, Option<V>)>,
+}
+
+impl<K: Ord, V> Default for Table<K, V> {
+    fn default() -> Self {
+        Table {
+            rows: BTreeMap::new(),
+            undo: Vec::new(),
+        }
+    }
+}
+
+impl<K, V> Deref for Table<K, V> {
+    type Target = BTreeMap<K, V>;
+
+    fn deref(&self) -> &BTreeMap<K, V> {
+        &self.rows
+    }
+}
+
+impl<K: Ord + Clone, V: Clone> Table<K, V> {
+    fn insert(&mut self, key: K, value: V) {
+        let old = self.rows.insert(key.clone(), value);
+        self.undo.push((key, old));
+    }
+
+    fn remove(&mut self, key: &K) -> Option<V> {
+        let old = self.rows.remove(key);
+        if let Some(ref value) = old {
+            self.undo.push((key.clone(), Some(value.clone())));
+        }
+        old
+    }
+
+    fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        if let Some(value) = self.rows.get(key) {
+            self.undo.push((key.clone(), Some(value.clone())));
+        }
+        self.rows.get_mut(key)
+    }
+
+    fn retain<F: Fn(&V) -> bool>(&mut self, keep: F) {
+        let removed = self.rows
+            .iter()
+            .filter(|&(_, value)| !keep(value))
+            .map(|(key, _)| key.clone())
+            .collect::<Vec<_>>();
+        for key in removed {
+            self.remove(&key);
+        }
+    }
+
+    fn rollback(&mut self, length: usize) {
+        while self.undo.len() > length {
+            match self.undo.pop() {
+                Some((key, Some(value))) => {
+                    self.rows.insert(key, value);
+                }
+                Some((key, None)) => {
+                    self.rows.remove(&key);
+                }
+                None => break,
+            }
+        }
+    }
+}
+
+/// Ends the transaction when dropped: restores depth, and rolls the
+/// changes back unless the transaction was committed. Panics inside of a
+/// transaction are rolled back too.
+struct TransactionGuard<'a> {
+    store: &'a MemoryStore,
+    depth: usize,
+    savepoint: Option<Savepoint>,
+}
+
+impl<'a> TransactionGuard<'a> {
+    fn commit(mut self) {
+        self.savepoint = None;
+    }
+}
+
+impl<'a> Drop for TransactionGuard<'a> {
+    fn drop(&mut self) {
+        self.store.depth.set(self.depth);
+        let mut state = self.store.state.borrow_mut();
+        if let Some(ref savepoint) = self.savepoint {
+            state.rollback(savepoint);
+        }
+        if self.depth == 0 {
+            state.forget_undo();
+        }
+    }
+}
+
+impl MemoryStore {
+    pub fn new() -> MemoryStore {
+        MemoryStore::default()
+    }
+
+    /// Amount of allocated galaxy objects of all types
+    pub fn galaxy_object_count(&self) -> usize {
+        self.state.borrow().galaxy_objects.len()
+    }
+
+    /// Amount of star links
+    pub fn link_count(&self) -> usize {
+        self.state.borrow().links.len()
+    }
+}
+
+fn remove_all<V: Clone>(table: &mut Table<i32, V>, ids: &[i32]) -> usize {
+    ids.iter().filter(|id| table.remove(id).is_some()).count()
+}
+
+impl GalaxyStore for MemoryStore {
+    fn transaction<T, F>(&self, f: F) -> GalaxyResult<T>
+    where
+        F: FnOnce() -> GalaxyResult<T>,
+    {
+        let depth = self.depth.get();
+        let guard = TransactionGuard {
+            store: self,
+            depth,
+            savepoint: Some(self.state.borrow().savepoint()),
+        };
+        self.depth.set(depth + 1);
+        let result = f();
+        if result.is_ok() {
+            guard.commit();
+        }
+        result
+    }
+
+    fn snapshot_transaction<T, F>(&self, f: F) -> GalaxyResult<T>
+    where
+        F: FnOnce() -> GalaxyResult<T>,
+    {
+        GalaxyStore::transaction(self, f)
+    }
+
+    fn create_galaxy_objects(&self, types: &[GalaxyObjectType]) -> GalaxyResult<Vec<GalaxyObject>> {
+        let mut state = self.state.borrow_mut();
+        let mut result = Vec::with_capacity(types.len());
+        for obj_type in types {
+            state.last_object_id += 1;
+            let id = state.last_object_id;
+            state.galaxy_objects.insert(id, *obj_type);
+            result.push(GalaxyObject {
+                id,
+                obj_type: *obj_type,
+            });
+        }
+        Ok(result)
+    }
+
+    fn insert_galaxy_objects(&self, objects: &[GalaxyObject]) -> GalaxyResult<usize> {
+        let mut state = self.state.borrow_mut();
+        if objects.iter().any(|o| state.galaxy_objects.contains_key(&o.id)) {
+            return Err(GalaxyError::Database(Error::DatabaseError(
+                DatabaseErrorKind::UniqueViolation,
+                Box::new("Galaxy object already exists".to_string()),
+            )));
+        }
+        for object in objects {
+            state.galaxy_objects.insert(object.id, object.obj_type);
+            state.last_object_id = state.last_object_id.max(object.id);
+        }
+        Ok(objects.len())
+    }
+
+    fn list_galaxy_objects(&self) -> GalaxyResult<Vec<GalaxyObject>> {
+        Ok(self.state
+            .borrow()
+            .galaxy_objects
+            .iter()
+            .map(|(id, obj_type)| GalaxyObject {
+                id: *id,
+                obj_type: *obj_type,
+            })
+            .collect())
+    }
+
+    fn get_galaxy_object(&self, id: i32) -> GalaxyResult<Option<GalaxyObject>> {
+        Ok(self.state
+            .borrow()
+            .galaxy_objects
+            .get(&id)
+            .map(|obj_type| GalaxyObject {
+                id,
+                obj_type: *obj_type,
+            }))
+    }
+
+    fn update_galaxy_object_type(&self, object: &GalaxyObject, obj_type: GalaxyObjectType) -> GalaxyResult<()> {
+        match self.state.borrow_mut().galaxy_objects.get_mut(&object.id) {
+            Some(t) if *t == object.obj_type => {
+                *t = obj_type;
+                Ok(())
+            }
+            _ => Err(GalaxyError::ObjectNotFound(object.clone())),
+        }
+    }
+
+    fn delete_galaxy_objects(&self, ids: &[i32]) -> GalaxyResult<usize> {
+        let mut state = self.state.borrow_mut();
+        // Players at deleted objects end up nowhere, as with ON DELETE SET NULL
+        let located = state
+            .players
+            .values()
+            .filter(|p| p.location_id.is_some_and(|id| ids.contains(&id)))
+            .map(|p| p.telegram_id)
+            .collect::<Vec<_>>();
+        for telegram_id in located {
+            if let Some(player) = state.players.get_mut(&telegram_id) {
+                player.location_id = None;
+            }
+        }
+        state
+            .jumps
+            .retain(|j| !ids.contains(&j.from_id) && !ids.contains(&j.to_id));
+        Ok(remove_all(&mut state.galaxy_objects, ids))
+    }
+
+    fn insert_generation_config(&self, config: &GenerationConfig) -> GalaxyResult<i32> {
+        let mut state = self.state.borrow_mut();
+        state.last_config_id += 1;
+        let id = state.last_config_id;
+        state.generation_configs.insert(id, config.clone());
+        Ok(id)
+    }
+
+    fn get_generation_config(&self, config_id: i32) -> GalaxyResult<GenerationConfig> {
+        self.state
+            .borrow()
+            .generation_configs
+            .get(&config_id)
+            .cloned()
+            .ok_or(GalaxyError::Database(Error::NotFound))
+    }
+
+    fn insert_sector(&self, sector: &NewStarSector) -> GalaxyResult<StarSector> {
+        let result = StarSector {
+            id: sector.id,
+            parent_id: sector.parent_id,
+            seed: sector.seed,
+            config_id: sector.config_id,
+            x: sector.x,
+            y: sector.y,
+            z: sector.z,
+            radius: sector.radius,
+            stars: sector.stars,
+        };
+        self.state
+            .borrow_mut()
+            .sectors
+            .insert(result.id, result.clone());
+        Ok(result)
+    }
+
+    fn get_sector(&self, id: i32) -> GalaxyResult<StarSector> {
+        self.state
+            .borrow()
+            .sectors
+            .get(&id)
+            .cloned()
+            .ok_or(GalaxyError::ObjectNotFound(GalaxyObject {
+                id,
+                obj_type: GalaxyObjectType::Sector,
+            }))
+    }
+
+    fn get_sectors(&self, ids: &[i32]) -> GalaxyResult<Vec<StarSector>> {
+        let state = self.state.borrow();
+        Ok(ids.iter().filter_map(|id| state.sectors.get(id).cloned()).collect())
+    }
+
+    fn get_root_sectors(&self) -> GalaxyResult<Vec<StarSector>> {
+        Ok(self.state
+            .borrow()
+            .sectors
+            .values()
+            .filter(|s| s.parent_id.is_none())
+            .cloned()
+            .collect())
+    }
+
+    fn list_sectors(&self) -> GalaxyResult<Vec<StarSector>> {
+        Ok(self.state.borrow().sectors.values().cloned().collect())
+    }
+
+    fn get_child_sectors(&self, parent_id: i32) -> GalaxyResult<Vec<StarSector>> {
+        Ok(self.state
+            .borrow()
+            .sectors
+            .values()
+            .filter(|s| s.parent_id == Some(parent_id))
+            .cloned()
+            .collect())
+    }
+
+    fn get_sector_subtree(&self, sector_id: i32) -> GalaxyResult<Vec<GalaxyObject>> {
+        let state = self.state.borrow();
+        let mut visited = HashSet::new();
+        let mut sectors = state.sectors.get(&sector_id).map(|s| s.id).into_iter().collect::<Vec<_>>();
+        // Visited sectors are skipped, so that cycles of parents end
+        while let Some(parent_id) = sectors.pop() {
+            if visited.insert(parent_id) {
+                sectors.extend(state.sectors.values().filter(|s| s.parent_id == Some(parent_id)).map(|s| s.id));
+            }
+        }
+
+        let mut result = visited
+            .iter()
+            .map(|id| GalaxyObject {
+                id: *id,
+                obj_type: GalaxyObjectType::Sector,
+            })
+            .collect::<Vec<_>>();
+        result.extend(state.futures.values().filter(|f| visited.contains(&f.parent_id)).map(GalaxyObject::from));
+        result.extend(state.systems.values().filter(|s| visited.contains(&s.sector_id)).map(GalaxyObject::from));
+        Ok(result)
+    }
+
+    fn count_sector_subtree(&self, sector_id: i32) -> GalaxyResult<SectorStats> {
+        let objects = self.get_sector_subtree(sector_id)?;
+        let count = |obj_type| objects.iter().filter(|o| o.obj_type == obj_type).count();
+        let ids = objects.iter().map(|o| o.id).collect::<HashSet<_>>();
+        let links = self.state
+            .borrow()
+            .links
+            .values()
+            .filter(|l| ids.contains(&l.a_id) && ids.contains(&l.b_id))
+            .count();
+        Ok(SectorStats {
+            sectors: count(GalaxyObjectType::Sector),
+            futures: count(GalaxyObjectType::SectorFuture),
+            systems: count(GalaxyObjectType::System),
+            links,
+        })
+    }
+
+    fn delete_sector_subtree(&self, sector_id: i32, keep_sector_object: bool) -> GalaxyResult<DeletedRows> {
+        let objects = self.get_sector_subtree(sector_id)?;
+        let ids_of = |obj_type| {
+            objects
+                .iter()
+                .filter(|o| o.obj_type == obj_type)
+                .map(|o| o.id)
+                .collect::<Vec<_>>()
+        };
+        let ids = objects.iter().map(|o| o.id).collect::<Vec<_>>();
+        let deleted_objects = ids
+            .iter()
+            .cloned()
+            .filter(|&id| !(keep_sector_object && id == sector_id))
+            .collect::<Vec<_>>();
+
+        Ok(DeletedRows {
+            links: self.delete_links_for_objects(&ids)?,
+            systems: self.delete_systems(&ids_of(GalaxyObjectType::System))?,
+            futures: self.delete_futures(&ids_of(GalaxyObjectType::SectorFuture))?,
+            sectors: self.delete_sectors(&ids_of(GalaxyObjectType::Sector))?,
+            galaxy_objects: self.delete_galaxy_objects(&deleted_objects)?,
+        })
+    }
+
+    fn delete_sectors(&self, ids: &[i32]) -> GalaxyResult<usize> {
+        Ok(remove_all(&mut self.state.borrow_mut().sectors, ids))
+    }
+
+    fn insert_futures(&self, futures: &[NewStarSectorFuture]) -> GalaxyResult<Vec<StarSectorFuture>> {
+        let result = futures
+            .iter()
+            .map(|f| StarSectorFuture {
+                id: f.id,
+                parent_id: f.parent_id,
+                radius: f.radius,
+                stars: f.stars,
+                seed: f.seed,
+                x: f.x,
+                y: f.y,
+                z: f.z,
+            })
+            .collect::<Vec<_>>();
+        let mut state = self.state.borrow_mut();
+        for future in &result {
+            state.futures.insert(future.id, future.clone());
+        }
+        Ok(result)
+    }
+
+    fn lock_futures(&self, ids: &[i32]) -> GalaxyResult<Vec<StarSectorFuture>> {
+        let state = self.state.borrow();
+        Ok(state
+            .futures
+            .values()
+            .filter(|f| ids.contains(&f.id))
+            .cloned()
+            .collect())
+    }
+
+    fn try_lock_futures(&self, ids: &[i32]) -> GalaxyResult<Vec<StarSectorFuture>> {
+        self.lock_futures(ids)
+    }
+
+    fn get_futures(&self, ids: &[i32]) -> GalaxyResult<Vec<StarSectorFuture>> {
+        let state = self.state.borrow();
+        Ok(ids.iter().filter_map(|id| state.futures.get(id).cloned()).collect())
+    }
+
+    fn get_child_futures(&self, sector_id: i32) -> GalaxyResult<Vec<StarSectorFuture>> {
+        Ok(self.state
+            .borrow()
+            .futures
+            .values()
+            .filter(|f| f.parent_id == sector_id)
+            .cloned()
+            .collect())
+    }
+
+    fn list_futures(&self) -> GalaxyResult<Vec<StarSectorFuture>> {
+        Ok(self.state.borrow().futures.values().cloned().collect())
+    }
+
+    fn delete_futures(&self, ids: &[i32]) -> GalaxyResult<usize> {
+        Ok(remove_all(&mut self.state.borrow_mut().futures, ids))
+    }
+
+    fn insert_systems(&self, systems: &[NewStarSystem]) -> GalaxyResult<Vec<StarSystem>> {
+        let result = systems
+            .iter()
+            .map(|s| StarSystem {
+                id: s.id,
+                name: s.name.clone(),
+                sector_id: s.sector_id,
+                x: s.x,
+                y: s.y,
+                z: s.z,
+                radius: s.radius,
+            })
+            .collect::<Vec<_>>();
+        let mut state = self.state.borrow_mut();
+        for system in &result {
+            state.systems.insert(system.id, system.clone());
+        }
+        Ok(result)
+    }
+
+    fn get_systems(&self, ids: &[i32]) -> GalaxyResult<Vec<StarSystem>> {
+        let state = self.state.borrow();
+        Ok(ids.iter().filter_map(|id| state.systems.get(id).cloned()).collect())
+    }
+
+    fn list_systems(&self, limit: i64) -> GalaxyResult<Vec<StarSystem>> {
+        Ok(self.state
+            .borrow()
+            .systems
+            .values()
+            .take(limit as usize)
+            .cloned()
+            .collect())
+    }
+
+    fn get_child_systems(&self, sector_id: i32) -> GalaxyResult<Vec<StarSystem>> {
+        Ok(self.state
+            .borrow()
+            .systems
+            .values()
+            .filter(|s| s.sector_id == sector_id)
+            .cloned()
+            .collect())
+    }
+
+    fn delete_systems(&self, ids: &[i32]) -> GalaxyResult<usize> {
+        Ok(remove_all(&mut self.state.borrow_mut().systems, ids))
+    }
+
+    fn get_taken_star_names(&self, config_id: i32, names: &[String]) -> GalaxyResult<HashSet<String>> {
+        let state = self.state.borrow();
+        Ok(state
+            .systems
+            .values()
+            .filter(|s| state.sectors[&s.sector_id].config_id == config_id && names.contains(&s.name))
+            .map(|s| s.name.clone())
+            .collect())
+    }
+
+    fn insert_links(&self, links: &[NewStarLink]) -> GalaxyResult<usize> {
+        let mut state = self.state.borrow_mut();
+        for link in links {
+            state.last_link_id += 1;
+            let id = state.last_link_id;
+            state.links.insert(
+                id,
+                StarLink {
+                    id,
+                    a_id: link.a_id,
+                    a_obj_type: link.a_obj_type,
+                    b_id: link.b_id,
+                    b_obj_type: link.b_obj_type,
+                    length: link.length,
+                    link_type: link.link_type,
+                    cost: link.cost,
+                },
+            );
+        }
+        Ok(links.len())
+    }
+
+    fn get_links_for_objects(&self, ids: &[i32]) -> GalaxyResult<Vec<StarLink>> {
+        Ok(self.state
+            .borrow()
+            .links
+            .values()
+            .filter(|l| ids.contains(&l.a_id) || ids.contains(&l.b_id))
+            .cloned()
+            .collect())
+    }
+
+    fn delete_links_for_objects(&self, ids: &[i32]) -> GalaxyResult<usize> {
+        let mut state = self.state.borrow_mut();
+        let before = state.links.len();
+        state
+            .links
+            .retain(|l| !ids.contains(&l.a_id) && !ids.contains(&l.b_id));
+        Ok(before - state.links.len())
+    }
+
+    fn list_links(&self) -> GalaxyResult<Vec<StarLink>> {
+        Ok(self.state.borrow().links.values().cloned().collect())
+    }
+
+    fn delete_links(&self, ids: &[i32]) -> GalaxyResult<usize> {
+        Ok(remove_all(&mut self.state.borrow_mut().links, ids))
+    }
+
+    fn insert_player_if_missing(&self, player: &NewPlayer) -> GalaxyResult<Player> {
+        let mut state = self.state.borrow_mut();
+        if let Some(existing) = state.players.get(&player.telegram_id) {
+            return Ok(existing.clone());
+        }
+        let result = Player {
+            telegram_id: player.telegram_id,
+            name: player.name.clone(),
+            created_at: player.created_at,
+            location_id: None,
+        };
+        state.players.insert(result.telegram_id, result.clone());
+        Ok(result)
+    }
+
+    fn get_player(&self, telegram_id: i64) -> GalaxyResult<Option<Player>> {
+        Ok(self.state.borrow().players.get(&telegram_id).cloned())
+    }
+
+    fn get_located_players(&self) -> GalaxyResult<Vec<Player>> {
+        Ok(self.state
+            .borrow()
+            .players
+            .values()
+            .filter(|p| p.location_id.is_some())
+            .cloned()
+            .collect())
+    }
+
+    fn get_players_in_sector(&self, sector_id: i32) -> GalaxyResult<Vec<Player>> {
+        let ids = self.get_sector_subtree(sector_id)?
+            .into_iter()
+            .map(|o| o.id)
+            .collect::<HashSet<_>>();
+        let state = self.state.borrow();
+        Ok(state
+            .players
+            .values()
+            .filter(|p| {
+                let arriving = state.jumps.get(&p.telegram_id).is_some_and(|j| ids.contains(&j.to_id));
+                p.location_id.is_some_and(|id| ids.contains(&id)) || arriving
+            })
+            .cloned()
+            .collect())
+    }
+
+    fn update_player_location(&self, telegram_id: i64, location_id: i32) -> GalaxyResult<Player> {
+        match self.state.borrow_mut().players.get_mut(&telegram_id) {
+            Some(player) => {
+                player.location_id = Some(location_id);
+                Ok(player.clone())
+            }
+            None => Err(GalaxyError::PlayerNotFound(telegram_id)),
+        }
+    }
+
+    fn insert_jump(&self, jump: &Jump) -> GalaxyResult<()> {
+        let mut state = self.state.borrow_mut();
+        if state.jumps.contains_key(&jump.telegram_id) {
+            return Err(GalaxyError::Database(Error::DatabaseError(
+                DatabaseErrorKind::UniqueViolation,
+                Box::new("Player is already jumping".to_string()),
+            )));
+        }
+        state.jumps.insert(jump.telegram_id, jump.clone());
+        Ok(())
+    }
+
+    fn get_jump(&self, telegram_id: i64) -> GalaxyResult<Option<Jump>> {
+        Ok(self.state.borrow().jumps.get(&telegram_id).cloned())
+    }
+
+    fn get_arrived_jumps(&self, time: NaiveDateTime) -> GalaxyResult<Vec<Jump>> {
+        let mut result: Vec<Jump> = self.state
+            .borrow()
+            .jumps
+            .values()
+            .filter(|j| j.arrives_at <= time)
+            .cloned()
+            .collect();
+        result.sort_by_key(|j| j.arrives_at);
+        Ok(result)
+    }
+
+    fn list_jumps(&self) -> GalaxyResult<Vec<Jump>> {
+        Ok(self.state.borrow().jumps.values().cloned().collect())
+    }
+
+    fn get_next_arrival(&self) -> GalaxyResult<Option<NaiveDateTime>> {
+        Ok(self.state.borrow().jumps.values().map(|j| j.arrives_at).min())
+    }
+
+    fn delete_jumps(&self, telegram_ids: &[i64]) -> GalaxyResult<usize> {
+        let mut state = self.state.borrow_mut();
+        Ok(telegram_ids
+            .iter()
+            .filter(|id| state.jumps.remove(id).is_some())
+            .count())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transaction_rolls_back_on_error() {
+        let store = MemoryStore::new();
+        let result: GalaxyResult<()> = store.transaction(|| {
+            store.create_galaxy_objects(&[GalaxyObjectType::System])?;
+            Err(GalaxyError::InvalidGenerationParams("test"))
+        });
+
+        assert!(result.is_err());
+        assert_eq!(store.galaxy_object_count(), 0);
+    }
+
+    #[test]
+    fn transaction_keeps_changes_on_success() {
+        let store = MemoryStore::new();
+        store
+            .transaction(|| store.create_galaxy_objects(&[GalaxyObjectType::System]))
+            .unwrap();
+
+        assert_eq!(store.galaxy_object_count(), 1);
+    }
+
+    #[test]
+    fn failed_nested_transaction_rolls_back_only_itself() {
+        let store = MemoryStore::new();
+        store
+            .transaction(|| {
+                store.create_galaxy_objects(&[GalaxyObjectType::System])?;
+                let nested: GalaxyResult<()> = store.transaction(|| {
+                    store.create_galaxy_objects(&[GalaxyObjectType::Sector])?;
+                    store.delete_galaxy_objects(&[1])?;
+                    Err(GalaxyError::InvalidGenerationParams("test"))
+                });
+                // Caller handles the error and goes on
+                assert!(nested.is_err());
+                store.create_galaxy_objects(&[GalaxyObjectType::Sector])
+            })
+            .unwrap();
+
+        let objects = store.list_galaxy_objects().unwrap();
+        assert_eq!(
+            objects.iter().map(|o| o.obj_type).collect::<Vec<_>>(),
+            vec![GalaxyObjectType::System, GalaxyObjectType::Sector]
+        );
+        assert_eq!(objects[1].id, 2);
+    }
+
+    #[test]
+    fn panicking_transaction_is_rolled_back() {
+        let store = MemoryStore::new();
+        let result = ::std::panic::catch_unwind(::std::panic::AssertUnwindSafe(|| {
+            store.transaction(|| -> GalaxyResult<()> {
+                store.create_galaxy_objects(&[GalaxyObjectType::System])?;
+                panic!("test")
+            })
+        }));
+
+        assert!(result.is_err());
+        assert_eq!(store.galaxy_object_count(), 0);
+        assert_eq!(store.depth.get(), 0);
+    }
+}
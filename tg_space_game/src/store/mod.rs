@@ -0,0 +1,156 @@
+use super::*;
+
+use chrono::NaiveDateTime;
+use diesel::sql_types::BigInt;
+use galaxy_objects::{DeletedRows, SectorStats};
+use std::collections::HashSet;
+
+pub use self::memory::MemoryStore;
+
+#[cfg(test)]
+pub mod fixtures;
+mod memory;
+mod pg;
+mod sqlite;
+
+/// Amounts of subtree objects as databases count them
+#[derive(QueryableByName)]
+struct SubtreeCounts {
+    #[sql_type = "BigInt"]
+    sectors: i64,
+    #[sql_type = "BigInt"]
+    futures: i64,
+    #[sql_type = "BigInt"]
+    systems: i64,
+    #[sql_type = "BigInt"]
+    links: i64,
+}
+
+impl From<SubtreeCounts> for SectorStats {
+    fn from(counts: SubtreeCounts) -> Self {
+        SectorStats {
+            sectors: counts.sectors as usize,
+            futures: counts.futures as usize,
+            systems: counts.systems as usize,
+            links: counts.links as usize,
+        }
+    }
+}
+
+/// Storage of the galaxy. Generation logic only talks to the storage
+/// through this trait, so it works the same with the database and in memory.
+pub trait GalaxyStore {
+    /// Runs `f` in a transaction, that is rolled back if `f` fails
+    fn transaction<T, F>(&self, f: F) -> GalaxyResult<T>
+    where
+        F: FnOnce() -> GalaxyResult<T>;
+    /// Runs `f` in a transaction, that doesn't see changes committed after
+    /// it started. Nested in another transaction, it's a plain nested one.
+    fn snapshot_transaction<T, F>(&self, f: F) -> GalaxyResult<T>
+    where
+        F: FnOnce() -> GalaxyResult<T>;
+
+    // Galaxy objects
+
+    /// Allocates ids for new objects of given types
+    fn create_galaxy_objects(&self, types: &[GalaxyObjectType]) -> GalaxyResult<Vec<GalaxyObject>>;
+    /// Saves objects with the ids they already have
+    fn insert_galaxy_objects(&self, objects: &[GalaxyObject]) -> GalaxyResult<usize>;
+    fn get_galaxy_object(&self, id: i32) -> GalaxyResult<Option<GalaxyObject>>;
+    /// Returns objects of all galaxies
+    fn list_galaxy_objects(&self) -> GalaxyResult<Vec<GalaxyObject>>;
+    /// Changes type of the object, failing if it's not of the given type anymore
+    fn update_galaxy_object_type(&self, object: &GalaxyObject, obj_type: GalaxyObjectType) -> GalaxyResult<()>;
+    fn delete_galaxy_objects(&self, ids: &[i32]) -> GalaxyResult<usize>;
+
+    // Generation configs
+
+    /// Saves config and returns its id
+    fn insert_generation_config(&self, config: &GenerationConfig) -> GalaxyResult<i32>;
+    fn get_generation_config(&self, config_id: i32) -> GalaxyResult<GenerationConfig>;
+
+    // Sectors
+
+    fn insert_sector(&self, sector: &NewStarSector) -> GalaxyResult<StarSector>;
+    fn get_sector(&self, id: i32) -> GalaxyResult<StarSector>;
+    fn get_sectors(&self, ids: &[i32]) -> GalaxyResult<Vec<StarSector>>;
+    /// Returns sectors without parents, which are roots of their galaxies
+    fn get_root_sectors(&self) -> GalaxyResult<Vec<StarSector>>;
+    /// Returns sectors of all galaxies
+    fn list_sectors(&self) -> GalaxyResult<Vec<StarSector>>;
+    /// Returns child sectors, locking them until the end of transaction
+    fn get_child_sectors(&self, parent_id: i32) -> GalaxyResult<Vec<StarSector>>;
+    /// Returns the sector, sectors inside of it at any depth, and their
+    /// futures and systems, collected with a single query
+    fn get_sector_subtree(&self, sector_id: i32) -> GalaxyResult<Vec<GalaxyObject>>;
+    /// Counts objects of the sector subtree, and links between them
+    fn count_sector_subtree(&self, sector_id: i32) -> GalaxyResult<SectorStats>;
+    /// Deletes the sector, everything inside of it and their links without
+    /// loading them. With `keep_sector_object` the galaxy object of the
+    /// sector itself stays, so that its id can be reused.
+    fn delete_sector_subtree(&self, sector_id: i32, keep_sector_object: bool) -> GalaxyResult<DeletedRows>;
+    fn delete_sectors(&self, ids: &[i32]) -> GalaxyResult<usize>;
+
+    // Sector futures
+
+    fn insert_futures(&self, futures: &[NewStarSectorFuture]) -> GalaxyResult<Vec<StarSectorFuture>>;
+    /// Returns existing futures, locking them in the order of ids until the
+    /// end of transaction
+    fn lock_futures(&self, ids: &[i32]) -> GalaxyResult<Vec<StarSectorFuture>>;
+    /// Same as `lock_futures`, but skips futures that are locked by other
+    /// transactions instead of waiting for them
+    fn try_lock_futures(&self, ids: &[i32]) -> GalaxyResult<Vec<StarSectorFuture>>;
+    fn get_futures(&self, ids: &[i32]) -> GalaxyResult<Vec<StarSectorFuture>>;
+    fn get_child_futures(&self, sector_id: i32) -> GalaxyResult<Vec<StarSectorFuture>>;
+    /// Returns futures of all galaxies
+    fn list_futures(&self) -> GalaxyResult<Vec<StarSectorFuture>>;
+    fn delete_futures(&self, ids: &[i32]) -> GalaxyResult<usize>;
+
+    // Star systems
+
+    fn insert_systems(&self, systems: &[NewStarSystem]) -> GalaxyResult<Vec<StarSystem>>;
+    fn get_systems(&self, ids: &[i32]) -> GalaxyResult<Vec<StarSystem>>;
+    /// Returns first `limit` star systems of all galaxies
+    fn list_systems(&self, limit: i64) -> GalaxyResult<Vec<StarSystem>>;
+    fn get_child_systems(&self, sector_id: i32) -> GalaxyResult<Vec<StarSystem>>;
+    fn delete_systems(&self, ids: &[i32]) -> GalaxyResult<usize>;
+    /// Returns which of the names star systems of the galaxy that uses given
+    /// config already have
+    fn get_taken_star_names(&self, config_id: i32, names: &[String]) -> GalaxyResult<HashSet<String>>;
+
+    // Star links
+
+    fn insert_links(&self, links: &[NewStarLink]) -> GalaxyResult<usize>;
+    /// Returns links that have any of the objects on either side
+    fn get_links_for_objects(&self, ids: &[i32]) -> GalaxyResult<Vec<StarLink>>;
+    fn delete_links_for_objects(&self, ids: &[i32]) -> GalaxyResult<usize>;
+    /// Returns links of all galaxies
+    fn list_links(&self) -> GalaxyResult<Vec<StarLink>>;
+    fn delete_links(&self, ids: &[i32]) -> GalaxyResult<usize>;
+
+    // Players
+
+    /// Saves the player, unless there's one with the same Telegram id
+    /// already, and returns the saved one
+    fn insert_player_if_missing(&self, player: &NewPlayer) -> GalaxyResult<Player>;
+    fn get_player(&self, telegram_id: i64) -> GalaxyResult<Option<Player>>;
+    /// Returns players that are somewhere in the galaxy
+    fn get_located_players(&self) -> GalaxyResult<Vec<Player>>;
+    /// Returns players inside of the sector at any depth, or flying there
+    fn get_players_in_sector(&self, sector_id: i32) -> GalaxyResult<Vec<Player>>;
+    /// Moves player to the galaxy object
+    fn update_player_location(&self, telegram_id: i64, location_id: i32) -> GalaxyResult<Player>;
+
+    // Jumps
+
+    fn insert_jump(&self, jump: &Jump) -> GalaxyResult<()>;
+    fn get_jump(&self, telegram_id: i64) -> GalaxyResult<Option<Jump>>;
+    /// Returns jumps that arrive by `time`, locking them until the end of
+    /// transaction
+    fn get_arrived_jumps(&self, time: NaiveDateTime) -> GalaxyResult<Vec<Jump>>;
+    /// Returns jumps of all players
+    fn list_jumps(&self) -> GalaxyResult<Vec<Jump>>;
+    /// Returns when the earliest of jumps arrives
+    fn get_next_arrival(&self) -> GalaxyResult<Option<NaiveDateTime>>;
+    fn delete_jumps(&self, telegram_ids: &[i64]) -> GalaxyResult<usize>;
+}
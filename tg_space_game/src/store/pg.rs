@@ -0,0 +1,507 @@
+use super::*;
+
+use diesel::connection::TransactionManager;
+use diesel::sql_types::Integer;
+
+/// Sector with sectors inside of it at any depth, and their futures and
+/// systems. UNION drops sectors that were already visited, so cycles of
+/// parents don't make the query endless.
+const SECTOR_SUBTREE: &str = "WITH RECURSIVE subtree (id) AS ( \
+         SELECT id FROM star_sectors WHERE id = $1 \
+         UNION \
+         SELECT s.id FROM star_sectors s JOIN subtree t ON s.parent_id = t.id \
+     ) \
+     SELECT id, 'sector'::galaxy_object_type AS obj_type FROM subtree \
+     UNION ALL \
+     SELECT id, 'sector_future'::galaxy_object_type FROM star_sector_futures \
+         WHERE parent_id IN (SELECT id FROM subtree) \
+     UNION ALL \
+     SELECT id, 'system'::galaxy_object_type FROM star_systems \
+         WHERE sector_id IN (SELECT id FROM subtree)";
+
+impl GalaxyStore for PgConnection {
+    fn transaction<T, F>(&self, f: F) -> GalaxyResult<T>
+    where
+        F: FnOnce() -> GalaxyResult<T>,
+    {
+        Connection::transaction(self, f)
+    }
+
+    fn snapshot_transaction<T, F>(&self, f: F) -> GalaxyResult<T>
+    where
+        F: FnOnce() -> GalaxyResult<T>,
+    {
+        // Isolation level can only be chosen by the outermost transaction
+        if TransactionManager::<PgConnection>::get_transaction_depth(self.transaction_manager()) == 0 {
+            self.build_transaction().repeatable_read().run(f)
+        } else {
+            Connection::transaction(self, f)
+        }
+    }
+
+    fn create_galaxy_objects(&self, types: &[GalaxyObjectType]) -> GalaxyResult<Vec<GalaxyObject>> {
+        use schema::galaxy_objects::dsl::*;
+        let new_objects = types
+            .iter()
+            .map(|t| NewGalaxyObject { obj_type: *t })
+            .collect::<Vec<_>>();
+        diesel::insert_into(galaxy_objects)
+            .values(&new_objects)
+            .get_results(self)
+            .map_err(GalaxyError::from)
+    }
+
+    fn insert_galaxy_objects(&self, objects: &[GalaxyObject]) -> GalaxyResult<usize> {
+        use schema::galaxy_objects::dsl::*;
+        let rows = objects
+            .iter()
+            .map(|o| (id.eq(o.id), obj_type.eq(o.obj_type)))
+            .collect::<Vec<_>>();
+        diesel::insert_into(galaxy_objects)
+            .values(&rows)
+            .execute(self)
+            .map_err(GalaxyError::from)
+    }
+
+    fn list_galaxy_objects(&self) -> GalaxyResult<Vec<GalaxyObject>> {
+        use schema::galaxy_objects::dsl::*;
+        galaxy_objects
+            .order(id)
+            .load(self)
+            .map_err(GalaxyError::from)
+    }
+
+    fn get_galaxy_object(&self, object_id: i32) -> GalaxyResult<Option<GalaxyObject>> {
+        use schema::galaxy_objects::dsl::*;
+        galaxy_objects
+            .find(object_id)
+            .get_result(self)
+            .optional()
+            .map_err(GalaxyError::from)
+    }
+
+    fn update_galaxy_object_type(&self, object: &GalaxyObject, object_type: GalaxyObjectType) -> GalaxyResult<()> {
+        use schema::galaxy_objects::dsl::*;
+        let updated = diesel::update(
+            galaxy_objects
+                .filter(id.eq(object.id))
+                .filter(obj_type.eq(object.obj_type)),
+        ).set(obj_type.eq(object_type))
+            .execute(self)?;
+        match updated {
+            0 => Err(GalaxyError::ObjectNotFound(object.clone())),
+            _ => Ok(()),
+        }
+    }
+
+    fn delete_galaxy_objects(&self, ids: &[i32]) -> GalaxyResult<usize> {
+        use schema::galaxy_objects::dsl::*;
+        diesel::delete(galaxy_objects.filter(id.eq_any(ids)))
+            .execute(self)
+            .map_err(GalaxyError::from)
+    }
+
+    fn insert_generation_config(&self, config: &GenerationConfig) -> GalaxyResult<i32> {
+        use schema::generation_configs::dsl::*;
+        diesel::insert_into(generation_configs)
+            .values(config)
+            .returning(id)
+            .get_result(self)
+            .map_err(GalaxyError::from)
+    }
+
+    fn get_generation_config(&self, config_id: i32) -> GalaxyResult<GenerationConfig> {
+        use schema::generation_configs::dsl::*;
+        generation_configs
+            .find(config_id)
+            .select((
+                branching_factor,
+                leaf_threshold,
+                link_density,
+                weight_distribution,
+                radius_scaling,
+                name_style,
+                link_reattach,
+                link_strategy,
+            ))
+            .get_result(self)
+            .map_err(GalaxyError::from)
+    }
+
+    fn insert_sector(&self, sector: &NewStarSector) -> GalaxyResult<StarSector> {
+        use schema::star_sectors::dsl::*;
+        diesel::insert_into(star_sectors)
+            .values(sector)
+            .get_result(self)
+            .map_err(GalaxyError::from)
+    }
+
+    fn get_sector(&self, sector_id: i32) -> GalaxyResult<StarSector> {
+        use schema::star_sectors::dsl::*;
+        star_sectors
+            .find(sector_id)
+            .get_result(self)
+            .map_err(GalaxyError::not_found(GalaxyObject {
+                id: sector_id,
+                obj_type: GalaxyObjectType::Sector,
+            }))
+    }
+
+    fn get_sectors(&self, ids: &[i32]) -> GalaxyResult<Vec<StarSector>> {
+        use schema::star_sectors::dsl::*;
+        star_sectors
+            .filter(id.eq_any(ids))
+            .load(self)
+            .map_err(GalaxyError::from)
+    }
+
+    fn get_root_sectors(&self) -> GalaxyResult<Vec<StarSector>> {
+        use schema::star_sectors::dsl::*;
+        star_sectors
+            .filter(parent_id.is_null())
+            .order(id)
+            .load(self)
+            .map_err(GalaxyError::from)
+    }
+
+    fn list_sectors(&self) -> GalaxyResult<Vec<StarSector>> {
+        use schema::star_sectors::dsl::*;
+        star_sectors
+            .order(id)
+            .load(self)
+            .map_err(GalaxyError::from)
+    }
+
+    fn get_child_sectors(&self, sector_id: i32) -> GalaxyResult<Vec<StarSector>> {
+        use schema::star_sectors::dsl::*;
+        star_sectors
+            .filter(parent_id.eq(sector_id))
+            .for_update()
+            .load(self)
+            .map_err(GalaxyError::from)
+    }
+
+    fn get_sector_subtree(&self, sector_id: i32) -> GalaxyResult<Vec<GalaxyObject>> {
+        diesel::sql_query(SECTOR_SUBTREE)
+            .bind::<Integer, _>(sector_id)
+            .load(self)
+            .map_err(GalaxyError::from)
+    }
+
+    fn count_sector_subtree(&self, sector_id: i32) -> GalaxyResult<SectorStats> {
+        diesel::sql_query(format!(
+            "WITH objects AS ({}) \
+             SELECT \
+                 (SELECT count(*) FROM objects WHERE obj_type = 'sector') AS sectors, \
+                 (SELECT count(*) FROM objects WHERE obj_type = 'sector_future') AS futures, \
+                 (SELECT count(*) FROM objects WHERE obj_type = 'system') AS systems, \
+                 (SELECT count(*) FROM star_links \
+                     WHERE a_id IN (SELECT id FROM objects) AND b_id IN (SELECT id FROM objects)) AS links",
+            SECTOR_SUBTREE
+        )).bind::<Integer, _>(sector_id)
+            .get_result::<SubtreeCounts>(self)
+            .map(SectorStats::from)
+            .map_err(GalaxyError::from)
+    }
+
+    fn delete_sector_subtree(&self, sector_id: i32, keep_sector_object: bool) -> GalaxyResult<DeletedRows> {
+        // Ids stay in the database, so there are no limits on their amount
+        GalaxyStore::transaction(self, || {
+            diesel::sql_query("CREATE TEMPORARY TABLE subtree_objects (id INTEGER PRIMARY KEY, obj_type galaxy_object_type NOT NULL)")
+                .execute(self)?;
+            diesel::sql_query(format!("INSERT INTO subtree_objects (id, obj_type) {}", SECTOR_SUBTREE))
+                .bind::<Integer, _>(sector_id)
+                .execute(self)?;
+            // Without statistics the planner expects a tiny table
+            diesel::sql_query("ANALYZE subtree_objects").execute(self)?;
+            let delete_inside = |table: &str, condition: &str| {
+                diesel::sql_query(format!("DELETE FROM {} WHERE {}", table, condition)).execute(self)
+            };
+            let inside = "id IN (SELECT id FROM subtree_objects)";
+            // Sides are deleted one by one, so that both use their indexes
+            let links = delete_inside("star_links", "a_id IN (SELECT id FROM subtree_objects)")?
+                + delete_inside("star_links", "b_id IN (SELECT id FROM subtree_objects)")?;
+            let systems = delete_inside("star_systems", inside)?;
+            let futures = delete_inside("star_sector_futures", inside)?;
+            let sectors = delete_inside("star_sectors", inside)?;
+            let galaxy_objects = if keep_sector_object {
+                delete_inside("galaxy_objects", &format!("{} AND id <> {}", inside, sector_id))?
+            } else {
+                delete_inside("galaxy_objects", inside)?
+            };
+            diesel::sql_query("DROP TABLE subtree_objects").execute(self)?;
+            Ok(DeletedRows {
+                sectors,
+                futures,
+                systems,
+                links,
+                galaxy_objects,
+            })
+        })
+    }
+
+    fn delete_sectors(&self, ids: &[i32]) -> GalaxyResult<usize> {
+        use schema::star_sectors::dsl::*;
+        diesel::delete(star_sectors.filter(id.eq_any(ids)))
+            .execute(self)
+            .map_err(GalaxyError::from)
+    }
+
+    fn insert_futures(&self, futures: &[NewStarSectorFuture]) -> GalaxyResult<Vec<StarSectorFuture>> {
+        use schema::star_sector_futures::dsl::*;
+        diesel::insert_into(star_sector_futures)
+            .values(futures)
+            .get_results(self)
+            .map_err(GalaxyError::from)
+    }
+
+    fn lock_futures(&self, ids: &[i32]) -> GalaxyResult<Vec<StarSectorFuture>> {
+        use schema::star_sector_futures::dsl::*;
+        star_sector_futures
+            .filter(id.eq_any(ids))
+            .order(id)
+            .for_update()
+            .load(self)
+            .map_err(GalaxyError::from)
+    }
+
+    fn try_lock_futures(&self, ids: &[i32]) -> GalaxyResult<Vec<StarSectorFuture>> {
+        use schema::star_sector_futures::dsl::*;
+        star_sector_futures
+            .filter(id.eq_any(ids))
+            .order(id)
+            .for_update()
+            .skip_locked()
+            .load(self)
+            .map_err(GalaxyError::from)
+    }
+
+    fn get_futures(&self, ids: &[i32]) -> GalaxyResult<Vec<StarSectorFuture>> {
+        use schema::star_sector_futures::dsl::*;
+        star_sector_futures
+            .filter(id.eq_any(ids))
+            .load(self)
+            .map_err(GalaxyError::from)
+    }
+
+    fn get_child_futures(&self, sector_id: i32) -> GalaxyResult<Vec<StarSectorFuture>> {
+        use schema::star_sector_futures::dsl::*;
+        star_sector_futures
+            .filter(parent_id.eq(sector_id))
+            .order(id)
+            .load(self)
+            .map_err(GalaxyError::from)
+    }
+
+    fn list_futures(&self) -> GalaxyResult<Vec<StarSectorFuture>> {
+        use schema::star_sector_futures::dsl::*;
+        star_sector_futures
+            .order(id)
+            .load(self)
+            .map_err(GalaxyError::from)
+    }
+
+    fn delete_futures(&self, ids: &[i32]) -> GalaxyResult<usize> {
+        use schema::star_sector_futures::dsl::*;
+        diesel::delete(star_sector_futures.filter(id.eq_any(ids)))
+            .execute(self)
+            .map_err(GalaxyError::from)
+    }
+
+    fn insert_systems(&self, systems: &[NewStarSystem]) -> GalaxyResult<Vec<StarSystem>> {
+        use schema::star_systems::dsl::*;
+        diesel::insert_into(star_systems)
+            .values(systems)
+            .get_results(self)
+            .map_err(GalaxyError::from)
+    }
+
+    fn get_systems(&self, ids: &[i32]) -> GalaxyResult<Vec<StarSystem>> {
+        use schema::star_systems::dsl::*;
+        star_systems
+            .filter(id.eq_any(ids))
+            .load(self)
+            .map_err(GalaxyError::from)
+    }
+
+    fn list_systems(&self, limit: i64) -> GalaxyResult<Vec<StarSystem>> {
+        use schema::star_systems::dsl::*;
+        star_systems
+            .order(id)
+            .limit(limit)
+            .load(self)
+            .map_err(GalaxyError::from)
+    }
+
+    fn get_child_systems(&self, sector: i32) -> GalaxyResult<Vec<StarSystem>> {
+        use schema::star_systems::dsl::*;
+        star_systems
+            .filter(sector_id.eq(sector))
+            .order(id)
+            .load(self)
+            .map_err(GalaxyError::from)
+    }
+
+    fn delete_systems(&self, ids: &[i32]) -> GalaxyResult<usize> {
+        use schema::star_systems::dsl::*;
+        diesel::delete(star_systems.filter(id.eq_any(ids)))
+            .execute(self)
+            .map_err(GalaxyError::from)
+    }
+
+    fn get_taken_star_names(&self, galaxy_config_id: i32, names: &[String]) -> GalaxyResult<HashSet<String>> {
+        use schema::star_sectors;
+        use schema::star_systems;
+        let taken = star_systems::table
+            .inner_join(star_sectors::table)
+            .filter(star_sectors::config_id.eq(galaxy_config_id))
+            .filter(star_systems::name.eq_any(names))
+            .select(star_systems::name)
+            .load::<String>(self)?;
+        Ok(taken.into_iter().collect())
+    }
+
+    fn insert_links(&self, links: &[NewStarLink]) -> GalaxyResult<usize> {
+        use schema::star_links::dsl::*;
+        diesel::insert_into(star_links)
+            .values(links)
+            .execute(self)
+            .map_err(GalaxyError::from)
+    }
+
+    fn get_links_for_objects(&self, ids: &[i32]) -> GalaxyResult<Vec<StarLink>> {
+        use schema::star_links::dsl::*;
+        star_links
+            .filter(a_id.eq_any(ids))
+            .or_filter(b_id.eq_any(ids))
+            .load(self)
+            .map_err(GalaxyError::from)
+    }
+
+    fn delete_links_for_objects(&self, ids: &[i32]) -> GalaxyResult<usize> {
+        use schema::star_links::dsl::*;
+        diesel::delete(
+            star_links
+                .filter(a_id.eq_any(ids))
+                .or_filter(b_id.eq_any(ids)),
+        ).execute(self)
+            .map_err(GalaxyError::from)
+    }
+
+    fn list_links(&self) -> GalaxyResult<Vec<StarLink>> {
+        use schema::star_links::dsl::*;
+        star_links
+            .order(id)
+            .load(self)
+            .map_err(GalaxyError::from)
+    }
+
+    fn delete_links(&self, ids: &[i32]) -> GalaxyResult<usize> {
+        use schema::star_links::dsl::*;
+        diesel::delete(star_links.filter(id.eq_any(ids)))
+            .execute(self)
+            .map_err(GalaxyError::from)
+    }
+
+    fn insert_player_if_missing(&self, player: &NewPlayer) -> GalaxyResult<Player> {
+        use schema::players::dsl::*;
+        // Waits for concurrent inserts of the same player instead of failing
+        diesel::insert_into(players)
+            .values(player)
+            .on_conflict(telegram_id)
+            .do_nothing()
+            .execute(self)?;
+        players
+            .find(player.telegram_id)
+            .get_result(self)
+            .map_err(GalaxyError::from)
+    }
+
+    fn get_player(&self, player_id: i64) -> GalaxyResult<Option<Player>> {
+        use schema::players::dsl::*;
+        players
+            .find(player_id)
+            .get_result(self)
+            .optional()
+            .map_err(GalaxyError::from)
+    }
+
+    fn get_located_players(&self) -> GalaxyResult<Vec<Player>> {
+        use schema::players::dsl::*;
+        players
+            .filter(location_id.is_not_null())
+            .order(telegram_id)
+            .load(self)
+            .map_err(GalaxyError::from)
+    }
+
+    fn get_players_in_sector(&self, sector_id: i32) -> GalaxyResult<Vec<Player>> {
+        diesel::sql_query(format!(
+            "WITH objects AS ({}) \
+             SELECT * FROM players \
+             WHERE location_id IN (SELECT id FROM objects) \
+                 OR telegram_id IN (SELECT telegram_id FROM jumps WHERE to_id IN (SELECT id FROM objects)) \
+             ORDER BY telegram_id",
+            SECTOR_SUBTREE
+        )).bind::<Integer, _>(sector_id)
+            .load(self)
+            .map_err(GalaxyError::from)
+    }
+
+    fn update_player_location(&self, player_id: i64, location: i32) -> GalaxyResult<Player> {
+        use schema::players::dsl::*;
+        diesel::update(players.find(player_id))
+            .set(location_id.eq(location))
+            .get_result(self)
+            .optional()?
+            .ok_or(GalaxyError::PlayerNotFound(player_id))
+    }
+
+    fn insert_jump(&self, jump: &Jump) -> GalaxyResult<()> {
+        use schema::jumps::dsl::*;
+        diesel::insert_into(jumps)
+            .values(jump)
+            .execute(self)
+            .map(|_| ())
+            .map_err(GalaxyError::from)
+    }
+
+    fn get_jump(&self, player_id: i64) -> GalaxyResult<Option<Jump>> {
+        use schema::jumps::dsl::*;
+        jumps
+            .find(player_id)
+            .get_result(self)
+            .optional()
+            .map_err(GalaxyError::from)
+    }
+
+    fn get_arrived_jumps(&self, time: NaiveDateTime) -> GalaxyResult<Vec<Jump>> {
+        use schema::jumps::dsl::*;
+        jumps
+            .filter(arrives_at.le(time))
+            .order(arrives_at)
+            .for_update()
+            .load(self)
+            .map_err(GalaxyError::from)
+    }
+
+    fn list_jumps(&self) -> GalaxyResult<Vec<Jump>> {
+        use schema::jumps::dsl::*;
+        jumps.order(telegram_id).load(self).map_err(GalaxyError::from)
+    }
+
+    fn get_next_arrival(&self) -> GalaxyResult<Option<NaiveDateTime>> {
+        use schema::jumps::dsl::*;
+        jumps
+            .select(diesel::dsl::min(arrives_at))
+            .get_result(self)
+            .map_err(GalaxyError::from)
+    }
+
+    fn delete_jumps(&self, player_ids: &[i64]) -> GalaxyResult<usize> {
+        use schema::jumps::dsl::*;
+        diesel::delete(jumps.filter(telegram_id.eq_any(player_ids)))
+            .execute(self)
+            .map_err(GalaxyError::from)
+    }
+}
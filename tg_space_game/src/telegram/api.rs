@@ -0,0 +1,92 @@
+use super::types::*;
+
+use serde::de::DeserializeOwned;
+use std::fmt;
+use std::io;
+use std::time::Duration;
+
+pub const DEFAULT_API_URL: &str = "https://api.telegram.org";
+
+#[derive(Debug)]
+pub enum TelegramError {
+    /// Request didn't reach Telegram, or response couldn't be read
+    Transport(String),
+    /// Telegram refused the request
+    Api(String),
+}
+
+impl fmt::Display for TelegramError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TelegramError::Transport(ref reason) => write!(f, "Error reaching Telegram: {}", reason),
+            TelegramError::Api(ref reason) => write!(f, "Telegram error: {}", reason),
+        }
+    }
+}
+
+impl From<io::Error> for TelegramError {
+    fn from(err: io::Error) -> Self {
+        TelegramError::Transport(err.to_string())
+    }
+}
+
+/// Client of Telegram Bot API
+pub struct TelegramApi {
+    base_url: String,
+    token: String,
+    agent: ureq::Agent,
+}
+
+impl TelegramApi {
+    /// `base_url` is `DEFAULT_API_URL`, unless requests go to a proxy or
+    /// a mock server
+    pub fn new(base_url: &str, token: &str) -> TelegramApi {
+        TelegramApi {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            token: token.to_string(),
+            // Long enough to outlive long polling
+            agent: ureq::AgentBuilder::new()
+                .timeout_read(Duration::from_secs(60))
+                .build(),
+        }
+    }
+
+    fn url(&self, method: &str) -> String {
+        format!("{}/bot{}/{}", self.base_url, self.token, method)
+    }
+
+    /// Calls the method. Errors never mention the url, because it has the
+    /// token in it.
+    fn call<T: DeserializeOwned>(&self, method: &str, body: impl serde::Serialize) -> Result<T, TelegramError> {
+        let response: Response<T> = match self.agent.post(&self.url(method)).send_json(body) {
+            Ok(response) => response.into_json()?,
+            // Refused requests still have a description in the body
+            Err(ureq::Error::Status(_, response)) => response.into_json()?,
+            Err(ureq::Error::Transport(err)) => {
+                return Err(TelegramError::Transport(format!("{} in {}", err.kind(), method)))
+            }
+        };
+        match response.result {
+            Some(result) if response.ok => Ok(result),
+            _ => Err(TelegramError::Api(
+                response
+                    .description
+                    .unwrap_or_else(|| "Unknown error".to_string()),
+            )),
+        }
+    }
+
+    /// Waits up to `timeout` seconds for updates newer than `offset`
+    pub fn get_updates(&self, offset: i64, timeout: u32) -> Result<Vec<Update>, TelegramError> {
+        #[derive(Serialize)]
+        struct GetUpdates {
+            offset: i64,
+            timeout: u32,
+        }
+        self.call("getUpdates", GetUpdates { offset, timeout })
+    }
+
+    pub fn send_message(&self, chat_id: i64, text: &str) -> Result<Message, TelegramError> {
+        self.call("sendMessage", SendMessage { chat_id, text })
+    }
+}
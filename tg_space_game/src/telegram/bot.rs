@@ -0,0 +1,334 @@
+use super::*;
+
+use chrono::{NaiveDateTime, Utc};
+use galaxy_objects::get_object_position;
+use movement::*;
+use players::*;
+use store::GalaxyStore;
+use telegram::api::{TelegramApi, TelegramError};
+
+const HELP: &str = "/start - enter the galaxy\n\
+                    /where - show where you are\n\
+                    /scan - show systems you can jump to\n\
+                    /jump <id> - jump to one of them";
+
+/// Seconds that Telegram holds a long polling request open
+const POLL_TIMEOUT: u32 = 30;
+
+/// Plays the game with Telegram users
+pub struct Bot<'a, S: 'a> {
+    api: TelegramApi,
+    store: &'a S,
+    /// Root sector that new players enter, first root by default
+    galaxy: Option<i32>,
+    /// Next update to receive
+    offset: i64,
+}
+
+impl<'a, S: GalaxyStore> Bot<'a, S> {
+    pub fn new(api: TelegramApi, store: &'a S, galaxy: Option<i32>) -> Bot<'a, S> {
+        Bot {
+            api,
+            store,
+            galaxy,
+            offset: 0,
+        }
+    }
+
+    /// Receives a batch of updates and answers them. Returns amount of
+    /// handled updates. Waits for updates no longer than until the next
+    /// ship arrives, so that ships land in time.
+    pub fn poll(&mut self) -> Result<usize, TelegramError> {
+        self.land(now());
+        let updates = self.api.get_updates(self.offset, self.poll_timeout(now()))?;
+        // Ships land before anyone can ask where they are
+        self.land(now());
+        for update in &updates {
+            self.offset = self.offset.max(update.update_id + 1);
+            let message = match update.message {
+                Some(ref message) => message,
+                None => continue,
+            };
+            if let (Some(ref user), Some(ref text)) = (&message.from, &message.text) {
+                let reply = self.handle(user, text);
+                self.api.send_message(message.chat.id, &reply)?;
+            }
+        }
+        Ok(updates.len())
+    }
+
+    /// Seconds until the next ship arrives, rounded up, or `POLL_TIMEOUT`
+    /// if that's sooner
+    fn poll_timeout(&self, now: NaiveDateTime) -> u32 {
+        match get_next_arrival(self.store) {
+            Ok(Some(arrives_at)) => {
+                let millis = (arrives_at - now).num_milliseconds().max(0);
+                ((millis + 999) / 1000).min(i64::from(POLL_TIMEOUT)) as u32
+            }
+            Ok(None) => POLL_TIMEOUT,
+            Err(err) => {
+                error!("Error getting arrivals: {}", err);
+                POLL_TIMEOUT
+            }
+        }
+    }
+
+    /// Lands arrived ships and tells their players where they are. Players
+    /// that can't be told are landed anyway. Returns amount of landed ships.
+    pub fn land(&self, now: NaiveDateTime) -> usize {
+        let arrived = match land_arrived_jumps(self.store, now) {
+            Ok(arrived) => arrived,
+            Err(err) => {
+                error!("Error landing ships: {}", err);
+                return 0;
+            }
+        };
+        for jump in &arrived {
+            let description = self
+                .store
+                .get_galaxy_object(jump.to_id)
+                .and_then(|destination| match destination {
+                    Some(destination) => self.describe(&destination),
+                    None => Err(GalaxyError::ObjectNotFound(GalaxyObject {
+                        id: jump.to_id,
+                        obj_type: GalaxyObjectType::System,
+                    })),
+                });
+            let sent = match description {
+                // Private chats have the same id as their users
+                Ok(description) => self
+                    .api
+                    .send_message(jump.telegram_id, &format!("You have arrived!\n{}", description))
+                    .map(|_| ())
+                    .map_err(|err| err.to_string()),
+                Err(err) => Err(err.to_string()),
+            };
+            if let Err(err) = sent {
+                warn!("Error telling {} about arrival: {}", jump.telegram_id, err);
+            }
+        }
+        arrived.len()
+    }
+
+    /// Executes a command, returning the reply
+    pub fn handle(&mut self, user: &User, text: &str) -> String {
+        let command = Command::parse(text);
+        info!("{} sent {:?}", user.id, command);
+        let result = match command {
+            Command::Start => self.start(user),
+            Command::Where => self.with_location(user, |bot, location| bot.describe(&location)),
+            Command::Scan => self.with_location(user, |bot, location| bot.scan(&location)),
+            Command::Jump(id) => self.with_location(user, |bot, _| bot.jump(user, id)),
+            Command::Help | Command::Unknown(_) => Ok(HELP.to_string()),
+        };
+        match result {
+            Ok(reply) => reply,
+            Err(ref err) if err.is_user_error() => err.to_string(),
+            Err(err) => {
+                error!("Error handling {:?} from {}: {}", text, user.id, err);
+                "Something went wrong, try again later".to_string()
+            }
+        }
+    }
+
+    fn with_location<F>(&mut self, user: &User, f: F) -> GalaxyResult<String>
+    where
+        F: FnOnce(&mut Self, GalaxyObject) -> GalaxyResult<String>,
+    {
+        if let Some(jump) = get_player_jump(self.store, user.id)? {
+            return self.describe_jump(&jump);
+        }
+        match self.location(user)? {
+            Some(location) => f(self, location),
+            None => Ok("You are not in the galaxy yet, send /start".to_string()),
+        }
+    }
+
+    fn location(&self, user: &User) -> GalaxyResult<Option<GalaxyObject>> {
+        match find_player(self.store, user.id)? {
+            Some(player) => get_player_location(self.store, &player),
+            None => Ok(None),
+        }
+    }
+
+    fn start(&mut self, user: &User) -> GalaxyResult<String> {
+        let player = register_player(self.store, user.id, &user.first_name)?;
+        if let Some(location) = get_player_location(self.store, &player)? {
+            return self.describe(&location);
+        }
+        let galaxy = match self.galaxy {
+            Some(id) => id,
+            None => match self.store.get_root_sectors()?.first() {
+                Some(root) => root.id,
+                None => return Ok("There are no star systems yet".to_string()),
+            },
+        };
+        let system = spawn_player(self.store, user.id, galaxy)?;
+        Ok(format!(
+            "Welcome, {}!\n{}",
+            player.name,
+            self.describe(&GalaxyObject::from(&system))?
+        ))
+    }
+
+    fn describe(&self, location: &GalaxyObject) -> GalaxyResult<String> {
+        let system = self.system(location)?;
+        Ok(format!(
+            "You are at {} ({:.1}, {:.1}, {:.1})",
+            system.name, system.x, system.y, system.z
+        ))
+    }
+
+    fn describe_jump(&self, jump: &Jump) -> GalaxyResult<String> {
+        let destination = self.store.get_systems(&[jump.to_id])?;
+        let left = (jump.arrives_at - now()).num_seconds().max(0);
+        Ok(format!(
+            "Jumping to {}, arriving in {} s",
+            destination.first().map_or("uncharted region", |s| s.name.as_str()),
+            left
+        ))
+    }
+
+    fn scan(&self, location: &GalaxyObject) -> GalaxyResult<String> {
+        let position = get_object_position(self.store, location)?;
+        let mut lines = vec!["You can jump to:".to_string()];
+        for neighbour in get_neighbours(self.store, location)? {
+            let distance = get_object_position(self.store, &neighbour)?.distance(&position);
+            let name = match neighbour.obj_type {
+                GalaxyObjectType::System => self.system(&neighbour)?.name,
+                _ => "Uncharted region".to_string(),
+            };
+            lines.push(format!("{} - {} ({:.1} away)", neighbour.id, name, distance));
+        }
+        Ok(lines.join("\n"))
+    }
+
+    fn jump(&mut self, user: &User, target_id: i32) -> GalaxyResult<String> {
+        let jump = start_jump(self.store, user.id, target_id, now())?;
+        self.describe_jump(&jump)
+    }
+
+    fn system(&self, object: &GalaxyObject) -> GalaxyResult<StarSystem> {
+        self.store
+            .get_systems(&[object.id])?
+            .pop()
+            .ok_or_else(|| GalaxyError::ObjectNotFound(object.clone()))
+    }
+}
+
+fn now() -> NaiveDateTime {
+    Utc::now().naive_utc()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use store::fixtures::galaxy;
+    use store::MemoryStore;
+
+    fn user() -> User {
+        User {
+            id: 1,
+            first_name: "Tester".to_string(),
+            username: None,
+        }
+    }
+
+    fn bot<'a>(store: &'a MemoryStore) -> Bot<'a, MemoryStore> {
+        galaxy(store, 200f32, 1);
+        Bot::new(TelegramApi::new("http://localhost:1", "token"), store, None)
+    }
+
+    #[test]
+    fn commands_need_start_first() {
+        let store = MemoryStore::new();
+        let mut bot = bot(&store);
+        assert!(bot.handle(&user(), "/scan").contains("/start"));
+    }
+
+    #[test]
+    fn start_spawns_player_at_star_system() {
+        let store = MemoryStore::new();
+        let mut bot = bot(&store);
+        assert!(bot.handle(&user(), "/start").starts_with("Welcome, Tester!"));
+
+        let location = bot.location(&user()).unwrap().unwrap();
+        assert_eq!(location.obj_type, GalaxyObjectType::System);
+    }
+
+    #[test]
+    fn jump_keeps_player_in_flight_until_arrival() {
+        let store = MemoryStore::new();
+        let mut bot = bot(&store);
+        bot.handle(&user(), "/start");
+        let from = bot.location(&user()).unwrap().unwrap();
+        let target = get_neighbours(&store, &from).unwrap()[0].clone();
+
+        assert!(bot.handle(&user(), &format!("/jump {}", target.id)).starts_with("Jumping to"));
+        assert!(bot.handle(&user(), "/where").starts_with("Jumping to"));
+        assert_eq!(bot.location(&user()).unwrap().unwrap(), from);
+
+        let jump = get_player_jump(&store, user().id).unwrap().unwrap();
+        land_arrived_jumps(&store, jump.arrives_at).unwrap();
+        assert_eq!(bot.location(&user()).unwrap().unwrap().id, jump.to_id);
+        assert!(bot.handle(&user(), "/where").starts_with("You are at"));
+    }
+
+    #[test]
+    fn land_lands_everyone_when_messages_fail() {
+        let store = MemoryStore::new();
+        let mut bot = bot(&store);
+        let users = (1..4)
+            .map(|id| User {
+                id,
+                first_name: "Tester".to_string(),
+                username: None,
+            })
+            .collect::<Vec<_>>();
+        let mut last_arrival = now();
+        for user in &users {
+            bot.handle(user, "/start");
+            let from = bot.location(user).unwrap().unwrap();
+            let target = get_neighbours(&store, &from).unwrap()[0].clone();
+            bot.handle(user, &format!("/jump {}", target.id));
+            last_arrival = last_arrival.max(get_player_jump(&store, user.id).unwrap().unwrap().arrives_at);
+        }
+
+        // Nothing listens to the bot's api url, so every message fails
+        assert_eq!(bot.land(last_arrival), users.len());
+        for user in &users {
+            assert_eq!(get_player_jump(&store, user.id).unwrap(), None);
+        }
+    }
+
+    #[test]
+    fn poll_waits_until_next_arrival() {
+        let store = MemoryStore::new();
+        let mut bot = bot(&store);
+        let time = now();
+        assert_eq!(bot.poll_timeout(time), POLL_TIMEOUT);
+
+        bot.handle(&user(), "/start");
+        let from = bot.location(&user()).unwrap().unwrap();
+        let target = get_neighbours(&store, &from).unwrap()[0].clone();
+        bot.handle(&user(), &format!("/jump {}", target.id));
+        let jump = get_player_jump(&store, user().id).unwrap().unwrap();
+
+        let half_second = chrono::Duration::milliseconds(500);
+        assert_eq!(bot.poll_timeout(jump.arrives_at - half_second), 1);
+        assert_eq!(bot.poll_timeout(jump.arrives_at + half_second), 0);
+        let long_ago = jump.arrives_at - chrono::Duration::seconds(i64::from(POLL_TIMEOUT) + 1);
+        assert_eq!(bot.poll_timeout(long_ago), POLL_TIMEOUT);
+    }
+
+    #[test]
+    fn jump_refuses_unlinked_targets() {
+        let store = MemoryStore::new();
+        let mut bot = bot(&store);
+        bot.handle(&user(), "/start");
+        let from = bot.location(&user()).unwrap().unwrap();
+
+        assert!(bot.handle(&user(), "/jump -1").contains("can't be reached"));
+        assert_eq!(bot.location(&user()).unwrap().unwrap(), from);
+    }
+}
@@ -0,0 +1,58 @@
+/// Command sent to the bot by a player
+#[derive(Debug, PartialEq, Clone)]
+pub enum Command {
+    /// Enter the galaxy
+    Start,
+    /// Show current location
+    Where,
+    /// Show neighbouring systems
+    Scan,
+    /// Jump to a neighbouring system or region by its id
+    Jump(i32),
+    Help,
+    Unknown(String),
+}
+
+impl Command {
+    /// Parses message text, like `/jump 42` or `/scan@SpaceBot`
+    pub fn parse(text: &str) -> Command {
+        let mut words = text.split_whitespace();
+        let name = match words.next() {
+            Some(word) if word.starts_with('/') => &word[1..],
+            _ => return Command::Unknown(text.to_string()),
+        };
+        // In group chats commands are addressed to a specific bot
+        let name = name.split('@').next().unwrap_or(name);
+
+        match name {
+            "start" => Command::Start,
+            "where" => Command::Where,
+            "scan" => Command::Scan,
+            "jump" => match words.next().map(str::parse) {
+                Some(Ok(id)) => Command::Jump(id),
+                _ => Command::Unknown(text.to_string()),
+            },
+            "help" => Command::Help,
+            _ => Command::Unknown(text.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_reads_command_arguments() {
+        assert_eq!(Command::parse("/jump 42"), Command::Jump(42));
+        assert_eq!(Command::parse("/scan@SpaceBot"), Command::Scan);
+        assert_eq!(Command::parse("  /where  "), Command::Where);
+    }
+
+    #[test]
+    fn parse_rejects_malformed_commands() {
+        assert_eq!(Command::parse("/jump"), Command::Unknown("/jump".to_string()));
+        assert_eq!(Command::parse("/jump far"), Command::Unknown("/jump far".to_string()));
+        assert_eq!(Command::parse("hello"), Command::Unknown("hello".to_string()));
+    }
+}
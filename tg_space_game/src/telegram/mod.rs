@@ -0,0 +1,14 @@
+//! Telegram frontend of the game. `TelegramApi` talks to the Bot API over
+//! HTTP, and `Bot` turns player commands into galaxy operations.
+
+use super::*;
+
+pub use self::api::{TelegramApi, TelegramError, DEFAULT_API_URL};
+pub use self::bot::Bot;
+pub use self::command::Command;
+pub use self::types::*;
+
+mod api;
+mod bot;
+mod command;
+mod types;
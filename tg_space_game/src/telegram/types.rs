@@ -0,0 +1,41 @@
+//! Subset of Telegram Bot API types that the bot needs
+
+/// Every Bot API response is wrapped in this
+#[derive(Deserialize, Debug)]
+pub struct Response<T> {
+    pub ok: bool,
+    pub result: Option<T>,
+    pub description: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Update {
+    pub update_id: i64,
+    pub message: Option<Message>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Message {
+    pub message_id: i64,
+    pub from: Option<User>,
+    pub chat: Chat,
+    pub text: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct User {
+    pub id: i64,
+    pub first_name: String,
+    pub username: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Chat {
+    pub id: i64,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SendMessage<'a> {
+    pub chat_id: i64,
+    pub text: &'a str,
+}
@@ -1,15 +1,52 @@
 use super::*;
 
+use rand::prng::ChaChaRng;
+use rand::{Rng, SeedableRng};
+
 /// Returns array of weights that are distributed exponentially
-pub fn exp_weights(amount: usize) -> Vec<u32> {
+pub fn exp_weights<R: Rng>(amount: usize, rng: &mut R) -> Vec<u32> {
     use rand::distributions::{Distribution, Exp};
     let exp = Exp::new(1.0);
-    let weights_f: Vec<f64> = exp.sample_iter(&mut rand::thread_rng())
+    let weights_f: Vec<f64> = exp.sample_iter(rng)
         .take(amount)
         .collect();
     let weight_sum = weights_f.iter().fold(0.0, |acc, x| acc + x);
     weights_f
         .iter()
-        .map(|x| ((x / weight_sum) * ((<u32>::max_value()) / 2) as f64).floor() as u32)
+        .map(|x| ((x / weight_sum) * (u32::MAX / 2) as f64).floor() as u32)
+        .collect()
+}
+
+/// Returns array of weights that are distributed uniformly
+pub fn uniform_weights<R: Rng>(amount: usize, rng: &mut R) -> Vec<u32> {
+    let weights_f: Vec<f64> = (0..amount).map(|_| rng.gen()).collect();
+    let weight_sum = weights_f.iter().fold(0.0, |acc, x| acc + x);
+    weights_f
+        .iter()
+        .map(|x| ((x / weight_sum) * (u32::MAX / 2) as f64).floor() as u32)
         .collect()
 }
+
+/// Returns array of weights according to distribution
+pub fn weights<R: Rng>(distribution: WeightDistribution, amount: usize, rng: &mut R) -> Vec<u32> {
+    match distribution {
+        WeightDistribution::Exponential => exp_weights(amount, rng),
+        WeightDistribution::Uniform => uniform_weights(amount, rng),
+        WeightDistribution::Constant => vec![(u32::MAX / 2) / (amount.max(1) as u32); amount],
+    }
+}
+
+/// Returns portable random generator, that produces the same sequence for
+/// the same seed on every platform and every run
+pub fn seeded_rng(seed: i64) -> ChaChaRng {
+    let mut bytes = [0u8; 32];
+    for (i, byte) in bytes.iter_mut().take(8).enumerate() {
+        *byte = (seed >> (i * 8)) as u8;
+    }
+    ChaChaRng::from_seed(bytes)
+}
+
+/// Returns fresh seed for a new galaxy
+pub fn random_seed() -> i64 {
+    rand::thread_rng().gen()
+}
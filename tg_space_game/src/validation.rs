@@ -0,0 +1,306 @@
+//! Integrity checks of stored galaxies.
+//!
+//! Database constraints keep most of the invariants, but memory stores,
+//! manual fixes and older schemas don't have them. `validate_galaxy` reports
+//! every broken invariant, and `repair_galaxy` fixes the ones that can be
+//! fixed without losing generated content.
+
+use super::*;
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use store::GalaxyStore;
+
+/// Broken invariant of a galaxy
+#[derive(Clone, PartialEq, Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Violation {
+    /// Sector, future or system has no galaxy object
+    MissingGalaxyObject { object: GalaxyObject },
+    /// Galaxy object of a sector, future or system is of another type
+    TypeMismatch {
+        object: GalaxyObject,
+        actual: GalaxyObjectType,
+    },
+    /// Galaxy object has no sector, future or system. Occupied objects have
+    /// players at them or flying to them.
+    OrphanGalaxyObject { object: GalaxyObject, occupied: bool },
+    /// End of a link doesn't exist
+    DanglingLink { link_id: i32, missing: GalaxyObject },
+    /// Sector, future or system is inside of a sector that doesn't exist
+    MissingParent { object: GalaxyObject, parent_id: i32 },
+    /// Sectors are inside of each other, smallest id first
+    ParentCycle { sector_ids: Vec<i32> },
+}
+
+impl Violation {
+    /// Whether `repair_galaxy` fixes the violation
+    pub fn is_repairable(&self) -> bool {
+        match *self {
+            Violation::MissingGalaxyObject { .. } | Violation::DanglingLink { .. } => true,
+            // Deleting the object would take players out of the galaxy
+            Violation::OrphanGalaxyObject { occupied, .. } => !occupied,
+            Violation::TypeMismatch { .. } | Violation::MissingParent { .. } | Violation::ParentCycle { .. } => {
+                false
+            }
+        }
+    }
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Violation::MissingGalaxyObject { ref object } => write!(
+                f,
+                "{} {} has no galaxy object",
+                object.obj_type.as_str(),
+                object.id
+            ),
+            Violation::TypeMismatch { ref object, actual } => write!(
+                f,
+                "{} {} has galaxy object of type {}",
+                object.obj_type.as_str(),
+                object.id,
+                actual.as_str()
+            ),
+            Violation::OrphanGalaxyObject { ref object, occupied } => write!(
+                f,
+                "Galaxy object {} has no {}{}",
+                object.id,
+                object.obj_type.as_str(),
+                if occupied { ", but players are there" } else { "" }
+            ),
+            Violation::DanglingLink { link_id, ref missing } => write!(
+                f,
+                "Link {} leads to missing {} {}",
+                link_id,
+                missing.obj_type.as_str(),
+                missing.id
+            ),
+            Violation::MissingParent { ref object, parent_id } => write!(
+                f,
+                "{} {} is inside of missing sector {}",
+                object.obj_type.as_str(),
+                object.id,
+                parent_id
+            ),
+            Violation::ParentCycle { ref sector_ids } => write!(f, "Sectors {:?} are inside of each other", sector_ids),
+        }
+    }
+}
+
+/// Checks all galaxies in the store. Tables are read in one snapshot
+/// transaction, so the galaxy can be generated while it's checked.
+pub fn validate_galaxy<S: GalaxyStore>(store: &S) -> GalaxyResult<Vec<Violation>> {
+    store.snapshot_transaction(|| find_violations(store))
+}
+
+fn find_violations<S: GalaxyStore>(store: &S) -> GalaxyResult<Vec<Violation>> {
+    let links = store.list_links()?;
+    let objects = store
+        .list_galaxy_objects()?
+        .into_iter()
+        .map(|o| (o.id, o.obj_type))
+        .collect::<BTreeMap<_, _>>();
+    let sectors = store.list_sectors()?;
+    let futures = store.list_futures()?;
+    let systems = store.list_systems(i64::MAX)?;
+    let mut occupied = store
+        .get_located_players()?
+        .into_iter()
+        .filter_map(|p| p.location_id)
+        .collect::<HashSet<_>>();
+    for jump in store.list_jumps()? {
+        occupied.insert(jump.from_id);
+        occupied.insert(jump.to_id);
+    }
+
+    // Rows of every type, with ids of sectors they are inside of
+    let mut rows: Vec<(GalaxyObject, Option<i32>)> = Vec::new();
+    rows.extend(sectors.iter().map(|s| (GalaxyObject::from(s), s.parent_id)));
+    rows.extend(futures.iter().map(|f| (GalaxyObject::from(f), Some(f.parent_id))));
+    rows.extend(systems.iter().map(|s| (GalaxyObject::from(s), Some(s.sector_id))));
+    let row_ids = rows.iter().map(|(o, _)| o.id).collect::<HashSet<_>>();
+    let existing = rows.iter().map(|(o, _)| o.clone()).collect::<HashSet<_>>();
+    let sector_parents = sectors.iter().map(|s| (s.id, s.parent_id)).collect::<HashMap<_, _>>();
+
+    let mut result = Vec::new();
+    for (object, parent_id) in &rows {
+        match objects.get(&object.id) {
+            None => result.push(Violation::MissingGalaxyObject {
+                object: object.clone(),
+            }),
+            Some(&actual) if actual != object.obj_type => result.push(Violation::TypeMismatch {
+                object: object.clone(),
+                actual,
+            }),
+            Some(_) => {}
+        }
+        if let Some(parent_id) = *parent_id {
+            if !sector_parents.contains_key(&parent_id) {
+                result.push(Violation::MissingParent {
+                    object: object.clone(),
+                    parent_id,
+                });
+            }
+        }
+    }
+
+    // Objects that are claimed by a row of another type are type mismatches
+    for (&id, &obj_type) in &objects {
+        if !row_ids.contains(&id) {
+            result.push(Violation::OrphanGalaxyObject {
+                object: GalaxyObject { id, obj_type },
+                occupied: occupied.contains(&id),
+            });
+        }
+    }
+
+    // Ends without galaxy objects are reported above and repaired together
+    for link in &links {
+        for end in &[link.side_a(), link.side_b()] {
+            if !existing.contains(end) {
+                result.push(Violation::DanglingLink {
+                    link_id: link.id,
+                    missing: end.clone(),
+                });
+            }
+        }
+    }
+
+    for sector_ids in parent_cycles(&sector_parents) {
+        result.push(Violation::ParentCycle { sector_ids });
+    }
+
+    Ok(result)
+}
+
+/// Finds cycles of sectors by following parents
+fn parent_cycles(parents: &HashMap<i32, Option<i32>>) -> Vec<Vec<i32>> {
+    let mut ids = parents.keys().cloned().collect::<Vec<_>>();
+    ids.sort();
+
+    let mut done = HashSet::new();
+    let mut result = Vec::new();
+    for id in ids {
+        let mut path: Vec<i32> = Vec::new();
+        let mut current = Some(id);
+        while let Some(sector_id) = current {
+            if done.contains(&sector_id) {
+                break;
+            }
+            if let Some(start) = path.iter().position(|&p| p == sector_id) {
+                let mut cycle = path[start..].to_vec();
+                let smallest = (0..cycle.len()).min_by_key(|&i| cycle[i]).unwrap_or(0);
+                cycle.rotate_left(smallest);
+                result.push(cycle);
+                break;
+            }
+            path.push(sector_id);
+            current = parents.get(&sector_id).cloned().unwrap_or(None);
+        }
+        done.extend(path);
+    }
+    result
+}
+
+/// Fixes violations that don't need any content to be deleted or made up:
+/// saves missing galaxy objects, deletes orphan galaxy objects that no
+/// player is at or flying to, and links that lead nowhere. Everything is
+/// done in one transaction. Returns repaired violations.
+pub fn repair_galaxy<S: GalaxyStore>(store: &S) -> GalaxyResult<Vec<Violation>> {
+    store.snapshot_transaction(|| {
+        let repaired = validate_galaxy(store)?
+            .into_iter()
+            .filter(Violation::is_repairable)
+            .collect::<Vec<_>>();
+
+        let mut missing = Vec::new();
+        let mut orphans = Vec::new();
+        let mut links = Vec::new();
+        for violation in &repaired {
+            match *violation {
+                Violation::MissingGalaxyObject { ref object } => missing.push(object.clone()),
+                Violation::OrphanGalaxyObject { ref object, .. } => orphans.push(object.id),
+                Violation::DanglingLink { link_id, .. } => links.push(link_id),
+                _ => {}
+            }
+        }
+
+        // Links to orphans lead nowhere too, so they go first
+        store.delete_links(&links)?;
+        store.delete_galaxy_objects(&orphans)?;
+        store.insert_galaxy_objects(&missing)?;
+        Ok(repaired)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use galaxy_objects::get_star_sector_children_futures;
+    use store::fixtures::*;
+    use store::MemoryStore;
+
+    #[test]
+    fn generated_galaxy_is_valid() {
+        let store = MemoryStore::new();
+        fulfilled_galaxy(&store, 2);
+        assert_eq!(validate_galaxy(&store).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn repair_restores_and_deletes_galaxy_objects() {
+        let store = MemoryStore::new();
+        let sector = fulfilled_galaxy(&store, 2);
+        let future = get_star_sector_children_futures(&store, &sector).unwrap()[0].clone();
+        let orphan = store.create_galaxy_objects(&[GalaxyObjectType::System]).unwrap()[0].clone();
+        store.delete_galaxy_objects(&[future.id]).unwrap();
+
+        let violations = validate_galaxy(&store).unwrap();
+        assert!(violations.contains(&Violation::OrphanGalaxyObject {
+            object: orphan,
+            occupied: false,
+        }));
+        assert!(violations.contains(&Violation::MissingGalaxyObject {
+            object: GalaxyObject::from(&future),
+        }));
+        assert!(!violations
+            .iter()
+            .any(|v| matches!(*v, Violation::DanglingLink { .. })));
+
+        let links = store.link_count();
+        assert_eq!(repair_galaxy(&store).unwrap(), violations);
+        assert_eq!(validate_galaxy(&store).unwrap(), vec![]);
+        // Links to the future are fine again once its object is back
+        assert_eq!(store.link_count(), links);
+    }
+
+    #[test]
+    fn repair_keeps_occupied_orphans() {
+        let store = MemoryStore::new();
+        let sector = fulfilled_galaxy(&store, 2);
+        let system = spawn_players(&store, &sector, 42..43).remove(0);
+        store.delete_links_for_objects(&[system.id]).unwrap();
+        store.delete_systems(&[system.id]).unwrap();
+
+        let orphaned = Violation::OrphanGalaxyObject {
+            object: GalaxyObject::from(&system),
+            occupied: true,
+        };
+        assert_eq!(validate_galaxy(&store).unwrap(), vec![orphaned.clone()]);
+        assert_eq!(repair_galaxy(&store).unwrap(), vec![]);
+        assert_eq!(validate_galaxy(&store).unwrap(), vec![orphaned]);
+    }
+
+    #[test]
+    fn parent_cycles_are_reported_once() {
+        let mut parents = HashMap::new();
+        parents.insert(1, None);
+        parents.insert(4, Some(2));
+        parents.insert(2, Some(3));
+        parents.insert(3, Some(2));
+        parents.insert(5, Some(4));
+        assert_eq!(parent_cycles(&parents), vec![vec![2, 3]]);
+    }
+}
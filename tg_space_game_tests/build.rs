@@ -22,7 +22,7 @@ fn database_url_from_env(env_var: &str) -> String {
 }
 
 fn main() {
-    run_migrations(&connection(), &mut std::io::stdout()).expect("Error running migrations!");
+    run_migrations(&GalaxyConnection::Pg(connection()), &mut std::io::stdout()).expect("Error running migrations!");
     /*
     let migrations_dir = diesel_migrations::find_migrations_directory().unwrap();
     diesel_migrations::run_pending_migrations_in_directory(
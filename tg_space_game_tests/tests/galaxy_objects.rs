@@ -1,16 +1,24 @@
 use super::*;
 
+use tg_space_game::config::GenerationConfig;
+use tg_space_game::schema::types::{GalaxyObjectType, LinkReattachPolicy, LinkStrategy};
+use tg_space_game::GalaxyError;
 use tg_space_game::galaxy_objects::*;
+use tg_space_game::space::Located;
+use tg_space_game::store::GalaxyStore;
 
 #[test]
 fn generate_star_sector_finishes_without_errors() {
     let connection = test_connection();
-    generate_star_sector(&connection, 1f32, 1f32, None).unwrap();
+    generate_star_sector(&connection, 1f32, 1f32, None, 1, &GenerationConfig::default()).unwrap();
 }
 
 fn generate_root(connection: &PgConnection, stars: f32) -> StarSector {
+    generate_root_with_seed(connection, stars, random_seed())
+}
 
-    generate_star_sector(connection, stars, 1f32, None)
+fn generate_root_with_seed(connection: &PgConnection, stars: f32, seed: i64) -> StarSector {
+    generate_star_sector(connection, stars, 1f32, None, seed, &GenerationConfig::default())
         .expect("Error generating star sector")
 }
 
@@ -38,9 +46,16 @@ fn generate_star_sectors_creates_stars() {
 }
 
 fn generate_root_with_futures(connection: &PgConnection) -> (StarSector, Vec<StarSectorFuture>) {
-    let sector = generate_root(&connection, 200f32);
+    generate_root_with_futures_seeded(connection, random_seed())
+}
 
-    let futures = get_star_sector_children_futures(&connection, &sector)
+fn generate_root_with_futures_seeded(
+    connection: &PgConnection,
+    seed: i64,
+) -> (StarSector, Vec<StarSectorFuture>) {
+    let sector = generate_root_with_seed(connection, 200f32, seed);
+
+    let futures = get_star_sector_children_futures(connection, &sector)
         .expect("Error loading star sector futures");
 
     (sector, futures)
@@ -59,7 +74,7 @@ fn generate_star_sectors_creates_futures() {
 fn generate_star_sector_creates_futures_can_be_found() {
     let connection = test_connection();
 
-    let future_id = (&generate_root_with_futures(&connection).1[0]).id;
+    let future_id = generate_root_with_futures(&connection).1[0].id;
 
     use tg_space_game::models::*;
     use tg_space_game::schema::star_sector_futures::dsl::*;
@@ -121,6 +136,64 @@ fn delete_sector_doesnt_delete_other_stars() {
     assert_eq!(systems_to_stay.len(), systems_stayed.len());
 }
 
+#[test]
+fn delete_sector_counts_deleted_rows() {
+    let connection = test_connection();
+    let sector = generate_root(&connection, 200f32);
+    let future = get_star_sector_children_futures(&connection, &sector).unwrap()[0].clone();
+    fulfill_star_sector_future(&connection, future.id).expect("Error fulfilling future");
+
+    let stats = get_sector_stats(&connection, sector.id).expect("Error getting stats");
+    let deleted = delete_sector(&connection, sector.id).expect("Error deleting sector");
+
+    assert_eq!(deleted.sectors, stats.sectors);
+    assert_eq!(deleted.futures, stats.futures);
+    assert_eq!(deleted.systems, stats.systems);
+    assert_eq!(deleted.links, stats.links);
+    assert_eq!(deleted.galaxy_objects, stats.sectors + stats.futures + stats.systems);
+}
+
+#[test]
+fn sector_stats_count_links_inside_only() {
+    let connection = test_connection();
+    let sector = generate_root(&connection, 200f32);
+    let future = get_star_sector_children_futures(&connection, &sector).unwrap()[0].clone();
+    let child = fulfill_star_sector_future(&connection, future.id).expect("Error fulfilling future");
+    let systems = connection.get_child_systems(child.id).unwrap();
+    let ids = systems.iter().map(|s| s.id).collect::<Vec<_>>();
+    let inside = connection
+        .get_links_for_objects(&ids)
+        .unwrap()
+        .iter()
+        .filter(|l| ids.contains(&l.a_id) && ids.contains(&l.b_id))
+        .count();
+
+    let stats = get_sector_stats(&connection, child.id).expect("Error getting stats");
+    assert_eq!(stats.sectors, 1);
+    assert_eq!(stats.futures, 0);
+    assert_eq!(stats.systems, systems.len());
+    assert_eq!(stats.links, inside);
+    assert!(get_sector_stats(&connection, -1).is_err());
+}
+
+#[test]
+fn delete_sector_ends_on_parent_cycles() {
+    let connection = test_connection();
+    let sector = generate_root(&connection, 200f32);
+    let future = get_star_sector_children_futures(&connection, &sector).unwrap()[0].clone();
+    let child = fulfill_star_sector_future(&connection, future.id).expect("Error fulfilling future");
+    {
+        use tg_space_game::schema::star_sectors::dsl::*;
+        diesel::update(star_sectors.find(sector.id))
+            .set(parent_id.eq(child.id))
+            .execute(&connection)
+            .expect("Error making a cycle");
+    }
+
+    let deleted = delete_sector(&connection, sector.id).expect("Error deleting sector");
+    assert_eq!(deleted.sectors, 2);
+}
+
 #[test]
 fn delete_sector_conserves_galaxy_object_count() {
     let connection = test_connection();
@@ -136,7 +209,7 @@ fn delete_sector_conserves_galaxy_object_count() {
 
     let posterior_count = galaxy_objects
         .count()
-        .get_result(&connection)
+        .get_result::<i64>(&connection)
         .expect("Error getting posterior count");
 
     assert_eq!(prior_count, posterior_count);
@@ -209,7 +282,7 @@ fn generate_delete_sector_conserves_link_count() {
 
     let posterior_count = star_links
         .count()
-        .get_result(&connection)
+        .get_result::<i64>(&connection)
         .expect("Error getting posterior count");
 
     assert_eq!(prior_count, posterior_count);
@@ -220,8 +293,10 @@ fn get_links_for_object_ids_returns_generated_links() {
     let connection = test_connection();
     let star = &generate_root_with_stars(&connection).1[0];
 
-    &get_links_for_object_ids(&connection, vec![star.id])
-        .expect("Error getting links for the star")[0];
+    let links = get_links_for_object_ids(&connection, vec![star.id])
+        .expect("Error getting links for the star");
+
+    assert!(!links.is_empty());
 }
 
 #[test]
@@ -251,4 +326,596 @@ fn fulfill_star_sector_future_delegates_links() {
         .expect("Error getting future's links");
 
     assert_eq!(0, future_links.len());
-}
\ No newline at end of file
+}
+
+/// Describes links between children of a sector by children's positions
+/// instead of their ids, so that layouts of different sectors can be compared.
+/// Links that lead outside of the sector are skipped.
+fn link_layout(connection: &PgConnection, children: &[GalaxyObject]) -> Vec<(usize, usize)> {
+    let index = |obj_id: i32| children.iter().position(|c| c.id == obj_id);
+    let mut links = get_links_for_objects(connection, children.to_vec())
+        .expect("Error getting links")
+        .iter()
+        .filter_map(|l| match (index(l.a_id), index(l.b_id)) {
+            (Some(a), Some(b)) => Some((a, b)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    links.sort();
+    links
+}
+
+/// Children futures' parameters and links between all children
+type SectorLayout = (Vec<(i64, u32, u32)>, Vec<(usize, usize)>);
+
+fn sector_layout(connection: &PgConnection, sector: &StarSector) -> SectorLayout {
+    let mut futures = get_star_sector_children_futures(connection, sector)
+        .expect("Error loading star sector futures");
+    futures.sort_by_key(|f| f.id);
+
+    use tg_space_game::schema::star_systems::dsl::*;
+    let systems = star_systems
+        .filter(sector_id.eq(sector.id))
+        .order(id)
+        .load::<StarSystem>(connection)
+        .expect("Error loading star systems");
+
+    let params = futures
+        .iter()
+        .map(|f| (f.seed, f.radius.to_bits(), f.stars.to_bits()))
+        .collect();
+    let objects = futures
+        .iter()
+        .map(GalaxyObject::from)
+        .chain(systems.iter().map(GalaxyObject::from))
+        .collect::<Vec<_>>();
+
+    (params, link_layout(connection, &objects))
+}
+
+#[test]
+fn generate_star_sector_with_same_seed_is_reproducible() {
+    let connection = test_connection();
+
+    let sector_a = generate_root_with_seed(&connection, 200f32, 42);
+    let sector_b = generate_root_with_seed(&connection, 200f32, 42);
+
+    assert_eq!(
+        sector_layout(&connection, &sector_a),
+        sector_layout(&connection, &sector_b)
+    );
+}
+
+#[test]
+fn generate_star_sector_with_different_seeds_differs() {
+    let connection = test_connection();
+
+    let sector_a = generate_root_with_seed(&connection, 200f32, 42);
+    let sector_b = generate_root_with_seed(&connection, 200f32, 43);
+
+    assert_ne!(
+        sector_layout(&connection, &sector_a),
+        sector_layout(&connection, &sector_b)
+    );
+}
+
+#[test]
+fn fulfill_star_sector_future_is_reproducible_in_any_order() {
+    let connection = test_connection();
+
+    let mut futures_a = generate_root_with_futures_seeded(&connection, 7).1;
+    let mut futures_b = generate_root_with_futures_seeded(&connection, 7).1;
+    futures_a.sort_by_key(|f| f.id);
+    futures_b.sort_by_key(|f| f.id);
+
+    // Fulfill futures in opposite orders
+    let sectors_a = futures_a
+        .iter()
+        .map(|f| fulfill_star_sector_future(&connection, f.id).unwrap())
+        .collect::<Vec<_>>();
+    let mut sectors_b = futures_b
+        .iter()
+        .rev()
+        .map(|f| fulfill_star_sector_future(&connection, f.id).unwrap())
+        .collect::<Vec<_>>();
+    sectors_b.reverse();
+
+    for (sector_a, sector_b) in sectors_a.iter().zip(sectors_b.iter()) {
+        assert_eq!(sector_a.seed, sector_b.seed);
+        assert_eq!(
+            sector_layout(&connection, sector_a),
+            sector_layout(&connection, sector_b)
+        );
+    }
+}
+
+#[test]
+fn generate_star_sector_uses_config_branching_factor() {
+    let connection = test_connection();
+    let config = GenerationConfig {
+        branching_factor: 4,
+        ..GenerationConfig::default()
+    };
+
+    let sector = generate_star_sector(&connection, 200f32, 1f32, None, 1, &config)
+        .expect("Error generating star sector");
+    let futures = get_star_sector_children_futures(&connection, &sector)
+        .expect("Error loading star sector futures");
+
+    assert_eq!(futures.len(), 4);
+}
+
+#[test]
+fn generate_star_sector_saves_config() {
+    let connection = test_connection();
+    let config = GenerationConfig {
+        branching_factor: 3,
+        link_density: 2f32,
+        ..GenerationConfig::default()
+    };
+
+    let sector = generate_star_sector(&connection, 5f32, 1f32, None, 1, &config)
+        .expect("Error generating star sector");
+
+    assert_eq!(get_generation_config(&connection, &sector).unwrap(), config);
+}
+
+#[test]
+fn fulfill_star_sector_future_uses_galaxy_config() {
+    let connection = test_connection();
+    let config = GenerationConfig {
+        branching_factor: 4,
+        ..GenerationConfig::default()
+    };
+
+    let root = generate_star_sector(&connection, 2000f32, 1f32, None, 1, &config)
+        .expect("Error generating star sector");
+    let future_id = get_star_sector_children_futures(&connection, &root)
+        .expect("Error loading star sector futures")[0]
+        .id;
+    let sector = fulfill_star_sector_future(&connection, future_id)
+        .expect("Error fulfilling star sector future");
+    let futures = get_star_sector_children_futures(&connection, &sector)
+        .expect("Error loading star sector futures");
+
+    assert_eq!(sector.config_id, root.config_id);
+    assert_eq!(futures.len(), 4);
+}
+
+#[test]
+fn fill_star_sector_names_stars_uniquely_across_galaxy() {
+    let connection = test_connection();
+    let (root, futures) = generate_root_with_futures(&connection);
+    for f in futures {
+        fulfill_star_sector_future(&connection, f.id).expect("Error fulfilling future");
+    }
+
+    let ids = connection
+        .get_sector_subtree(root.id)
+        .expect("Error loading galaxy")
+        .iter()
+        .filter(|o| o.obj_type == GalaxyObjectType::System)
+        .map(|o| o.id)
+        .collect::<Vec<_>>();
+    let names = connection
+        .get_systems(&ids)
+        .expect("Error loading systems")
+        .into_iter()
+        .map(|s| s.name)
+        .collect::<std::collections::HashSet<_>>();
+
+    // Every one of 200 stars has its own name
+    assert_eq!(names.len(), 200);
+    assert!(!names.contains("StarName"));
+}
+
+#[test]
+fn generate_star_sector_places_futures_inside_sector() {
+    let connection = test_connection();
+    let (sector, futures) = generate_root_with_futures(&connection);
+
+    for f in futures {
+        assert!(f.distance_to(&sector) + f.radius <= sector.radius + 1e-4);
+    }
+}
+
+#[test]
+fn generate_star_sector_places_stars_inside_sector() {
+    let connection = test_connection();
+    let (sector, systems) = generate_root_with_stars(&connection);
+
+    for s in systems {
+        assert!(s.radius > 0f32);
+        assert!(s.distance_to(&sector) + s.radius <= sector.radius + 1e-4);
+    }
+}
+
+#[test]
+fn fulfill_star_sector_future_keeps_position() {
+    let connection = test_connection();
+    let future = generate_root_with_futures(&connection).1.remove(0);
+
+    let sector = fulfill_star_sector_future(&connection, future.id)
+        .expect("Error fulfilling star sector future");
+
+    assert_eq!(sector.position(), future.position());
+    assert_eq!(sector.radius, future.radius);
+}
+
+/// Returns all systems and futures of the galaxy, that links connect
+fn galaxy_leaves(connection: &PgConnection, root: &StarSector) -> Vec<GalaxyObject> {
+    use tg_space_game::schema::{star_sector_futures, star_sectors, star_systems};
+
+    let sector_ids = star_sectors::table
+        .filter(star_sectors::config_id.eq(root.config_id))
+        .select(star_sectors::id)
+        .load::<i32>(connection)
+        .expect("Error loading sectors");
+    let systems = star_systems::table
+        .filter(star_systems::sector_id.eq_any(sector_ids.clone()))
+        .load::<StarSystem>(connection)
+        .expect("Error loading systems");
+    let futures = star_sector_futures::table
+        .filter(star_sector_futures::parent_id.eq_any(sector_ids))
+        .load::<StarSectorFuture>(connection)
+        .expect("Error loading futures");
+
+    systems
+        .iter()
+        .map(GalaxyObject::from)
+        .chain(futures.iter().map(GalaxyObject::from))
+        .collect()
+}
+
+fn assert_galaxy_connected(connection: &PgConnection, root: &StarSector) {
+    use std::collections::HashSet;
+
+    let leaves = galaxy_leaves(connection, root);
+    let mut reached: HashSet<i32> = HashSet::new();
+    let mut frontier = vec![leaves[0].id];
+    reached.insert(leaves[0].id);
+    while !frontier.is_empty() {
+        let links = get_links_for_object_ids(connection, frontier.clone())
+            .expect("Error getting links");
+        frontier = links
+            .iter()
+            .flat_map(|l| vec![l.a_id, l.b_id])
+            .filter(|obj_id| reached.insert(*obj_id))
+            .collect();
+    }
+
+    assert_eq!(reached.len(), leaves.len());
+}
+
+#[test]
+fn fulfill_star_sector_future_keeps_galaxy_connected() {
+    let connection = test_connection();
+    let (root, futures) = generate_root_with_futures(&connection);
+
+    for f in futures.iter().take(3) {
+        fulfill_star_sector_future(&connection, f.id).expect("Error fulfilling future");
+        assert_galaxy_connected(&connection, &root);
+    }
+}
+
+#[test]
+fn fulfill_star_sector_future_keeps_galaxy_connected_at_every_level() {
+    let connection = test_connection();
+    let config = GenerationConfig {
+        link_reattach: LinkReattachPolicy::Weighted,
+        ..GenerationConfig::default()
+    };
+
+    let root = generate_star_sector(&connection, 2000f32, 1f32, None, 3, &config)
+        .expect("Error generating star sector");
+    let fulfill_first_child = |sector: &StarSector| {
+        let future_id = get_star_sector_children_futures(&connection, sector)
+            .expect("Error loading star sector futures")[0]
+            .id;
+        fulfill_star_sector_future(&connection, future_id).expect("Error fulfilling future")
+    };
+
+    let sector = fulfill_first_child(&root);
+    assert_galaxy_connected(&connection, &root);
+    fulfill_first_child(&sector);
+    assert_galaxy_connected(&connection, &root);
+}
+
+#[test]
+fn spatial_link_strategies_keep_galaxy_connected() {
+    let connection = test_connection();
+    for strategy in &[LinkStrategy::SpanningTree, LinkStrategy::NearestNeighbours, LinkStrategy::Gabriel] {
+        let config = GenerationConfig {
+            link_strategy: *strategy,
+            ..GenerationConfig::default()
+        };
+        let root = generate_star_sector(&connection, 2000f32, 1f32, None, 4, &config)
+            .expect("Error generating star sector");
+        assert_eq!(
+            connection.get_generation_config(root.config_id).expect("Error loading config"),
+            config
+        );
+
+        let future_id = get_star_sector_children_futures(&connection, &root)
+            .expect("Error loading star sector futures")[0]
+            .id;
+        fulfill_star_sector_future(&connection, future_id).expect("Error fulfilling future");
+        assert_galaxy_connected(&connection, &root);
+    }
+}
+
+#[test]
+fn fulfill_star_sector_future_reattaches_external_links() {
+    let connection = test_connection();
+    let future_id = generate_root_with_futures(&connection).1[0].id;
+
+    let external_links = get_links_for_object_ids(&connection, vec![future_id])
+        .expect("Error getting future's links")
+        .iter()
+        .filter(|l| l.a_id != l.b_id)
+        .count();
+
+    let sector = fulfill_star_sector_future(&connection, future_id)
+        .expect("Error fulfilling star sector future");
+
+    use tg_space_game::schema::star_systems::dsl::*;
+    let children = star_systems
+        .filter(sector_id.eq(sector.id))
+        .select(id)
+        .load::<i32>(&connection)
+        .expect("Error loading star systems");
+    let crossing_links = get_links_for_object_ids(&connection, children.clone())
+        .expect("Error getting children links")
+        .iter()
+        .filter(|l| children.contains(&l.a_id) != children.contains(&l.b_id))
+        .count();
+
+    assert_eq!(external_links, crossing_links);
+}
+
+#[test]
+fn fulfill_star_sector_future_twice_fails() {
+    let connection = test_connection();
+    let future_id = generate_root_with_futures(&connection).1[0].id;
+    fulfill_star_sector_future(&connection, future_id).unwrap();
+
+    match fulfill_star_sector_future(&connection, future_id) {
+        Err(GalaxyError::FutureAlreadyFulfilled(id)) => assert_eq!(id, future_id),
+        other => panic!("Unexpected result {:?}", other.map(|s| s.id)),
+    }
+}
+
+#[test]
+fn fulfill_star_sector_future_on_system_fails() {
+    let connection = test_connection();
+    let (_, systems) = generate_root_with_stars(&connection);
+
+    match fulfill_star_sector_future(&connection, systems[0].id) {
+        Err(GalaxyError::WrongObjectType { object, expected }) => {
+            assert_eq!(object, GalaxyObject::from(&systems[0]));
+            assert_eq!(expected, GalaxyObjectType::SectorFuture);
+        }
+        other => panic!("Unexpected result {:?}", other.map(|s| s.id)),
+    }
+}
+
+#[test]
+fn fulfill_missing_star_sector_future_fails() {
+    let connection = test_connection();
+
+    match fulfill_star_sector_future(&connection, -1) {
+        Err(ref err @ GalaxyError::ObjectNotFound(_)) => assert!(err.is_user_error()),
+        other => panic!("Unexpected result {:?}", other.map(|s| s.id)),
+    }
+}
+
+#[test]
+fn delete_missing_sector_fails() {
+    let connection = test_connection();
+
+    match delete_sector(&connection, -1) {
+        Err(ref err @ GalaxyError::ObjectNotFound(_)) => assert!(err.is_user_error()),
+        other => panic!("Unexpected result {:?}", other),
+    }
+}
+
+#[test]
+fn generate_star_sector_rejects_invalid_params() {
+    let connection = test_connection();
+    let config = GenerationConfig::default();
+
+    match generate_star_sector(&connection, 0f32, 1f32, None, 1, &config) {
+        Err(GalaxyError::InvalidGenerationParams(_)) => {}
+        other => panic!("Unexpected result {:?}", other.map(|s| s.id)),
+    }
+    let config = GenerationConfig {
+        branching_factor: 1,
+        ..GenerationConfig::default()
+    };
+    match generate_star_sector(&connection, 10f32, 1f32, None, 1, &config) {
+        Err(GalaxyError::Config(_)) => {}
+        other => panic!("Unexpected result {:?}", other.map(|s| s.id)),
+    }
+}
+
+#[test]
+fn resolve_object_fulfills_future_once_under_concurrent_requests() {
+    use std::sync::{Arc, Barrier};
+    use std::thread;
+
+    // Concurrent requests need committed data, so the galaxy is deleted in the end
+    let connection = connection();
+    let sector = generate_root(&connection, 200f32);
+    let future = GalaxyObject::from(&get_star_sector_children_futures(&connection, &sector).unwrap()[0]);
+
+    let barrier = Arc::new(Barrier::new(4));
+    let requests = (0..4)
+        .map(|_| {
+            let barrier = barrier.clone();
+            let future = future.clone();
+            thread::spawn(move || {
+                let own_connection = super::connection();
+                barrier.wait();
+                resolve_object(&own_connection, &future)
+            })
+        })
+        .collect::<Vec<_>>();
+    let results = requests
+        .into_iter()
+        .map(|r| r.join().expect("Request panicked"))
+        .collect::<Vec<_>>();
+
+    let stats = get_sector_stats(&connection, future.id);
+    delete_sector(&connection, sector.id).expect("Error deleting sector");
+
+    let systems = results
+        .into_iter()
+        .map(|r| r.expect("Error resolving future").id)
+        .collect::<Vec<_>>();
+    assert!(systems.iter().all(|id| *id == systems[0]));
+    // Generated once: the future is a sector with a single set of children
+    let stats = stats.expect("Error getting stats");
+    assert_eq!(stats.sectors, 1);
+    assert!(stats.systems > 0);
+}
+
+#[test]
+fn linked_futures_can_be_fulfilled_concurrently() {
+    use std::thread;
+
+    // Siblings re-attach the links between them, so their fulfillments
+    // must not interleave
+    let connection = connection();
+    let sector = generate_root(&connection, 2000f32);
+    let futures = get_star_sector_children_futures(&connection, &sector).unwrap();
+
+    let requests = futures
+        .iter()
+        .map(|future| {
+            let future_id = future.id;
+            thread::spawn(move || fulfill_star_sector_future(&super::connection(), future_id))
+        })
+        .collect::<Vec<_>>();
+    let results = requests
+        .into_iter()
+        .map(|r| r.join().expect("Request panicked"))
+        .collect::<Vec<_>>();
+    let links = get_links_for_object_ids(&connection, futures.iter().map(|f| f.id).collect());
+    delete_sector(&connection, sector.id).expect("Error deleting sector");
+
+    for result in results {
+        result.expect("Error fulfilling future");
+    }
+    // Nothing links to the fulfilled futures anymore
+    assert!(links.expect("Error getting links").is_empty());
+}
+
+#[test]
+fn load_entities_returns_rows_of_all_types_in_order() {
+    let connection = test_connection();
+    let (sector, futures) = generate_root_with_futures(&connection);
+    let system = resolve_object(&connection, &GalaxyObject::from(&futures[0])).unwrap();
+    let objects = vec![
+        GalaxyObject::from(&futures[1]),
+        GalaxyObject::from(&system),
+        GalaxyObject::from(&sector),
+        GalaxyObject::from(&futures[2]),
+    ];
+
+    let entities = load_entities(&connection, &objects).expect("Error loading entities");
+
+    assert_eq!(
+        entities.iter().map(GalaxyObject::from).collect::<Vec<_>>(),
+        objects
+    );
+    match entities[1] {
+        GalaxyEntity::System(ref loaded) => assert_eq!(loaded.name, system.name),
+        ref entity => panic!("Unexpected entity {:?}", entity),
+    }
+    assert_eq!(entities[0].parent_id(), Some(sector.id));
+}
+
+#[test]
+fn load_entity_of_missing_object_fails() {
+    let connection = test_connection();
+    let (_, futures) = generate_root_with_futures(&connection);
+    fulfill_star_sector_future(&connection, futures[0].id).unwrap();
+
+    // The future is a sector now
+    match load_entity(&connection, &GalaxyObject::from(&futures[0])) {
+        Err(GalaxyError::ObjectNotFound(object)) => assert_eq!(object.id, futures[0].id),
+        result => panic!("Unexpected result {:?}", result),
+    }
+}
+
+#[test]
+fn collapse_sector_turns_sector_into_the_same_future() {
+    let connection = test_connection();
+    let root = generate_root(&connection, 200f32);
+    let future = get_star_sector_children_futures(&connection, &root).unwrap()[0].clone();
+    let sector = fulfill_star_sector_future(&connection, future.id).expect("Error fulfilling future");
+    let systems = connection.get_child_systems(sector.id).unwrap();
+
+    let collapsed = collapse_sector(&connection, sector.id).expect("Error collapsing sector");
+    assert_eq!((collapsed.id, collapsed.seed, collapsed.stars), (future.id, future.seed, future.stars));
+    assert!(connection.get_child_systems(sector.id).unwrap().is_empty());
+    let object = GalaxyObject::from(&collapsed);
+    assert_eq!(connection.get_galaxy_object(sector.id).unwrap(), Some(object.clone()));
+    let links = connection.get_links_for_objects(&[collapsed.id]).unwrap();
+    assert!(!links.is_empty());
+    assert!(links.iter().all(|link| link.touches(&object)));
+
+    let again = fulfill_star_sector_future(&connection, future.id).expect("Error fulfilling future");
+    assert_eq!(again, sector);
+    let names = |systems: Vec<StarSystem>| {
+        let mut names = systems.into_iter().map(|s| s.name).collect::<Vec<_>>();
+        names.sort();
+        names
+    };
+    assert_eq!(names(connection.get_child_systems(sector.id).unwrap()), names(systems));
+}
+
+#[test]
+fn collapse_sector_waits_for_neighbours_fulfilled_concurrently() {
+    use std::thread;
+
+    // Fulfilling a neighbour re-attaches links that collapsing moves to
+    // the future, so they must not interleave
+    let connection = connection();
+    let root = generate_root(&connection, 2000f32);
+    let futures = get_star_sector_children_futures(&connection, &root).unwrap();
+    let sector = fulfill_star_sector_future(&connection, futures[0].id).expect("Error fulfilling future");
+
+    let collapse = thread::spawn(move || collapse_sector(&super::connection(), sector.id));
+    let requests = futures[1..]
+        .iter()
+        .map(|future| {
+            let future_id = future.id;
+            thread::spawn(move || fulfill_star_sector_future(&super::connection(), future_id))
+        })
+        .collect::<Vec<_>>();
+    let collapsed = collapse.join().expect("Collapse panicked");
+    let results = requests
+        .into_iter()
+        .map(|r| r.join().expect("Request panicked"))
+        .collect::<Vec<_>>();
+    let ends = connection.get_sector_subtree(root.id).and_then(|objects| {
+        let ids = objects.iter().map(|o| o.id).collect::<Vec<_>>();
+        let links = connection.get_links_for_objects(&ids)?;
+        links
+            .iter()
+            .flat_map(|link| vec![link.side_a(), link.side_b()])
+            .map(|end| Ok((end.clone(), connection.get_galaxy_object(end.id)?)))
+            .collect::<Result<Vec<_>, tg_space_game::GalaxyError>>()
+    });
+    delete_sector(&connection, root.id).expect("Error deleting sector");
+
+    collapsed.expect("Error collapsing sector");
+    for result in results {
+        result.expect("Error fulfilling future");
+    }
+    // Every link leads to objects that exist and are of the linked type
+    for (end, object) in ends.expect("Error getting links") {
+        assert_eq!(object, Some(end));
+    }
+}
@@ -1,8 +1,16 @@
+extern crate chrono;
 extern crate diesel;
 extern crate dotenv;
 extern crate tg_space_game;
 
 mod galaxy_objects;
+mod movement;
+mod players;
+mod pregeneration;
+mod routing;
+mod sqlite;
+mod telegram;
+mod validation;
 
 use self::diesel::*;
 use self::dotenv::dotenv;
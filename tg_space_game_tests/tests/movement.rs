@@ -0,0 +1,93 @@
+use super::*;
+
+use chrono::{Duration, Utc};
+use tg_space_game::config::GenerationConfig;
+use tg_space_game::galaxy_objects::*;
+use tg_space_game::movement::*;
+use tg_space_game::players::*;
+use tg_space_game::store::GalaxyStore;
+
+/// Spawns a player in a new galaxy, returning the galaxy and player's location
+fn spawned_player(connection: &PgConnection) -> (StarSector, GalaxyObject) {
+    let sector = generate_star_sector(connection, 200f32, 100f32, None, 9, &GenerationConfig::default())
+        .expect("Error generating star sector");
+    register_player(connection, 42, "Tester").expect("Error registering player");
+    let system = spawn_player(connection, 42, sector.id).expect("Error spawning player");
+    (sector, GalaxyObject::from(&system))
+}
+
+#[test]
+fn jump_is_persisted_until_arrival() {
+    let connection = test_connection();
+    let (_, from) = spawned_player(&connection);
+    let target = get_neighbours(&connection, &from).unwrap()[0].clone();
+    let now = Utc::now().naive_utc();
+
+    let jump = start_jump(&connection, 42, target.id, now).expect("Error starting jump");
+    assert_eq!(get_player_jump(&connection, 42).unwrap(), Some(jump.clone()));
+    // Other tests may have ships in flight too
+    assert!(get_next_arrival(&connection).unwrap().unwrap() <= jump.arrives_at);
+
+    let halfway = now + (jump.arrives_at - now) / 2;
+    assert!(land_arrived_jumps(&connection, halfway).unwrap().is_empty());
+    assert_eq!(find_player(&connection, 42).unwrap().unwrap().location_id, Some(from.id));
+
+    let landed = land_arrived_jumps(&connection, jump.arrives_at + Duration::seconds(1)).unwrap();
+    assert_eq!(landed, vec![jump.clone()]);
+    assert_eq!(find_player(&connection, 42).unwrap().unwrap().location_id, Some(jump.to_id));
+    assert_eq!(get_player_jump(&connection, 42).unwrap(), None);
+}
+
+#[test]
+fn jump_time_depends_on_link_length() {
+    let connection = test_connection();
+    let (_, from) = spawned_player(&connection);
+    let target = get_neighbours(&connection, &from).unwrap()[0].clone();
+    let now = Utc::now().naive_utc();
+
+    let jump = start_jump(&connection, 42, target.id, now).expect("Error starting jump");
+
+    let destination = GalaxyObject {
+        id: jump.to_id,
+        obj_type: tg_space_game::schema::types::GalaxyObjectType::System,
+    };
+    let length = get_object_position(&connection, &from)
+        .unwrap()
+        .distance(&get_object_position(&connection, &destination).unwrap());
+    assert_eq!(jump.arrives_at - jump.departed_at, travel_time(length));
+}
+
+#[test]
+fn delete_sector_cancels_jumps() {
+    let connection = test_connection();
+    let (sector, from) = spawned_player(&connection);
+    let target = get_neighbours(&connection, &from).unwrap()[0].clone();
+    start_jump(&connection, 42, target.id, Utc::now().naive_utc()).expect("Error starting jump");
+
+    delete_sector(&connection, sector.id).expect("Error deleting sector");
+
+    assert_eq!(get_player_jump(&connection, 42).unwrap(), None);
+}
+
+#[test]
+fn players_in_sector_include_arriving_ones() {
+    let connection = test_connection();
+    let (sector, from) = spawned_player(&connection);
+    let target = get_neighbours(&connection, &from).unwrap()[0].clone();
+    let target = resolve_object(&connection, &target).expect("Error resolving target");
+    let target_sector = connection.get_systems(&[target.id]).unwrap()[0].sector_id;
+
+    assert_eq!(connection.get_players_in_sector(sector.id).unwrap().len(), 1);
+
+    start_jump(&connection, 42, target.id, Utc::now().naive_utc()).expect("Error starting jump");
+    {
+        // Only the jump leads to the target now
+        use tg_space_game::schema::players::dsl::*;
+        diesel::update(players.find(42))
+            .set(location_id.eq(None::<i32>))
+            .execute(&connection)
+            .expect("Error moving player");
+    }
+    let inside = connection.get_players_in_sector(target_sector).unwrap();
+    assert_eq!(inside.iter().map(|p| p.telegram_id).collect::<Vec<_>>(), vec![42]);
+}
@@ -0,0 +1,97 @@
+use super::*;
+
+use tg_space_game::config::GenerationConfig;
+use tg_space_game::galaxy_objects::*;
+use tg_space_game::players::*;
+use tg_space_game::GalaxyError;
+
+fn generate_galaxy(connection: &PgConnection) -> StarSector {
+    generate_star_sector(connection, 200f32, 100f32, None, 5, &GenerationConfig::default())
+        .expect("Error generating star sector")
+}
+
+#[test]
+fn register_player_saves_player() {
+    let connection = test_connection();
+    let player = register_player(&connection, 42, "Tester").expect("Error registering player");
+
+    let found = find_player(&connection, 42)
+        .expect("Error finding player")
+        .expect("Player wasn't saved");
+    assert_eq!(found.name, "Tester");
+    assert_eq!(found.created_at, player.created_at);
+    assert_eq!(found.location_id, None);
+}
+
+#[test]
+fn spawn_player_moves_player_to_star_system() {
+    let connection = test_connection();
+    let sector = generate_galaxy(&connection);
+    register_player(&connection, 42, "Tester").expect("Error registering player");
+
+    let system = spawn_player(&connection, 42, sector.id).expect("Error spawning player");
+
+    let player = find_player(&connection, 42).unwrap().unwrap();
+    assert_eq!(
+        get_player_location(&connection, &player).unwrap(),
+        Some(GalaxyObject::from(&system))
+    );
+}
+
+#[test]
+fn spawn_unregistered_player_fails() {
+    let connection = test_connection();
+    let sector = generate_galaxy(&connection);
+
+    match spawn_player(&connection, 42, sector.id) {
+        Err(GalaxyError::PlayerNotFound(42)) => {}
+        result => panic!("Unexpected result {:?}", result),
+    }
+}
+
+#[test]
+fn delete_sector_leaves_players_nowhere() {
+    let connection = test_connection();
+    let sector = generate_galaxy(&connection);
+    register_player(&connection, 42, "Tester").unwrap();
+    spawn_player(&connection, 42, sector.id).unwrap();
+
+    delete_sector(&connection, sector.id).expect("Error deleting sector");
+
+    let player = find_player(&connection, 42).unwrap().unwrap();
+    assert_eq!(player.location_id, None);
+}
+
+#[test]
+fn register_player_survives_concurrent_registrations() {
+    use std::sync::{Arc, Barrier};
+    use std::thread;
+    use tg_space_game::schema::players::dsl::*;
+
+    // Concurrent requests need committed data, so the player is deleted in the end
+    let player_id = 4_200_000_000 + i64::from(std::process::id());
+    let barrier = Arc::new(Barrier::new(4));
+    let requests = (0..4)
+        .map(|_| {
+            let barrier = barrier.clone();
+            thread::spawn(move || {
+                let own_connection = super::connection();
+                barrier.wait();
+                register_player(&own_connection, player_id, "Tester")
+            })
+        })
+        .collect::<Vec<_>>();
+    let results = requests
+        .into_iter()
+        .map(|r| r.join().expect("Request panicked"))
+        .collect::<Vec<_>>();
+    diesel::delete(players.find(player_id))
+        .execute(&connection())
+        .expect("Error deleting player");
+
+    let created = results
+        .into_iter()
+        .map(|r| r.expect("Error registering player").created_at)
+        .collect::<Vec<_>>();
+    assert!(created.iter().all(|c| *c == created[0]));
+}
@@ -0,0 +1,51 @@
+use super::*;
+
+use tg_space_game::config::GenerationConfig;
+use tg_space_game::galaxy_objects::*;
+use tg_space_game::players::*;
+use tg_space_game::pregeneration::*;
+use tg_space_game::schema::types::GalaxyObjectType;
+use tg_space_game::store::GalaxyStore;
+use tg_space_game::GalaxyResult;
+
+#[test]
+fn pregeneration_fulfills_futures_near_players() {
+    // Workers need committed data, so everything is deleted in the end
+    let connection = connection();
+    let sector = generate_star_sector(&connection, 2000f32, 100f32, None, 6, &GenerationConfig::default())
+        .expect("Error generating star sector");
+    register_player(&connection, -15, "Tester").expect("Error registering player");
+    spawn_player(&connection, -15, sector.id).expect("Error spawning player");
+
+    let options = PregenerationOptions {
+        workers: 3,
+        ..PregenerationOptions::default()
+    };
+    let jobs = find_pregeneration_jobs(&connection, &options);
+    // Futures next to the ones being fulfilled are left for the next rounds
+    let url = database_url_from_env("PG_DATABASE_URL");
+    let fulfilled = (0..10)
+        .map(|_| run_pregeneration(&url, &options))
+        .collect::<GalaxyResult<Vec<_>>>();
+    let objects = jobs.as_ref().ok().map(|jobs| {
+        jobs.iter()
+            .map(|job| connection.get_galaxy_object(job.future_id).unwrap())
+            .collect::<Vec<_>>()
+    });
+
+    {
+        use tg_space_game::schema::players::dsl::*;
+        diesel::delete(players.find(-15))
+            .execute(&connection)
+            .expect("Error deleting player");
+    }
+    delete_sector(&connection, sector.id).expect("Error deleting sector");
+
+    let jobs = jobs.expect("Error finding jobs");
+    assert!(!jobs.is_empty());
+    let fulfilled = fulfilled.expect("Error running pregeneration");
+    assert!(fulfilled[0] > 0);
+    for object in objects.unwrap() {
+        assert_eq!(object.unwrap().obj_type, GalaxyObjectType::Sector);
+    }
+}
@@ -0,0 +1,104 @@
+use super::*;
+
+use tg_space_game::config::GenerationConfig;
+use tg_space_game::galaxy_objects::*;
+use tg_space_game::schema::types::GalaxyObjectType;
+
+fn generate_galaxy(connection: &PgConnection) -> (StarSector, Vec<StarSectorFuture>) {
+    let sector = generate_star_sector(connection, 200f32, 1f32, None, 11, &GenerationConfig::default())
+        .expect("Error generating star sector");
+    let futures = get_star_sector_children_futures(connection, &sector)
+        .expect("Error loading star sector futures");
+    (sector, futures)
+}
+
+fn sector_systems(connection: &PgConnection, sector: &StarSector) -> Vec<GalaxyObject> {
+    use tg_space_game::schema::star_systems::dsl::*;
+    star_systems
+        .filter(sector_id.eq(sector.id))
+        .load::<StarSystem>(connection)
+        .expect("Error loading star systems")
+        .iter()
+        .map(GalaxyObject::from)
+        .collect()
+}
+
+fn assert_hops_linked(connection: &PgConnection, route: &Route) {
+    for pair in route.hops.windows(2) {
+        let links = get_links_for_object_ids(connection, vec![pair[0].id])
+            .expect("Error getting links");
+        assert!(links.iter().any(|l| {
+            (l.a_id == pair[0].id && l.b_id == pair[1].id)
+                || (l.a_id == pair[1].id && l.b_id == pair[0].id)
+        }));
+    }
+}
+
+#[test]
+fn find_route_between_futures_goes_over_links() {
+    let connection = test_connection();
+    let futures = generate_galaxy(&connection).1;
+    let from = GalaxyObject::from(&futures[0]);
+    let to = GalaxyObject::from(&futures[futures.len() - 1]);
+
+    let route = find_route(&connection, &from, &to)
+        .expect("Error finding route")
+        .expect("Futures of a sector are connected");
+
+    assert_eq!(route.hops[0], from);
+    assert_eq!(route.hops[route.hops.len() - 1], to);
+    assert_hops_linked(&connection, &route);
+}
+
+#[test]
+fn find_route_passes_through_futures_as_coarse_nodes() {
+    let connection = test_connection();
+    let futures = generate_galaxy(&connection).1;
+    let first = fulfill_star_sector_future(&connection, futures[0].id).unwrap();
+    let last = fulfill_star_sector_future(&connection, futures[futures.len() - 1].id).unwrap();
+    let from = sector_systems(&connection, &first)[0].clone();
+    let to = sector_systems(&connection, &last)[0].clone();
+
+    let route = find_route(&connection, &from, &to)
+        .expect("Error finding route")
+        .expect("Galaxy is connected");
+
+    assert_hops_linked(&connection, &route);
+}
+
+#[test]
+fn find_route_with_fulfill_futures_goes_through_systems_only() {
+    let connection = test_connection();
+    let futures = generate_galaxy(&connection).1;
+    let first = fulfill_star_sector_future(&connection, futures[0].id).unwrap();
+    let last = fulfill_star_sector_future(&connection, futures[futures.len() - 1].id).unwrap();
+    let from = sector_systems(&connection, &first)[0].clone();
+    let to = sector_systems(&connection, &last)[0].clone();
+
+    let options = RouteOptions {
+        fulfill_futures: true,
+    };
+    let route = find_route_with_options(&connection, &from, &to, &options)
+        .expect("Error finding route")
+        .expect("Galaxy is connected");
+
+    assert_hops_linked(&connection, &route);
+    assert!(route
+        .hops
+        .iter()
+        .all(|hop| hop.obj_type == GalaxyObjectType::System));
+}
+
+#[test]
+fn find_route_to_itself_has_zero_length() {
+    let connection = test_connection();
+    let futures = generate_galaxy(&connection).1;
+    let future = GalaxyObject::from(&futures[0]);
+
+    let route = find_route(&connection, &future, &future)
+        .expect("Error finding route")
+        .unwrap();
+
+    assert_eq!(route.hops, vec![future]);
+    assert_eq!(route.cost, 0f32);
+}
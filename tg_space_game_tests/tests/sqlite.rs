@@ -0,0 +1,165 @@
+use super::*;
+
+use tg_space_game::config::GenerationConfig;
+use tg_space_game::galaxy_objects::*;
+use tg_space_game::store::GalaxyStore;
+use tg_space_game::GalaxyConnection;
+
+fn sqlite_connection() -> GalaxyConnection {
+    let result = GalaxyConnection::establish("sqlite://:memory:").unwrap();
+    tg_space_game::run_migrations(&result, &mut std::io::sink()).unwrap();
+    result
+}
+
+fn generate_root<S: GalaxyStore>(connection: &S, stars: f32, seed: i64) -> StarSector {
+    generate_star_sector(connection, stars, 100f32, None, seed, &GenerationConfig::default())
+        .expect("Error generating star sector")
+}
+
+#[test]
+fn sqlite_generates_star_sector() {
+    let connection = sqlite_connection();
+    let sector = generate_root(&connection, 5f32, 1);
+
+    let systems = connection.get_child_systems(sector.id).unwrap();
+    assert_eq!(systems.len(), 5);
+    assert_eq!(connection.get_root_sectors().unwrap()[0].id, sector.id);
+}
+
+#[test]
+fn sqlite_fulfills_star_sector_future() {
+    let connection = sqlite_connection();
+    let sector = generate_root(&connection, 200f32, 2);
+    let future = get_star_sector_children_futures(&connection, &sector).unwrap()[0].clone();
+
+    let fulfilled = fulfill_star_sector_future(&connection, future.id).unwrap();
+
+    assert_eq!(fulfilled.id, future.id);
+    assert_eq!(fulfilled.config_id, sector.config_id);
+    assert!(!connection.get_child_systems(future.id).unwrap().is_empty());
+    assert!(connection.get_futures(&[future.id]).unwrap().is_empty());
+}
+
+fn future_layout<S: GalaxyStore>(store: &S, sector: &StarSector) -> Vec<(i64, f32, f32, f32)> {
+    store
+        .get_child_futures(sector.id)
+        .unwrap()
+        .iter()
+        .map(|f| (f.seed, f.x, f.y, f.z))
+        .collect()
+}
+
+#[test]
+fn sqlite_generates_same_galaxy_as_postgres() {
+    let sqlite = sqlite_connection();
+    let pg = test_connection();
+
+    let sqlite_sector = generate_root(&sqlite, 200f32, 3);
+    let pg_sector = generate_root(&pg, 200f32, 3);
+
+    assert_eq!(future_layout(&sqlite, &sqlite_sector), future_layout(&pg, &pg_sector));
+}
+
+#[test]
+fn sqlite_delete_sector_removes_everything() {
+    let connection = sqlite_connection();
+    let sector = generate_root(&connection, 200f32, 4);
+    let future = get_star_sector_children_futures(&connection, &sector).unwrap()[0].clone();
+    fulfill_star_sector_future(&connection, future.id).unwrap();
+
+    delete_sector(&connection, sector.id).unwrap();
+
+    assert!(connection.get_root_sectors().unwrap().is_empty());
+    assert!(connection.list_systems(1).unwrap().is_empty());
+}
+
+#[test]
+fn sqlite_counts_sector_stats() {
+    let connection = sqlite_connection();
+    let sector = generate_root(&connection, 200f32, 5);
+    let future = get_star_sector_children_futures(&connection, &sector).unwrap()[0].clone();
+    fulfill_star_sector_future(&connection, future.id).unwrap();
+
+    let stats = get_sector_stats(&connection, sector.id).unwrap();
+    assert_eq!(stats.sectors, 2);
+    assert_eq!(stats.futures, 9);
+    assert_eq!(stats.systems, connection.get_child_systems(future.id).unwrap().len());
+    assert_eq!(stats.links, connection.list_links().unwrap().len());
+}
+
+#[test]
+fn exported_galaxy_moves_from_postgres_to_sqlite() {
+    use tg_space_game::export::*;
+
+    let pg = test_connection();
+    let sqlite = sqlite_connection();
+    let sector = generate_root(&pg, 200f32, 6);
+    let future = get_star_sector_children_futures(&pg, &sector).unwrap()[0].clone();
+    fulfill_star_sector_future(&pg, future.id).unwrap();
+    let export = export_galaxy(&pg, sector.id).unwrap();
+
+    let document = GalaxyExport::from_json(&export.to_json()).unwrap();
+    let imported = import_galaxy(&sqlite, &document).unwrap();
+
+    let moved = export_galaxy(&sqlite, imported.id).unwrap();
+    assert_eq!(moved.config, export.config);
+    assert_eq!(moved.sectors.len(), 2);
+    assert_eq!(moved.futures.len(), export.futures.len());
+    assert_eq!(moved.links.len(), export.links.len());
+    let names = |e: &GalaxyExport| e.systems.iter().map(|s| s.name.clone()).collect::<Vec<_>>();
+    assert_eq!(names(&moved), names(&export));
+}
+
+#[test]
+fn establish_rejects_unknown_scheme() {
+    assert!(GalaxyConnection::establish("mysql://localhost/galaxy").is_err());
+}
+
+#[test]
+fn sqlite_spawns_player() {
+    use tg_space_game::players::*;
+
+    let connection = sqlite_connection();
+    let sector = generate_root(&connection, 5f32, 3);
+    register_player(&connection, 42, "Tester").unwrap();
+
+    let system = spawn_player(&connection, 42, sector.id).unwrap();
+    let player = find_player(&connection, 42).unwrap().unwrap();
+    assert_eq!(player.name, "Tester");
+    assert_eq!(player.location_id, Some(system.id));
+}
+
+#[test]
+fn sqlite_fulfills_linked_futures_concurrently() {
+    use std::thread;
+
+    let path = std::env::temp_dir().join(format!("tg_space_game_{}.sqlite", std::process::id()));
+    let url = format!("sqlite://{}", path.display());
+    let connection = GalaxyConnection::establish(&url).unwrap();
+    tg_space_game::run_migrations(&connection, &mut std::io::sink()).unwrap();
+    let sector = generate_root(&connection, 2000f32, 7);
+    let futures = get_star_sector_children_futures(&connection, &sector).unwrap();
+
+    let requests = futures
+        .iter()
+        .map(|future| {
+            let (url, future_id) = (url.clone(), future.id);
+            thread::spawn(move || {
+                let own_connection = GalaxyConnection::establish(&url).unwrap();
+                fulfill_star_sector_future(&own_connection, future_id)
+            })
+        })
+        .collect::<Vec<_>>();
+    let results = requests
+        .into_iter()
+        .map(|r| r.join().expect("Request panicked"))
+        .collect::<Vec<_>>();
+    let links = connection.get_links_for_objects(&futures.iter().map(|f| f.id).collect::<Vec<_>>());
+    let _ = std::fs::remove_file(&path);
+
+    for result in results {
+        result.expect("Error fulfilling future");
+    }
+    // Every future was fulfilled once, and nothing links to futures anymore
+    assert!(links.unwrap().is_empty());
+}
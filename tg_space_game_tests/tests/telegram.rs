@@ -0,0 +1,145 @@
+use super::*;
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use tg_space_game::config::GenerationConfig;
+use tg_space_game::galaxy_objects::generate_star_sector;
+use tg_space_game::telegram::{Bot, TelegramApi, TelegramError};
+
+/// Request received by the mock server: method name and JSON body
+type Request = (String, String);
+
+/// Serves Bot API requests on a random local port with `respond`, which
+/// gets the method name and returns HTTP status and JSON body. Returns
+/// base url of the server and the log of received requests.
+fn mock_telegram<F>(respond: F) -> (String, Arc<Mutex<Vec<Request>>>)
+where
+    F: Fn(&str) -> (u16, String) + Send + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").expect("Error binding mock server");
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let log = requests.clone();
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = match stream {
+                Ok(stream) => stream,
+                Err(_) => continue,
+            };
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            let mut content_length = 0;
+            loop {
+                let mut header = String::new();
+                reader.read_line(&mut header).unwrap();
+                if header.trim().is_empty() {
+                    break;
+                }
+                let lower = header.to_lowercase();
+                if let Some(value) = lower.strip_prefix("content-length:") {
+                    content_length = value.trim().parse().unwrap();
+                }
+            }
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).unwrap();
+
+            // Request line is "POST /bot<token>/<method> HTTP/1.1"
+            let path = request_line.split(' ').nth(1).unwrap_or("");
+            let method = path.rsplit('/').next().unwrap_or("").to_string();
+            let (status, response) = respond(&method);
+            log.lock()
+                .unwrap()
+                .push((method, String::from_utf8(body).unwrap()));
+            write!(
+                stream,
+                "HTTP/1.1 {} Mock\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                response.len(),
+                response
+            )
+            .unwrap();
+        }
+    });
+    (url, requests)
+}
+
+fn message_json(text: &str) -> String {
+    format!(
+        r#"{{"message_id":1,"from":{{"id":42,"first_name":"Tester"}},"chat":{{"id":42}},"text":"{}"}}"#,
+        text
+    )
+}
+
+#[test]
+fn bot_answers_start_through_telegram_api() {
+    let connection = test_connection();
+    generate_star_sector(&connection, 200f32, 100f32, None, 3, &GenerationConfig::default())
+        .expect("Error generating star sector");
+
+    let served = Mutex::new(false);
+    let (url, requests) = mock_telegram(move |method| match method {
+        "getUpdates" => {
+            let mut served = served.lock().unwrap();
+            if *served {
+                (200, r#"{"ok":true,"result":[]}"#.to_string())
+            } else {
+                *served = true;
+                let update = format!(r#"{{"update_id":7,"message":{}}}"#, message_json("/start"));
+                (200, format!(r#"{{"ok":true,"result":[{}]}}"#, update))
+            }
+        }
+        _ => (200, format!(r#"{{"ok":true,"result":{}}}"#, message_json("reply"))),
+    });
+
+    let mut bot = Bot::new(TelegramApi::new(&url, "token"), &connection, None);
+    assert_eq!(bot.poll().expect("Error polling"), 1);
+    assert_eq!(bot.poll().expect("Error polling"), 0);
+
+    let requests = requests.lock().unwrap();
+    let methods: Vec<&str> = requests.iter().map(|r| r.0.as_str()).collect();
+    assert_eq!(methods, vec!["getUpdates", "sendMessage", "getUpdates"]);
+    assert!(requests[1].1.contains(r#""chat_id":42"#));
+    assert!(requests[1].1.contains("Welcome, Tester!"));
+    // Second poll confirms the first update
+    assert!(requests[2].1.contains(r#""offset":8"#));
+}
+
+#[test]
+fn bot_reports_refused_requests() {
+    let connection = test_connection();
+    let (url, _) = mock_telegram(|_| {
+        (
+            401,
+            r#"{"ok":false,"error_code":401,"description":"Unauthorized"}"#.to_string(),
+        )
+    });
+
+    let mut bot = Bot::new(TelegramApi::new(&url, "wrong"), &connection, None);
+    match bot.poll() {
+        Err(TelegramError::Api(description)) => assert_eq!(description, "Unauthorized"),
+        result => panic!("Unexpected result {:?}", result),
+    }
+}
+
+#[test]
+fn transport_errors_dont_leak_the_token() {
+    // Nothing listens on the port once the listener is dropped
+    let url = {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("Error binding port");
+        format!("http://{}", listener.local_addr().unwrap())
+    };
+
+    let api = TelegramApi::new(&url, "123456:secret-token");
+    match api.get_updates(0, 0) {
+        Err(err @ TelegramError::Transport(_)) => {
+            let text = err.to_string();
+            assert!(text.contains("getUpdates"), "{}", text);
+            assert!(!text.contains("secret-token"), "{}", text);
+            assert!(!format!("{:?}", err).contains("secret-token"));
+        }
+        result => panic!("Unexpected result {:?}", result),
+    }
+}
@@ -0,0 +1,49 @@
+use super::*;
+
+use tg_space_game::config::GenerationConfig;
+use tg_space_game::galaxy_objects::*;
+use tg_space_game::schema::types::{GalaxyObjectType, LinkType};
+use tg_space_game::store::GalaxyStore;
+use tg_space_game::validation::*;
+
+#[test]
+fn validation_reports_and_repairs_corrupted_galaxy() {
+    use tg_space_game::schema::star_sectors::dsl::*;
+
+    let connection = test_connection();
+    let sector = generate_star_sector(&connection, 200f32, 100f32, None, 5, &GenerationConfig::default())
+        .expect("Error generating star sector");
+    let future = get_star_sector_children_futures(&connection, &sector).unwrap()[0].clone();
+    let child = fulfill_star_sector_future(&connection, future.id).unwrap();
+
+    let orphan = connection.create_galaxy_objects(&[GalaxyObjectType::System]).unwrap()[0].clone();
+    let system = GalaxyObject::from(&connection.get_child_systems(child.id).unwrap()[0]);
+    connection
+        .insert_links(&[NewStarLink::new(&system, &orphan, 1f32, LinkType::Hyperlane)])
+        .unwrap();
+    diesel::update(star_sectors.find(sector.id))
+        .set(parent_id.eq(child.id))
+        .execute(&connection)
+        .unwrap();
+
+    let violations = validate_galaxy(&connection).unwrap();
+    let cycle = Violation::ParentCycle {
+        sector_ids: vec![sector.id, child.id],
+    };
+    let orphaned = Violation::OrphanGalaxyObject {
+        object: orphan.clone(),
+        occupied: false,
+    };
+    assert!(violations.contains(&cycle));
+    assert!(violations.contains(&orphaned));
+    assert!(violations
+        .iter()
+        .any(|v| matches!(*v, Violation::DanglingLink { ref missing, .. } if *missing == orphan)));
+
+    let repaired = repair_galaxy(&connection).unwrap();
+    assert!(repaired.contains(&orphaned));
+    assert!(!repaired.contains(&cycle));
+    assert_eq!(connection.get_galaxy_object(orphan.id).unwrap(), None);
+    assert!(connection.get_links_for_objects(&[orphan.id]).unwrap().is_empty());
+    assert!(validate_galaxy(&connection).unwrap().contains(&cycle));
+}